@@ -0,0 +1,90 @@
+//! Incremental construction of a [`Graph`] from edges added one at a time.
+
+use crate::graph::Graph;
+
+/// Collects undirected edges and vertex weights in any order and turns them
+/// into a CSR [`Graph`].
+///
+/// The vertex count grows to cover every vertex mentioned, so it need not
+/// be known up front. Edges go through [`Graph::from_edges`] on
+/// [`GraphBuilder::finalize`]: each is stored in both directions, repeated
+/// pairs are merged by summing their weights, and self-loops are dropped.
+///
+/// ```
+/// use metis_rs::GraphBuilder;
+///
+/// let mut b = GraphBuilder::new();
+/// b.add_edge(2, 0, 1).add_edge(0, 1, 3).set_vertex_weight(1, 5);
+/// let g = b.finalize();
+/// assert_eq!(g.n, 3);
+/// assert_eq!(g.neighbors(0), &[1, 2]);
+/// assert_eq!(g.vwgt, vec![1, 5, 1]);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct GraphBuilder {
+    n: usize,
+    edges: Vec<(usize, usize, i64)>,
+    vwgt: Vec<i64>,
+}
+
+impl GraphBuilder {
+    /// An empty builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A builder for at least `n` vertices, which keeps isolated vertices
+    /// up to `n - 1` that no edge mentions.
+    pub fn with_vertices(n: usize) -> Self {
+        Self {
+            n,
+            ..Self::default()
+        }
+    }
+
+    /// Number of vertices so far: one more than the largest vertex
+    /// mentioned, or the count given to [`GraphBuilder::with_vertices`].
+    pub fn num_vertices(&self) -> usize {
+        self.n
+    }
+
+    /// Add the undirected edge `u - v` with weight `w`.
+    ///
+    /// Panics if `w` is negative.
+    pub fn add_edge(&mut self, u: usize, v: usize, w: i64) -> &mut Self {
+        assert!(w >= 0, "edge ({}, {}) has negative weight {}", u, v, w);
+        self.n = self.n.max(u.max(v) + 1);
+        self.edges.push((u, v, w));
+        self
+    }
+
+    /// Set the weight of vertex `u`; vertices never given one weigh 1.
+    ///
+    /// Panics if `w` is negative.
+    pub fn set_vertex_weight(&mut self, u: usize, w: i64) -> &mut Self {
+        assert!(w >= 0, "vertex {} has negative weight {}", u, w);
+        self.n = self.n.max(u + 1);
+        if self.vwgt.len() <= u {
+            self.vwgt.resize(u + 1, 1);
+        }
+        self.vwgt[u] = w;
+        self
+    }
+
+    /// Build the graph.
+    ///
+    /// Edge weights stay implicit (empty `adjwgt`) if every merged weight
+    /// is 1, and vertex weights if none was set.
+    pub fn finalize(self) -> Graph {
+        let mut g = Graph::from_edges(self.n, &self.edges);
+        if g.adjwgt.iter().all(|&w| w == 1) {
+            g.adjwgt = Vec::new();
+        }
+        if !self.vwgt.is_empty() {
+            let mut vwgt = self.vwgt;
+            vwgt.resize(self.n, 1);
+            g.vwgt = vwgt;
+        }
+        g
+    }
+}
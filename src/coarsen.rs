@@ -4,7 +4,37 @@
 //! by matching vertices along heavy edges until the graph is small enough
 //! for direct partitioning.
 
+use std::cmp::Reverse;
+
+use crate::config::PartitionConfig;
 use crate::graph::Graph;
+use crate::rng::Rng;
+
+/// How vertices are paired up on each coarsening level.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CoarsenScheme {
+    /// Heavy-edge matching: visit vertices in natural (or seeded random)
+    /// order and match each with its heaviest unmatched neighbor.
+    #[default]
+    HeavyEdge,
+    /// Sorted heavy-edge matching (SHEM), as in METIS: visit vertices in
+    /// increasing degree order, shuffling vertices of equal degree, and
+    /// break ties between equally heavy edges at random. Low-degree
+    /// vertices, which have the fewest candidates, pick first, so fewer are
+    /// left unmatched than in natural order.
+    SortedHeavyEdge,
+    /// Size-constrained label propagation: every vertex repeatedly joins the
+    /// neighboring cluster it is most strongly connected to, and each
+    /// cluster becomes one coarse vertex. Shrinks irregular graphs, such as
+    /// social networks, much faster than pairwise matching.
+    Cluster,
+    /// Global path algorithm (GPA): scan all edges heaviest first, keeping
+    /// those that extend vertex-disjoint paths, then take a maximum-weight
+    /// matching of each path by dynamic programming. Usually contracts more
+    /// edge weight than greedy matching, so the coarse graphs keep a
+    /// smaller cut, at the cost of sorting every edge on every level.
+    GlobalPath,
+}
 
 /// Result of a single coarsening level.
 #[derive(Clone, Debug)]
@@ -17,95 +47,926 @@ pub struct CoarsenLevel {
     pub nc: usize,
 }
 
+/// Coarsening hierarchy of a graph, as built by the multilevel pipeline.
+///
+/// Graph 0 is the input graph and graph `i` the result of `i` coarsening
+/// steps; level `i` ([`MultilevelHierarchy::levels`]) maps the vertices of
+/// graph `i` to those of graph `i + 1`.
+#[derive(Clone, Debug)]
+pub struct MultilevelHierarchy<'a> {
+    graph: &'a Graph,
+    levels: Vec<CoarsenLevel>,
+}
+
+impl<'a> MultilevelHierarchy<'a> {
+    /// Number of coarsening steps; graphs are numbered `0..=depth()`.
+    pub fn depth(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Graph after `level` coarsening steps; graph 0 is the input graph.
+    pub fn graph(&self, level: usize) -> &Graph {
+        match level {
+            0 => self.graph,
+            _ => &self.levels[level - 1].graph,
+        }
+    }
+
+    /// The smallest graph of the hierarchy.
+    pub fn coarsest(&self) -> &Graph {
+        self.graph(self.depth())
+    }
+
+    /// Coarsening steps, finest first.
+    pub fn levels(&self) -> &[CoarsenLevel] {
+        &self.levels
+    }
+
+    /// Map from the vertices of graph `level` to those of graph `level + 1`.
+    pub fn cmap(&self, level: usize) -> &[usize] {
+        &self.levels[level].cmap
+    }
+
+    /// Vertex count of every graph, finest first.
+    pub fn sizes(&self) -> Vec<usize> {
+        (0..=self.depth()).map(|i| self.graph(i).n).collect()
+    }
+
+    /// Project a partition of graph `level + 1` onto graph `level`, giving
+    /// each vertex the part of its coarse vertex.
+    pub fn project(&self, level: usize, coarse_part: &[usize]) -> Vec<usize> {
+        assert_eq!(
+            coarse_part.len(),
+            self.levels[level].nc,
+            "coarse_part must have one entry per coarse vertex"
+        );
+        self.cmap(level).iter().map(|&c| coarse_part[c]).collect()
+    }
+
+    /// The coarsening steps, finest first, without the input graph.
+    pub fn into_levels(self) -> Vec<CoarsenLevel> {
+        self.levels
+    }
+}
+
+/// Coarsen `g` as [`PartitionConfig::partition`] does under `opts`.
+///
+/// Uses the configured coarsening scheme, threshold (see
+/// [`PartitionConfig::coarsen_per_part`]), seed, pinned vertices and coarse
+/// vertex weight cap, and keeps at least `nparts` vertices on every level.
+/// Pair it with a custom initial partitioner or refiner through
+/// [`MultilevelHierarchy::project`].
+pub fn build_hierarchy<'a>(g: &'a Graph, opts: &PartitionConfig) -> MultilevelHierarchy<'a> {
+    opts.validate(g);
+    hierarchy(g, opts, opts.seed, &mut CoarsenScratch::default())
+}
+
+/// [`build_hierarchy`] with the seed given explicitly, as used by each
+/// trial of the pipeline, taking working arrays from `scratch`. `config`
+/// must already be validated against `g`.
+pub(crate) fn hierarchy<'a>(
+    g: &'a Graph,
+    config: &PartitionConfig,
+    seed: Option<u64>,
+    scratch: &mut CoarsenScratch,
+) -> MultilevelHierarchy<'a> {
+    let nparts = config.nparts.max(1);
+    let threshold = config
+        .coarsen_threshold
+        .max(config.coarsen_per_part.saturating_mul(nparts))
+        .max(nparts * 2);
+    let mut rng = seed.map(Rng::new);
+    let max_vwgt: Vec<i64> = (0..g.ncon())
+        .map(|c| {
+            let total: i64 = (0..g.n).map(|u| g.constraint_weight(u, c)).sum();
+            (config.max_coarse_weight * total as f64 / nparts as f64).ceil() as i64
+        })
+        .collect();
+    let levels = coarsen_levels(
+        g,
+        threshold,
+        config.coarsen_scheme,
+        rng.as_mut(),
+        &config.fixed,
+        nparts,
+        &max_vwgt,
+        scratch,
+    );
+    MultilevelHierarchy { graph: g, levels }
+}
+
 /// Coarsen the graph by heavy-edge matching.
 ///
-/// Visits vertices in random (deterministic) order, greedily matching each
-/// unmatched vertex with its heaviest unmatched neighbor.
+/// Visits vertices in natural order, greedily matching each unmatched vertex
+/// with its heaviest unmatched neighbor. When that leaves many vertices
+/// unmatched, those sharing a neighbor are matched with each other. See
+/// [`coarsen_once_seeded`] for a randomized visit order.
 pub fn coarsen_once(g: &Graph) -> CoarsenLevel {
+    coarsen_level(
+        g,
+        CoarsenScheme::HeavyEdge,
+        None,
+        &[],
+        0,
+        &[],
+        &mut CoarsenScratch::default(),
+    )
+}
+
+/// Coarsen the graph by heavy-edge matching, keeping at least `min_vertices`
+/// coarse vertices.
+///
+/// Matching stops once another merge would take the coarse graph below
+/// `min_vertices`, so partitioning the result into `nparts <= min_vertices`
+/// parts can still give every part a vertex.
+pub fn coarsen_once_bounded(g: &Graph, min_vertices: usize) -> CoarsenLevel {
+    coarsen_level(
+        g,
+        CoarsenScheme::HeavyEdge,
+        None,
+        &[],
+        min_vertices,
+        &[],
+        &mut CoarsenScratch::default(),
+    )
+}
+
+/// Coarsen the graph by heavy-edge matching without merging vertices pinned
+/// to different parts.
+///
+/// `fixed[u] == Some(k)` pins `u` to part `k`. Two vertices are only matched
+/// if at most one of them is pinned or both are pinned to the same part, so
+/// every coarse vertex has a well-defined label (see [`coarse_fixed`]).
+pub fn coarsen_once_fixed(g: &Graph, fixed: &[Option<usize>]) -> CoarsenLevel {
+    assert_eq!(fixed.len(), g.n, "fixed must have one entry per vertex");
+    coarsen_level(
+        g,
+        CoarsenScheme::HeavyEdge,
+        None,
+        fixed,
+        0,
+        &[],
+        &mut CoarsenScratch::default(),
+    )
+}
+
+/// Coarsen the graph by heavy-edge matching without creating coarse
+/// vertices heavier than `max_vertex_weight` on any constraint.
+///
+/// Merges that would exceed the cap are skipped, so a heavy region cannot
+/// snowball into a coarse vertex too heavy for any part. Vertices already
+/// over the cap stay unmatched.
+pub fn coarsen_once_capped(g: &Graph, max_vertex_weight: i64) -> CoarsenLevel {
+    coarsen_level(
+        g,
+        CoarsenScheme::HeavyEdge,
+        None,
+        &[],
+        0,
+        &vec![max_vertex_weight; g.ncon()],
+        &mut CoarsenScratch::default(),
+    )
+}
+
+/// Coarsen the graph by heavy-edge matching, visiting vertices in an order
+/// shuffled by `seed`.
+///
+/// The same seed always produces the same matching.
+pub fn coarsen_once_seeded(g: &Graph, seed: u64) -> CoarsenLevel {
+    coarsen_level(
+        g,
+        CoarsenScheme::HeavyEdge,
+        Some(&mut Rng::new(seed)),
+        &[],
+        0,
+        &[],
+        &mut CoarsenScratch::default(),
+    )
+}
+
+/// Coarsen the graph by sorted heavy-edge matching (see
+/// [`CoarsenScheme::SortedHeavyEdge`]), breaking ties with a generator
+/// seeded with `seed`.
+///
+/// The same seed always produces the same matching.
+pub fn coarsen_once_sorted(g: &Graph, seed: u64) -> CoarsenLevel {
+    coarsen_level(
+        g,
+        CoarsenScheme::SortedHeavyEdge,
+        Some(&mut Rng::new(seed)),
+        &[],
+        0,
+        &[],
+        &mut CoarsenScratch::default(),
+    )
+}
+
+/// Coarsen the graph by global path matching (see
+/// [`CoarsenScheme::GlobalPath`]).
+pub fn coarsen_once_global_path(g: &Graph) -> CoarsenLevel {
+    coarsen_level(
+        g,
+        CoarsenScheme::GlobalPath,
+        None,
+        &[],
+        0,
+        &[],
+        &mut CoarsenScratch::default(),
+    )
+}
+
+/// Coarsen the graph by contracting clusters found by size-constrained
+/// label propagation (see [`CoarsenScheme::Cluster`]), visiting vertices in
+/// natural order.
+pub fn coarsen_once_clustered(g: &Graph) -> CoarsenLevel {
+    coarsen_level(
+        g,
+        CoarsenScheme::Cluster,
+        None,
+        &[],
+        0,
+        &[],
+        &mut CoarsenScratch::default(),
+    )
+}
+
+/// Fixed labels of the coarse vertices of `level`, given those of its fine
+/// vertices.
+///
+/// A coarse vertex is pinned to `k` if any of its fine vertices is. Returns
+/// an empty vector when `fixed` is empty.
+pub fn coarse_fixed(fixed: &[Option<usize>], level: &CoarsenLevel) -> Vec<Option<usize>> {
+    if fixed.is_empty() {
+        return Vec::new();
+    }
+    let mut cfixed = vec![None; level.nc];
+    for (u, &f) in fixed.iter().enumerate() {
+        if f.is_some() {
+            cfixed[level.cmap[u]] = f;
+        }
+    }
+    cfixed
+}
+
+/// Per-vertex working arrays of coarsening, kept by a [`crate::Workspace`]
+/// so that repeated coarsening reuses their memory.
+#[derive(Debug, Default)]
+pub(crate) struct CoarsenScratch {
+    /// Order in which the matching visits vertices.
+    order: Vec<usize>,
+    /// Partner of each vertex in the matching being built.
+    mate: Vec<usize>,
+    visited: Vec<bool>,
+    /// Fine vertices of each coarse vertex, in CSR form with `mptr`.
+    members: Vec<usize>,
+    mptr: Vec<usize>,
+    fill: Vec<usize>,
+    /// Marker table of [`coarse_rows`] when rows are built serially.
+    #[cfg_attr(feature = "rayon", allow(dead_code))]
+    htable: Vec<usize>,
+}
+
+/// One level of matching under `scheme`, in natural order or shuffled by
+/// `rng`.
+///
+/// `fixed` is either empty or holds the pinned part of each vertex. No
+/// merge takes the coarse graph below `min_nc` vertices or creates a coarse
+/// vertex heavier than `max_vwgt`, which holds one cap per constraint or is
+/// empty for no cap. Sorted heavy-edge matching without `rng` breaks ties
+/// with a generator seeded with 0. Working arrays are taken from `scratch`.
+fn coarsen_level(
+    g: &Graph,
+    scheme: CoarsenScheme,
+    mut rng: Option<&mut Rng>,
+    fixed: &[Option<usize>],
+    min_nc: usize,
+    max_vwgt: &[i64],
+    scratch: &mut CoarsenScratch,
+) -> CoarsenLevel {
+    let max_merges = g.n.saturating_sub(min_nc);
+    scratch.order.clear();
+    scratch.order.extend(0..g.n);
+    let (cmap, nc) = match scheme {
+        CoarsenScheme::HeavyEdge => {
+            if let Some(rng) = rng {
+                rng.shuffle(&mut scratch.order);
+            }
+            heavy_edge_matching(g, scratch, max_merges, fixed, true, None, max_vwgt)
+        }
+        CoarsenScheme::SortedHeavyEdge => {
+            let mut fallback = Rng::new(0);
+            let rng = rng.unwrap_or(&mut fallback);
+            rng.shuffle(&mut scratch.order);
+            // Stable, so equal degrees keep their shuffled order
+            scratch.order.sort_by_key(|&u| g.degree(u));
+            heavy_edge_matching(g, scratch, max_merges, fixed, true, Some(rng), max_vwgt)
+        }
+        CoarsenScheme::Cluster => {
+            if let Some(rng) = rng {
+                rng.shuffle(&mut scratch.order);
+            }
+            label_propagation_clustering(g, &scratch.order, min_nc, fixed, max_vwgt)
+        }
+        CoarsenScheme::GlobalPath => {
+            if let Some(rng) = rng.as_deref_mut() {
+                rng.shuffle(&mut scratch.order);
+            }
+            path_matching(g, &scratch.order, max_merges, fixed, rng, max_vwgt)
+        }
+    };
+
+    // Build coarsened graph
+    let graph = build_coarse_graph(g, &cmap, nc, scratch);
+
+    CoarsenLevel { graph, cmap, nc }
+}
+
+/// Coarsen the graph towards exactly `target` coarse vertices.
+///
+/// Heavy-edge matching stops as soon as enough pairs have been merged. If
+/// matching alone cannot shrink the graph far enough, remaining unmatched
+/// vertices are absorbed into the coarse vertex of their heaviest neighbor.
+/// This is best effort: when neither step can reach `target` (e.g. no
+/// unmatched vertices are left), the result has more than `target` vertices.
+pub fn coarsen_to_size(g: &Graph, target: usize) -> CoarsenLevel {
+    let target = target.max(1);
+    let mut scratch = CoarsenScratch::default();
+    scratch.order.extend(0..g.n);
+    let (mut cmap, mut nc) = heavy_edge_matching(
+        g,
+        &mut scratch,
+        g.n.saturating_sub(target),
+        &[],
+        false,
+        None,
+        &[],
+    );
+
+    if nc > target {
+        let mut size = vec![0usize; nc];
+        for u in 0..g.n {
+            size[cmap[u]] += 1;
+        }
+        let mut remaining = nc;
+        for u in 0..g.n {
+            if remaining <= target {
+                break;
+            }
+            if size[cmap[u]] != 1 {
+                continue;
+            }
+            let mut best_c = None;
+            let mut best_w = -1i64;
+            for (v, w) in g.edges(u) {
+                if cmap[v] != cmap[u] && w > best_w {
+                    best_w = w;
+                    best_c = Some(cmap[v]);
+                }
+            }
+            if let Some(c) = best_c {
+                size[cmap[u]] = 0;
+                size[c] += 1;
+                cmap[u] = c;
+                remaining -= 1;
+            }
+        }
+
+        // Renumber the surviving coarse vertices contiguously
+        let mut renumber = vec![usize::MAX; nc];
+        nc = 0;
+        for c in cmap.iter_mut() {
+            if renumber[*c] == usize::MAX {
+                renumber[*c] = nc;
+                nc += 1;
+            }
+            *c = renumber[*c];
+        }
+    }
+
+    let graph = build_coarse_graph(g, &cmap, nc, &mut scratch);
+    CoarsenLevel { graph, cmap, nc }
+}
+
+/// Fraction of vertices that must remain unmatched after heavy-edge
+/// matching before the two-hop fallback runs.
+const UNMATCHED_FOR_TWO_HOP: f64 = 0.10;
+
+/// Greedy heavy-edge matching visiting vertices in `scratch.order`, merging
+/// at most `max_merges` pairs.
+///
+/// With `two_hop` set, if more than 10% of the vertices are left unmatched
+/// (typical of star-like and power-law graphs, where hubs take all the
+/// matches), a second round pairs up the leftovers that share a neighbor;
+/// see [`two_hop_matching`].
+///
+/// `fixed` is either empty or holds the pinned part of each vertex; vertices
+/// pinned to different parts are never matched. Ties between equally heavy
+/// edges go to the first neighbor, or to a uniformly random one with `rng`.
+/// No pair heavier than `max_vwgt` in total is matched. Returns `(cmap, nc)`,
+/// with coarse vertices numbered in `order` of their first fine vertex.
+fn heavy_edge_matching(
+    g: &Graph,
+    scratch: &mut CoarsenScratch,
+    max_merges: usize,
+    fixed: &[Option<usize>],
+    two_hop: bool,
+    mut rng: Option<&mut Rng>,
+    max_vwgt: &[i64],
+) -> (Vec<usize>, usize) {
     let n = g.n;
-    let mut matched = vec![false; n];
-    let mut cmap = vec![0usize; n];
-    let mut nc = 0usize;
+    let CoarsenScratch {
+        order,
+        mate,
+        visited,
+        ..
+    } = scratch;
+    let order = &*order;
+    // mate[u] == u marks an unmatched vertex
+    mate.clear();
+    mate.extend(0..n);
+    visited.clear();
+    visited.resize(n, false);
+    let mut merges = 0usize;
+    // Favorite neighbors only help ties that need no random draws
+    #[cfg(feature = "rayon")]
+    let favorite = match rng {
+        Some(_) => Vec::new(),
+        None => favorite_neighbors(g, fixed, max_vwgt),
+    };
+    #[cfg(not(feature = "rayon"))]
+    let favorite: Vec<Option<usize>> = Vec::new();
 
-    // Visit in natural order (deterministic; could shuffle for randomization)
-    for u in 0..n {
-        if matched[u] {
+    for &u in order {
+        if visited[u] {
             continue;
         }
+        visited[u] = true;
 
         // Find heaviest unmatched neighbor
         let mut best_v = None;
         let mut best_w = -1i64;
-        for k in 0..g.degree(u) {
-            let v = g.adjncy[g.xadj[u] + k];
-            if !matched[v] && v != u {
-                let w = g.edge_weight(u, k);
-                if w > best_w {
-                    best_w = w;
-                    best_v = Some(v);
+        // Neighbors seen so far with weight best_w, for reservoir sampling
+        let mut ties = 0usize;
+        if merges < max_merges {
+            match favorite.get(u) {
+                Some(&Some(v)) if !visited[v] => best_v = Some(v),
+                Some(None) => {}
+                _ => {
+                    for (v, w) in g.edges(u) {
+                        if !visited[v]
+                            && v != u
+                            && compatible(fixed, u, v)
+                            && fits(g, u, v, max_vwgt)
+                        {
+                            if w > best_w {
+                                best_w = w;
+                                best_v = Some(v);
+                                ties = 1;
+                            } else if w == best_w {
+                                if let Some(rng) = rng.as_deref_mut() {
+                                    ties += 1;
+                                    if rng.below(ties) == 0 {
+                                        best_v = Some(v);
+                                    }
+                                }
+                            }
+                        }
+                    }
                 }
             }
         }
 
         if let Some(v) = best_v {
-            // Match u and v into coarse vertex nc
-            cmap[u] = nc;
-            cmap[v] = nc;
-            matched[u] = true;
-            matched[v] = true;
-        } else {
-            // Unmatched singleton
+            mate[u] = v;
+            mate[v] = u;
+            visited[v] = true;
+            merges += 1;
+        }
+    }
+
+    let unmatched = n - 2 * merges;
+    if two_hop && merges < max_merges && unmatched as f64 > UNMATCHED_FOR_TWO_HOP * n as f64 {
+        two_hop_matching(g, order, mate, max_merges - merges, fixed, max_vwgt);
+    }
+
+    number_matching(order, mate)
+}
+
+/// Heaviest neighbor of every vertex that [`heavy_edge_matching`] could
+/// match it with, ignoring which vertices are already matched; ties go to
+/// the first neighbor.
+///
+/// While a vertex's favorite is unmatched, it is also its heaviest
+/// unmatched neighbor, so the matching only rescans the neighbors of
+/// vertices whose favorite was taken and matches exactly as a full scan
+/// would. Only the rescans are sequential.
+#[cfg(feature = "rayon")]
+fn favorite_neighbors(g: &Graph, fixed: &[Option<usize>], max_vwgt: &[i64]) -> Vec<Option<usize>> {
+    use rayon::prelude::*;
+    (0..g.n)
+        .into_par_iter()
+        .map(|u| {
+            let mut best = None;
+            let mut best_w = -1i64;
+            for (v, w) in g.edges(u) {
+                if w > best_w && v != u && compatible(fixed, u, v) && fits(g, u, v, max_vwgt) {
+                    best_w = w;
+                    best = Some(v);
+                }
+            }
+            best
+        })
+        .collect()
+}
+
+/// Coarse vertex map of the matching `mate` (`mate[u] == u` for unmatched
+/// vertices), numbering pairs in `order` of their first vertex.
+fn number_matching(order: &[usize], mate: &[usize]) -> (Vec<usize>, usize) {
+    let mut cmap = vec![usize::MAX; mate.len()];
+    let mut nc = 0usize;
+    for &u in order {
+        if cmap[u] == usize::MAX {
             cmap[u] = nc;
-            matched[u] = true;
+            cmap[mate[u]] = nc;
+            nc += 1;
         }
-        nc += 1;
     }
+    (cmap, nc)
+}
 
-    // Build coarsened graph
-    let graph = build_coarse_graph(g, &cmap, nc);
+/// Global path matching (see [`CoarsenScheme::GlobalPath`]), merging at
+/// most `max_merges` pairs.
+///
+/// Edges are scanned heaviest first, ties in random order with `rng` and
+/// in index order otherwise. An edge is kept if both endpoints have fewer
+/// than two kept edges and it closes no cycle, so the kept edges form
+/// vertex-disjoint paths. Each path, walked from its endpoint first in
+/// `order`, is then matched optimally by dynamic programming. Pins and
+/// `max_vwgt` are respected as in [`heavy_edge_matching`], including the
+/// two-hop fallback.
+fn path_matching(
+    g: &Graph,
+    order: &[usize],
+    max_merges: usize,
+    fixed: &[Option<usize>],
+    rng: Option<&mut Rng>,
+    max_vwgt: &[i64],
+) -> (Vec<usize>, usize) {
+    let n = g.n;
+    let mut edges: Vec<(i64, usize, usize)> = Vec::new();
+    for u in 0..n {
+        for (v, w) in g.edges(u) {
+            if u < v && compatible(fixed, u, v) && fits(g, u, v, max_vwgt) {
+                edges.push((w, u, v));
+            }
+        }
+    }
+    if let Some(rng) = rng {
+        rng.shuffle(&mut edges);
+    }
+    // Stable, so equal weights keep their (shuffled) order
+    edges.sort_by_key(|&(w, _, _)| Reverse(w));
 
-    CoarsenLevel { graph, cmap, nc }
+    // links[u] holds u's kept edges as (neighbor, weight)
+    let mut links: Vec<Vec<(usize, i64)>> = vec![Vec::new(); n];
+    let mut root: Vec<usize> = (0..n).collect();
+    let find = |root: &mut [usize], mut u: usize| {
+        while root[u] != u {
+            root[u] = root[root[u]];
+            u = root[u];
+        }
+        u
+    };
+    for (w, u, v) in edges {
+        if links[u].len() < 2 && links[v].len() < 2 {
+            let (ru, rv) = (find(&mut root, u), find(&mut root, v));
+            if ru != rv {
+                root[ru] = rv;
+                links[u].push((v, w));
+                links[v].push((u, w));
+            }
+        }
+    }
+
+    let mut mate: Vec<usize> = (0..n).collect();
+    let mut merges = 0usize;
+    let mut walked = vec![false; n];
+    let mut path = Vec::new();
+    let mut weights = Vec::new();
+    let mut best = Vec::new();
+    for &start in order {
+        if walked[start] || links[start].len() != 1 {
+            continue;
+        }
+        path.clear();
+        weights.clear();
+        path.push(start);
+        walked[start] = true;
+        let mut prev = usize::MAX;
+        let mut cur = start;
+        while let Some(&(next, w)) = links[cur].iter().find(|&&(v, _)| v != prev) {
+            path.push(next);
+            weights.push(w);
+            walked[next] = true;
+            prev = cur;
+            cur = next;
+        }
+
+        // best[i] is the heaviest matching of the first i path vertices
+        best.clear();
+        best.resize(path.len() + 1, 0i64);
+        for i in 2..=path.len() {
+            best[i] = best[i - 1].max(best[i - 2] + weights[i - 2]);
+        }
+        let mut i = path.len();
+        while i >= 2 {
+            if merges < max_merges && best[i - 2] + weights[i - 2] >= best[i - 1] {
+                let (u, v) = (path[i - 2], path[i - 1]);
+                mate[u] = v;
+                mate[v] = u;
+                merges += 1;
+                i -= 2;
+            } else {
+                i -= 1;
+            }
+        }
+    }
+
+    let unmatched = n - 2 * merges;
+    if merges < max_merges && unmatched as f64 > UNMATCHED_FOR_TWO_HOP * n as f64 {
+        two_hop_matching(g, order, &mut mate, max_merges - merges, fixed, max_vwgt);
+    }
+
+    number_matching(order, &mate)
 }
 
-/// Build the coarsened graph from the fine graph and vertex mapping.
-fn build_coarse_graph(g: &Graph, cmap: &[usize], nc: usize) -> Graph {
-    use std::collections::HashMap;
+/// Label propagation sweeps made by [`CoarsenScheme::Cluster`] per level.
+const CLUSTER_ROUNDS: usize = 5;
+
+/// Heaviest cluster [`CoarsenScheme::Cluster`] builds on each constraint, in
+/// multiples of the average vertex weight on that constraint (but never
+/// below the heaviest vertex).
+const MAX_CLUSTER_GROWTH: i64 = 8;
+
+/// Cluster vertices by size-constrained label propagation, visiting them in
+/// `order`, and keep at least `min_nc` clusters, none heavier than
+/// `max_vwgt` unless it is a single vertex.
+///
+/// Every vertex starts in its own cluster. Each sweep moves a vertex to the
+/// neighboring cluster with the heaviest connection to it, if that beats
+/// its own cluster and keeps the cluster within its cap on every
+/// constraint; ties go to the first such neighbor. Sweeps stop after
+/// [`CLUSTER_ROUNDS`] or once nothing moves. Pinned vertices never move and
+/// other vertices only join clusters compatible with their pin, so each
+/// cluster holds at most one pinned label. Returns `(cmap, nc)`, with
+/// clusters numbered in `order` of their first fine vertex.
+fn label_propagation_clustering(
+    g: &Graph,
+    order: &[usize],
+    min_nc: usize,
+    fixed: &[Option<usize>],
+    max_vwgt: &[i64],
+) -> (Vec<usize>, usize) {
+    let n = g.n;
+    let ncon = g.ncon();
+    let cap: Vec<i64> = (0..ncon)
+        .map(|k| {
+            let total: i64 = (0..n).map(|u| g.constraint_weight(u, k)).sum();
+            let heaviest = (0..n).map(|u| g.constraint_weight(u, k)).max().unwrap_or(0);
+            heaviest
+                .max(MAX_CLUSTER_GROWTH.saturating_mul(total) / n.max(1) as i64)
+                .min(max_vwgt.get(k).copied().unwrap_or(i64::MAX))
+        })
+        .collect();
+    let pinned = |u: usize| !fixed.is_empty() && fixed[u].is_some();
+
+    // Cluster c is named after the vertex that founded it; pinned vertices
+    // never leave their own, so a cluster's pin is its founder's.
+    // weight[c * ncon + k] is the weight of cluster c on constraint k.
+    let mut label: Vec<usize> = (0..n).collect();
+    let mut weight: Vec<i64> = (0..n)
+        .flat_map(|u| (0..ncon).map(move |k| g.constraint_weight(u, k)))
+        .collect();
+    let mut size = vec![1usize; n];
+    let mut clusters = n;
+    let mut conn = vec![0i64; n];
+    let mut touched = Vec::new();
+
+    for _ in 0..CLUSTER_ROUNDS {
+        let mut moved = false;
+        for &u in order {
+            if pinned(u) {
+                continue;
+            }
+            let own = label[u];
+            if size[own] == 1 && clusters <= min_nc {
+                continue;
+            }
+            for (v, w) in g.edges(u) {
+                let c = label[v];
+                if conn[c] == 0 {
+                    touched.push(c);
+                }
+                conn[c] += w;
+            }
+            let mut best = own;
+            let mut best_conn = conn[own];
+            for &c in &touched {
+                if c != own
+                    && conn[c] > best_conn
+                    && (0..ncon).all(|k| weight[c * ncon + k] + g.constraint_weight(u, k) <= cap[k])
+                    && compatible(fixed, c, u)
+                {
+                    best = c;
+                    best_conn = conn[c];
+                }
+            }
+            for c in touched.drain(..) {
+                conn[c] = 0;
+            }
+            if best != own {
+                for k in 0..ncon {
+                    let w = g.constraint_weight(u, k);
+                    weight[own * ncon + k] -= w;
+                    weight[best * ncon + k] += w;
+                }
+                size[own] -= 1;
+                if size[own] == 0 {
+                    clusters -= 1;
+                }
+                size[best] += 1;
+                label[u] = best;
+                moved = true;
+            }
+        }
+        if !moved {
+            break;
+        }
+    }
+
+    let mut cmap = vec![usize::MAX; n];
+    let mut renumber = vec![usize::MAX; n];
+    let mut nc = 0usize;
+    for &u in order {
+        if renumber[label[u]] == usize::MAX {
+            renumber[label[u]] = nc;
+            nc += 1;
+        }
+        cmap[u] = renumber[label[u]];
+    }
+    (cmap, nc)
+}
+
+/// Pair up unmatched vertices that share a neighbor, merging at most
+/// `max_merges` pairs, none heavier than `max_vwgt` in total.
+///
+/// Each vertex of `order` acts as a pivot whose unmatched neighbors are
+/// matched with each other, two at a time. This covers both leaves hanging
+/// off the same hub and vertices with identical adjacency. Runs in
+/// `O(n + m)`.
+fn two_hop_matching(
+    g: &Graph,
+    order: &[usize],
+    mate: &mut [usize],
+    max_merges: usize,
+    fixed: &[Option<usize>],
+    max_vwgt: &[i64],
+) {
+    let mut merges = 0usize;
+    for &pivot in order {
+        let mut pending: Option<usize> = None;
+        for &v in g.neighbors(pivot) {
+            if merges == max_merges {
+                return;
+            }
+            if mate[v] != v || v == pivot {
+                continue;
+            }
+            match pending {
+                Some(u) if u != v && compatible(fixed, u, v) && fits(g, u, v, max_vwgt) => {
+                    mate[u] = v;
+                    mate[v] = u;
+                    merges += 1;
+                    pending = None;
+                }
+                Some(_) => {}
+                None => pending = Some(v),
+            }
+        }
+    }
+}
+
+/// Whether `u` and `v` may share a coarse vertex under the pins in `fixed`.
+fn compatible(fixed: &[Option<usize>], u: usize, v: usize) -> bool {
+    if fixed.is_empty() {
+        return true;
+    }
+    match (fixed[u], fixed[v]) {
+        (Some(a), Some(b)) => a == b,
+        _ => true,
+    }
+}
+
+/// Whether merging `u` and `v` keeps the coarse vertex within `max_vwgt`,
+/// which holds the cap of each constraint or is empty for no cap.
+fn fits(g: &Graph, u: usize, v: usize, max_vwgt: &[i64]) -> bool {
+    max_vwgt.iter().enumerate().all(|(c, &cap)| {
+        g.constraint_weight(u, c)
+            .saturating_add(g.constraint_weight(v, c))
+            <= cap
+    })
+}
 
+/// Build the coarsened graph from the fine graph and vertex mapping.
+///
+/// Merged vertex and edge weights saturate at `i64::MAX` rather than wrap.
+fn build_coarse_graph(g: &Graph, cmap: &[usize], nc: usize, scratch: &mut CoarsenScratch) -> Graph {
     // Accumulate coarse vertex weights
     let mut cvwgt = vec![0i64; nc];
     for u in 0..g.n {
-        cvwgt[cmap[u]] += g.vertex_weight(u);
+        cvwgt[cmap[u]] = cvwgt[cmap[u]].saturating_add(g.vertex_weight(u));
+    }
+    let mut cvwgt_multi = Vec::new();
+    if !g.vwgt_multi.is_empty() {
+        cvwgt_multi = vec![vec![0i64; g.ncon()]; nc];
+        for u in 0..g.n {
+            for (c, &w) in g.vwgt_multi[u].iter().enumerate() {
+                let cw = &mut cvwgt_multi[cmap[u]][c];
+                *cw = cw.saturating_add(w);
+            }
+        }
     }
 
-    // Accumulate coarse edges
-    // For each coarse vertex cu, collect neighbors with accumulated weights
-    let mut adj_map: Vec<HashMap<usize, i64>> = vec![HashMap::new(); nc];
+    let cvsize = if g.vsize.is_empty() {
+        Vec::new()
+    } else {
+        let mut cvsize = vec![0i64; nc];
+        for u in 0..g.n {
+            cvsize[cmap[u]] = cvsize[cmap[u]].saturating_add(g.vsize[u]);
+        }
+        cvsize
+    };
 
+    // Fine vertices of each coarse vertex, in CSR form
+    let CoarsenScratch {
+        members,
+        mptr,
+        fill,
+        ..
+    } = scratch;
+    mptr.clear();
+    mptr.resize(nc + 1, 0);
+    for &c in cmap {
+        mptr[c + 1] += 1;
+    }
+    for c in 0..nc {
+        mptr[c + 1] += mptr[c];
+    }
+    members.clear();
+    members.resize(g.n, 0);
+    fill.clear();
+    fill.extend_from_slice(mptr);
     for u in 0..g.n {
-        let cu = cmap[u];
-        for k in 0..g.degree(u) {
-            let v = g.adjncy[g.xadj[u] + k];
-            let cv = cmap[v];
-            if cu != cv {
-                let w = g.edge_weight(u, k);
-                *adj_map[cu].entry(cv).or_insert(0) += w;
-            }
-        }
+        members[fill[cmap[u]]] = u;
+        fill[cmap[u]] += 1;
     }
+    let (members, mptr) = (&*members, &*mptr);
 
-    // Convert to CSR
-    let mut xadj = vec![0usize; nc + 1];
-    let mut adjncy = Vec::new();
-    let mut adjwgt = Vec::new();
+    // Build the rows in blocks of consecutive coarse vertices. Blocks only
+    // read `g`, so they can be built in parallel, each thread reusing one
+    // marker table.
+    let blocks = nc.div_ceil(ROW_BLOCK);
+    let block_rows = |htable: &mut Vec<usize>, b: usize| {
+        let rows = b * ROW_BLOCK..nc.min((b + 1) * ROW_BLOCK);
+        coarse_rows(g, cmap, members, mptr, rows, htable)
+    };
+    #[cfg(feature = "rayon")]
+    let built: Vec<RowBlock> = {
+        use rayon::prelude::*;
+        (0..blocks)
+            .into_par_iter()
+            .map_init(|| vec![usize::MAX; nc], block_rows)
+            .collect()
+    };
+    #[cfg(not(feature = "rayon"))]
+    let built: Vec<RowBlock> = {
+        // Entries are back at `usize::MAX` after every call
+        let htable = &mut scratch.htable;
+        htable.resize(htable.len().max(nc), usize::MAX);
+        (0..blocks).map(|b| block_rows(htable, b)).collect()
+    };
 
-    for cu in 0..nc {
-        let mut neighbors: Vec<(usize, i64)> = adj_map[cu].drain().collect();
-        neighbors.sort_by_key(|&(v, _)| v);
-        for (v, w) in neighbors {
-            adjncy.push(v);
-            adjwgt.push(w);
+    // Concatenate the blocks into CSR
+    let mut xadj = Vec::with_capacity(nc + 1);
+    xadj.push(0);
+    let mut adjncy = Vec::with_capacity(built.iter().map(|b| b.adjncy.len()).sum());
+    let mut adjwgt = Vec::with_capacity(adjncy.capacity());
+    for block in built {
+        for len in block.lengths {
+            xadj.push(xadj.last().unwrap() + len);
         }
-        xadj[cu + 1] = adjncy.len();
+        adjncy.extend(block.adjncy);
+        adjwgt.extend(block.adjwgt);
     }
 
     Graph {
@@ -114,23 +975,213 @@ fn build_coarse_graph(g: &Graph, cmap: &[usize], nc: usize) -> Graph {
         adjncy,
         adjwgt,
         vwgt: cvwgt,
+        vwgt_multi: cvwgt_multi,
+        vsize: cvsize,
     }
 }
 
+/// Coarse vertices per block of rows built by [`coarse_rows`].
+const ROW_BLOCK: usize = 1024;
+
+/// Edges of a block of consecutive coarse vertices, in CSR order.
+struct RowBlock {
+    /// Number of neighbors of each coarse vertex of the block.
+    lengths: Vec<usize>,
+    adjncy: Vec<usize>,
+    adjwgt: Vec<i64>,
+}
+
+/// Merge the edges of each coarse vertex in `rows`, sorted by neighbor.
+///
+/// `members[mptr[c]..mptr[c + 1]]` are the fine vertices of coarse vertex
+/// `c`. Duplicate coarse edges are combined through `htable`, which maps a
+/// coarse neighbor to its slot in the current row; it must hold
+/// `usize::MAX` for every coarse vertex and is left that way, so one table
+/// serves any number of calls without reallocation.
+fn coarse_rows(
+    g: &Graph,
+    cmap: &[usize],
+    members: &[usize],
+    mptr: &[usize],
+    rows: std::ops::Range<usize>,
+    htable: &mut [usize],
+) -> RowBlock {
+    let mut block = RowBlock {
+        lengths: Vec::with_capacity(rows.len()),
+        adjncy: Vec::new(),
+        adjwgt: Vec::new(),
+    };
+    let mut row: Vec<(usize, i64)> = Vec::new();
+    for cu in rows {
+        for &u in &members[mptr[cu]..mptr[cu + 1]] {
+            for (v, w) in g.edges(u) {
+                let cv = cmap[v];
+                if cv == cu {
+                    continue;
+                }
+                match htable[cv] {
+                    usize::MAX => {
+                        htable[cv] = row.len();
+                        row.push((cv, w));
+                    }
+                    slot => row[slot].1 = row[slot].1.saturating_add(w),
+                }
+            }
+        }
+        row.sort_unstable_by_key(|&(v, _)| v);
+        block.lengths.push(row.len());
+        for (v, w) in row.drain(..) {
+            htable[v] = usize::MAX;
+            block.adjncy.push(v);
+            block.adjwgt.push(w);
+        }
+    }
+    block
+}
+
 /// Coarsen the graph repeatedly until it has fewer than `threshold` vertices.
 ///
 /// Returns a stack of coarsening levels (finest to coarsest).
 pub fn multilevel_coarsen(g: &Graph, threshold: usize) -> Vec<CoarsenLevel> {
-    let mut levels = Vec::new();
-    let mut current = g.clone();
+    coarsen_levels(
+        g,
+        threshold,
+        CoarsenScheme::HeavyEdge,
+        None,
+        &[],
+        0,
+        &[],
+        &mut CoarsenScratch::default(),
+    )
+}
+
+/// Like [`multilevel_coarsen`], but every level visits vertices in an order
+/// shuffled by a generator seeded with `seed`.
+///
+/// The same seed always produces the same hierarchy.
+pub fn multilevel_coarsen_seeded(g: &Graph, threshold: usize, seed: u64) -> Vec<CoarsenLevel> {
+    coarsen_levels(
+        g,
+        threshold,
+        CoarsenScheme::HeavyEdge,
+        Some(&mut Rng::new(seed)),
+        &[],
+        0,
+        &[],
+        &mut CoarsenScratch::default(),
+    )
+}
+
+/// Like [`multilevel_coarsen`], but every level uses sorted heavy-edge
+/// matching (see [`coarsen_once_sorted`]) with ties broken by a generator
+/// seeded with `seed`.
+pub fn multilevel_coarsen_sorted(g: &Graph, threshold: usize, seed: u64) -> Vec<CoarsenLevel> {
+    coarsen_levels(
+        g,
+        threshold,
+        CoarsenScheme::SortedHeavyEdge,
+        Some(&mut Rng::new(seed)),
+        &[],
+        0,
+        &[],
+        &mut CoarsenScratch::default(),
+    )
+}
+
+/// Like [`multilevel_coarsen`], but never merges vertices pinned to
+/// different parts (see [`coarsen_once_fixed`]).
+///
+/// Use [`coarse_fixed`] to carry the pins down each level.
+pub fn multilevel_coarsen_fixed(
+    g: &Graph,
+    threshold: usize,
+    fixed: &[Option<usize>],
+) -> Vec<CoarsenLevel> {
+    assert_eq!(fixed.len(), g.n, "fixed must have one entry per vertex");
+    coarsen_levels(
+        g,
+        threshold,
+        CoarsenScheme::HeavyEdge,
+        None,
+        fixed,
+        0,
+        &[],
+        &mut CoarsenScratch::default(),
+    )
+}
+
+/// Fraction of all possible edges above which a graph counts as dense.
+const DENSE_FRACTION: f64 = 0.25;
+
+/// Whether more than [`DENSE_FRACTION`] of all vertex pairs of `g` are
+/// adjacent. Contracting a dense graph further mostly merges edges that
+/// every partition cuts anyway, so coarsening stops there.
+fn is_dense(g: &Graph) -> bool {
+    let pairs = g.n as f64 * g.n.saturating_sub(1) as f64;
+    g.n > 1 && g.adjncy.len() as f64 > DENSE_FRACTION * pairs
+}
+
+/// Shared loop behind the `multilevel_coarsen*` functions.
+///
+/// `fixed` is either empty or holds the pinned part of each vertex of `g`.
+/// No level has fewer than `min_nc` vertices or a vertex heavier than
+/// `max_vwgt` (beyond those of `g`); coarsening stops once these bounds
+/// prevent further progress, or once a level is dense (see [`is_dense`]).
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn coarsen_levels(
+    g: &Graph,
+    threshold: usize,
+    scheme: CoarsenScheme,
+    mut rng: Option<&mut Rng>,
+    fixed: &[Option<usize>],
+    min_nc: usize,
+    max_vwgt: &[i64],
+    scratch: &mut CoarsenScratch,
+) -> Vec<CoarsenLevel> {
+    let mut levels: Vec<CoarsenLevel> = Vec::new();
+    let mut current_fixed = fixed.to_vec();
 
-    while current.n > threshold {
-        let level = coarsen_once(&current);
+    loop {
+        // Each level coarsens the graph of the previous one in place
+        let current = levels.last().map_or(g, |l| &l.graph);
+        if current.n <= threshold || is_dense(current) {
+            break;
+        }
+        let level = coarsen_level(
+            current,
+            scheme,
+            rng.as_deref_mut(),
+            &current_fixed,
+            min_nc,
+            max_vwgt,
+            scratch,
+        );
         // Stop if coarsening made no progress
         if level.nc >= current.n {
             break;
         }
-        current = level.graph.clone();
+        current_fixed = coarse_fixed(&current_fixed, &level);
+        levels.push(level);
+    }
+
+    levels
+}
+
+/// Coarsen the graph through a prescribed sequence of level sizes.
+///
+/// Level `i` is produced by [`coarsen_to_size`] with `target_sizes[i]`, so
+/// each level is best-effort: it may stay above its target if matching
+/// cannot shrink the graph further. Coarsening stops early once a level makes
+/// no progress.
+pub fn multilevel_coarsen_to_sizes(g: &Graph, target_sizes: &[usize]) -> Vec<CoarsenLevel> {
+    let mut levels: Vec<CoarsenLevel> = Vec::new();
+
+    for &target in target_sizes {
+        let current = levels.last().map_or(g, |l| &l.graph);
+        let level = coarsen_to_size(current, target);
+        if level.nc >= current.n {
+            break;
+        }
         levels.push(level);
     }
 
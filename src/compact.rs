@@ -0,0 +1,121 @@
+//! Compact 32-bit storage for large graphs.
+//!
+//! A [`Graph`] stores indices as `usize` and weights as `i64`, twice what a
+//! graph with fewer than 2^32 directed edges and modest weights needs. A
+//! [`CompactGraph`] holds the same arrays as `u32` and `i32`, halving the
+//! footprint of a graph kept in memory between partitioning runs.
+//!
+//! This is a storage format only. Coarsening, initial partitioning and
+//! refinement all work on full-width [`Graph`]s, so a compact graph is
+//! converted back before partitioning and the partitioner's own memory use
+//! and cache behavior are unchanged.
+
+use crate::graph::{Graph, GraphError};
+
+/// A [`Graph`] with 32-bit indices and weights.
+///
+/// Built with [`CompactGraph::try_from`], which fails if the graph does not
+/// fit, and converted back losslessly with [`Graph::from`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompactGraph {
+    n: usize,
+    xadj: Vec<u32>,
+    adjncy: Vec<u32>,
+    /// Empty if every edge has weight 1.
+    adjwgt: Vec<i32>,
+    /// Empty if every vertex has weight 1.
+    vwgt: Vec<i32>,
+    /// `ncon` weights per vertex, flattened, or empty.
+    vwgt_multi: Vec<i32>,
+    ncon: usize,
+    /// Empty if every vertex's size is its weight.
+    vsize: Vec<i32>,
+}
+
+impl CompactGraph {
+    /// Number of vertices.
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    /// Degree of vertex `u`.
+    pub fn degree(&self, u: usize) -> usize {
+        (self.xadj[u + 1] - self.xadj[u]) as usize
+    }
+
+    /// Neighbors of vertex `u`.
+    pub fn neighbors(&self, u: usize) -> &[u32] {
+        &self.adjncy[self.xadj[u] as usize..self.xadj[u + 1] as usize]
+    }
+
+    /// Edge weight for the `k`-th neighbor of `u`, as [`Graph::edge_weight`].
+    pub fn edge_weight(&self, u: usize, k: usize) -> i64 {
+        if self.adjwgt.is_empty() {
+            1
+        } else {
+            self.adjwgt[self.xadj[u] as usize + k] as i64
+        }
+    }
+
+    /// Vertex weight for `u`, as [`Graph::vertex_weight`].
+    pub fn vertex_weight(&self, u: usize) -> i64 {
+        if self.vwgt.is_empty() {
+            1
+        } else {
+            self.vwgt[u] as i64
+        }
+    }
+
+    /// Bytes held by the graph's arrays, not counting spare capacity.
+    pub fn memory_bytes(&self) -> usize {
+        4 * (self.xadj.len()
+            + self.adjncy.len()
+            + self.adjwgt.len()
+            + self.vwgt.len()
+            + self.vwgt_multi.len()
+            + self.vsize.len())
+    }
+}
+
+/// Narrow every entry of `values`, or fail if one does not fit.
+fn narrow<T: Copy, U: TryFrom<T>>(values: &[T]) -> Result<Vec<U>, GraphError> {
+    values
+        .iter()
+        .map(|&x| U::try_from(x).map_err(|_| GraphError::CompactOverflow))
+        .collect()
+}
+
+impl TryFrom<&Graph> for CompactGraph {
+    type Error = GraphError;
+
+    /// Store `g` in 32 bits, failing with [`GraphError::CompactOverflow`] if
+    /// it has 2^32 or more directed edges or a weight outside `i32`. The
+    /// graph must be valid (see [`Graph::validate`]).
+    fn try_from(g: &Graph) -> Result<Self, GraphError> {
+        Ok(CompactGraph {
+            n: g.n,
+            xadj: narrow(&g.xadj)?,
+            adjncy: narrow(&g.adjncy)?,
+            adjwgt: narrow(&g.adjwgt)?,
+            vwgt: narrow(&g.vwgt)?,
+            vwgt_multi: narrow(&g.vwgt_multi.concat())?,
+            ncon: g.ncon(),
+            vsize: narrow(&g.vsize)?,
+        })
+    }
+}
+
+impl From<&CompactGraph> for Graph {
+    fn from(c: &CompactGraph) -> Self {
+        let widen = |values: &[i32]| values.iter().map(|&w| w as i64).collect();
+        Graph {
+            n: c.n,
+            xadj: c.xadj.iter().map(|&x| x as usize).collect(),
+            adjncy: c.adjncy.iter().map(|&v| v as usize).collect(),
+            adjwgt: widen(&c.adjwgt),
+            vwgt: widen(&c.vwgt),
+            vwgt_multi: c.vwgt_multi.chunks(c.ncon).map(widen).collect(),
+            vsize: widen(&c.vsize),
+        }
+    }
+}
@@ -0,0 +1,460 @@
+//! Builder-style configuration of the multilevel partitioner.
+//!
+//! [`PartitionConfig`] gathers every knob of the k-way pipeline in one
+//! place, so new options extend the builder instead of adding parameters to
+//! the free functions.
+
+use std::fmt;
+
+use crate::coarsen::CoarsenScheme;
+use crate::graph::{Graph, GraphError};
+use crate::kway::{
+    best_of, kway_observed, RefineLevels, COARSEN_PER_PART, COARSEN_THRESHOLD, REFINE_PASSES,
+};
+use crate::partition::{check_fixed, check_tpwgts, InitialMethod, SeedStrategy, BISECTION_SEEDS};
+use crate::quality::PartitionStats;
+use crate::refine::{Objective, RefineMode, MAX_IMBALANCE};
+use crate::workspace::Workspace;
+
+/// Options for a k-way partitioning run.
+///
+/// Start from [`PartitionConfig::default`] and override what you need:
+///
+/// ```
+/// use metis_rs::{Objective, PartitionConfig};
+///
+/// let config = PartitionConfig::default()
+///     .nparts(4)
+///     .imbalance(1.03)
+///     .seed(42)
+///     .objective(Objective::CommVolume);
+/// # let _ = config;
+/// ```
+///
+/// The defaults reproduce [`crate::part_kway`] with two parts.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PartitionConfig {
+    pub(crate) nparts: usize,
+    pub(crate) imbalance: f64,
+    pub(crate) ubvec: Vec<f64>,
+    pub(crate) seed: Option<u64>,
+    pub(crate) ntrials: usize,
+    pub(crate) objective: Objective,
+    pub(crate) refine_mode: RefineMode,
+    pub(crate) tpwgts: Vec<f64>,
+    pub(crate) fixed: Vec<Option<usize>>,
+    pub(crate) coarsen_threshold: usize,
+    pub(crate) coarsen_per_part: usize,
+    pub(crate) coarsen_scheme: CoarsenScheme,
+    pub(crate) max_coarse_weight: f64,
+    pub(crate) refine_passes: usize,
+    pub(crate) min_improvement: f64,
+    pub(crate) initial_method: InitialMethod,
+    pub(crate) ncuts: usize,
+    pub(crate) bisection_seeds: usize,
+    pub(crate) seed_strategy: SeedStrategy,
+    pub(crate) min_part_weight: i64,
+    pub(crate) minconn: bool,
+    pub(crate) contiguous: bool,
+    pub(crate) refine_levels: RefineLevels,
+    pub(crate) flow_refinement: bool,
+}
+
+impl Default for PartitionConfig {
+    fn default() -> Self {
+        Self {
+            nparts: 2,
+            imbalance: MAX_IMBALANCE,
+            ubvec: Vec::new(),
+            seed: None,
+            ntrials: 1,
+            objective: Objective::EdgeCut,
+            refine_mode: RefineMode::Greedy,
+            tpwgts: Vec::new(),
+            fixed: Vec::new(),
+            coarsen_threshold: COARSEN_THRESHOLD,
+            coarsen_per_part: COARSEN_PER_PART,
+            coarsen_scheme: CoarsenScheme::HeavyEdge,
+            max_coarse_weight: 1.0,
+            refine_passes: REFINE_PASSES,
+            min_improvement: 0.0,
+            initial_method: InitialMethod::RecursiveBisection,
+            ncuts: 1,
+            bisection_seeds: BISECTION_SEEDS,
+            seed_strategy: SeedStrategy::Spread,
+            min_part_weight: 0,
+            minconn: false,
+            contiguous: false,
+            refine_levels: RefineLevels::All,
+            flow_refinement: false,
+        }
+    }
+}
+
+impl PartitionConfig {
+    /// Number of parts (default 2).
+    pub fn nparts(mut self, nparts: usize) -> Self {
+        self.nparts = nparts;
+        self
+    }
+
+    /// Largest allowed part weight relative to its target (default `1.05`,
+    /// i.e. 5% imbalance). Must be at least `1.0`.
+    pub fn imbalance(mut self, imbalance: f64) -> Self {
+        self.imbalance = imbalance;
+        self
+    }
+
+    /// Largest allowed part weight relative to its target, per constraint
+    /// (default: [`PartitionConfig::imbalance`] for every constraint).
+    ///
+    /// Needs one entry per constraint of the partitioned graph (see
+    /// [`Graph::ncon`]), each at least `1.0`, and overrides
+    /// [`PartitionConfig::imbalance`] when set.
+    pub fn ubvec(mut self, ubvec: &[f64]) -> Self {
+        self.ubvec = ubvec.to_vec();
+        self
+    }
+
+    /// Randomize coarsening with `seed` (default: natural vertex order).
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Run `ntrials` seeded trials and keep the best (default 1).
+    ///
+    /// Trial `t` uses seed `base + t`, where `base` is the configured seed or
+    /// 0. The best trial has the lowest objective value, then the lightest
+    /// heaviest part, then the lowest trial number. With the `rayon` feature
+    /// the trials run in parallel, with the same result.
+    pub fn ntrials(mut self, ntrials: usize) -> Self {
+        self.ntrials = ntrials;
+        self
+    }
+
+    /// Quantity minimized during refinement (default [`Objective::EdgeCut`]).
+    pub fn objective(mut self, objective: Objective) -> Self {
+        self.objective = objective;
+        self
+    }
+
+    /// How refinement passes treat non-improving moves (default
+    /// [`RefineMode::Greedy`]).
+    pub fn refine_mode(mut self, mode: RefineMode) -> Self {
+        self.refine_mode = mode;
+        self
+    }
+
+    /// Target fraction of the total weight for each part (default uniform).
+    ///
+    /// See [`crate::partition::check_tpwgts`] for the accepted input.
+    pub fn tpwgts(mut self, tpwgts: &[f64]) -> Self {
+        self.tpwgts = tpwgts.to_vec();
+        self
+    }
+
+    /// Pin vertex `u` to part `k` whenever `fixed[u] == Some(k)` (default:
+    /// no pins). Must have one entry per vertex of the partitioned graph.
+    pub fn fixed(mut self, fixed: &[Option<usize>]) -> Self {
+        self.fixed = fixed.to_vec();
+        self
+    }
+
+    /// Stop coarsening once the graph has at most this many vertices
+    /// (default 20). The pipeline never coarsens below `2 * nparts`, nor
+    /// below [`PartitionConfig::coarsen_per_part`] vertices per part.
+    pub fn coarsen_threshold(mut self, threshold: usize) -> Self {
+        self.coarsen_threshold = threshold;
+        self
+    }
+
+    /// Stop coarsening once the graph has at most `vertices * nparts`
+    /// vertices (default 10 per part), if that exceeds
+    /// [`PartitionConfig::coarsen_threshold`]. With many parts a fixed
+    /// threshold would leave a coarsest graph too small for a good initial
+    /// partition.
+    pub fn coarsen_per_part(mut self, vertices: usize) -> Self {
+        self.coarsen_per_part = vertices;
+        self
+    }
+
+    /// How vertices are matched on each coarsening level (default
+    /// [`CoarsenScheme::HeavyEdge`]). Ties in
+    /// [`CoarsenScheme::SortedHeavyEdge`] are broken by the configured seed,
+    /// or by seed 0 without one.
+    pub fn coarsen_scheme(mut self, scheme: CoarsenScheme) -> Self {
+        self.coarsen_scheme = scheme;
+        self
+    }
+
+    /// Heaviest coarse vertex that coarsening may create, as a fraction of
+    /// the average part weight (default `1.0`). With several constraints
+    /// the cap applies to each, relative to its own average part weight.
+    /// Merges past the cap are skipped, so no coarse vertex outweighs a part
+    /// and the initial partition can be balanced. Must be positive; see
+    /// [`crate::coarsen::coarsen_once_capped`].
+    pub fn max_coarse_weight(mut self, fraction: f64) -> Self {
+        self.max_coarse_weight = fraction;
+        self
+    }
+
+    /// Maximum number of FM passes per level (default 10).
+    pub fn refine_passes(mut self, passes: usize) -> Self {
+        self.refine_passes = passes;
+        self
+    }
+
+    /// Stop refining a level once a pass lowers the edge cut by less than
+    /// `fraction` of the cut before it (default 0: only stop when a pass
+    /// makes no move). E.g. `0.01` skips the long tail of passes that each
+    /// gain under 1%.
+    pub fn min_improvement(mut self, fraction: f64) -> Self {
+        self.min_improvement = fraction;
+        self
+    }
+
+    /// Uncoarsening levels at which FM refinement runs (default
+    /// [`RefineLevels::All`]). Refining fewer levels trades cut quality for
+    /// speed.
+    pub fn refine_levels(mut self, levels: RefineLevels) -> Self {
+        self.refine_levels = levels;
+        self
+    }
+
+    /// Follow the FM passes of every refined level with max-flow min-cut
+    /// steps between adjacent parts (default `false`), as in
+    /// [`crate::refine::flow_refine`]. Slower, but can reach cuts that FM
+    /// cannot.
+    pub fn flow_refinement(mut self, enabled: bool) -> Self {
+        self.flow_refinement = enabled;
+        self
+    }
+
+    /// How the coarsest graph is partitioned (default
+    /// [`InitialMethod::RecursiveBisection`]).
+    pub fn initial_method(mut self, method: InitialMethod) -> Self {
+        self.initial_method = method;
+        self
+    }
+
+    /// Number of initial partitions of the coarsest graph to compute, keeping
+    /// the best (default 1).
+    ///
+    /// The first is the usual deterministic one; cut `i` is randomized with
+    /// seed `base + i`, where `base` is the trial's seed or 0. Each is
+    /// rebalanced and given a few FM passes, and the one least over the
+    /// balance bounds, then with the lowest cut, is uncoarsened. `0` is
+    /// treated as 1.
+    pub fn ncuts(mut self, ncuts: usize) -> Self {
+        self.ncuts = ncuts;
+        self
+    }
+
+    /// Number of seed vertices each greedy-growing bisection of
+    /// [`InitialMethod::RecursiveBisection`] grows a candidate from
+    /// (default 7). `0` is treated as 1.
+    pub fn bisection_seeds(mut self, count: usize) -> Self {
+        self.bisection_seeds = count;
+        self
+    }
+
+    /// How those seed vertices are picked (default
+    /// [`SeedStrategy::Spread`]). Randomized cuts of
+    /// [`PartitionConfig::ncuts`] always pick them at random.
+    pub fn seed_strategy(mut self, strategy: SeedStrategy) -> Self {
+        self.seed_strategy = strategy;
+        self
+    }
+
+    /// Smallest allowed part weight on every constraint (default 0); with
+    /// unit vertex weights this is a minimum vertex count per part.
+    ///
+    /// Recursive bisection grows each side to its floor even past the
+    /// imbalance bound, and refinement never moves a vertex out of a part
+    /// at its floor. [`InitialMethod::GreedyKway`] and
+    /// [`InitialMethod::Bubble`] do not enforce it.
+    /// Panics in [`PartitionConfig::partition`] if `nparts` parts cannot all
+    /// reach the floor.
+    pub fn min_part_weight(mut self, weight: i64) -> Self {
+        self.min_part_weight = weight;
+        self
+    }
+
+    /// Also minimize the number of pairs of adjacent parts during k-way
+    /// refinement (default `false`), as in [`crate::refine::fm_refine_minconn`].
+    pub fn minconn(mut self, minconn: bool) -> Self {
+        self.minconn = minconn;
+        self
+    }
+
+    /// Require every part to induce a connected subgraph (default `false`).
+    ///
+    /// After refinement, stray fragments of each part are reassigned with
+    /// [`crate::refine::enforce_contiguity`], which ignores the imbalance
+    /// bound. Parts can only be connected where `g` is, so a part may still
+    /// span several connected components of `g`. Cannot be combined with
+    /// pinned vertices.
+    pub fn contiguous(mut self, contiguous: bool) -> Self {
+        self.contiguous = contiguous;
+        self
+    }
+
+    /// Partition `g` with this configuration.
+    ///
+    /// Panics if the imbalance or an entry of `ubvec` is below `1.0`, if
+    /// `ubvec` does not match the graph's constraints, if the target weights
+    /// or pinned vertices do not fit `g` and `nparts`, if pinned vertices
+    /// are combined with [`PartitionConfig::contiguous`], or if the minimum
+    /// part weight cannot be met.
+    pub fn partition(&self, g: &Graph) -> PartitionResult {
+        self.partition_with_workspace(g, &mut Workspace::new())
+    }
+
+    /// Partition `g` with this configuration, reusing the working memory
+    /// of `workspace` across calls.
+    ///
+    /// Same result and panics as [`PartitionConfig::partition`]; see
+    /// [`Workspace`].
+    pub fn partition_with_workspace(
+        &self,
+        g: &Graph,
+        workspace: &mut Workspace,
+    ) -> PartitionResult {
+        self.validate(g);
+        let (edge_cut, part, _) = best_of(g, self, workspace);
+        let stats = g.partition_stats(&part, self.nparts);
+        PartitionResult {
+            edge_cut,
+            part,
+            stats,
+        }
+    }
+
+    /// Partition `g` with this configuration, reporting every refinement
+    /// pass to `observer` as [`crate::part_kway_observed`] does.
+    ///
+    /// Same result and panics as [`PartitionConfig::partition`]; also panics
+    /// if more than one trial is configured, since the passes of competing
+    /// trials would be interleaved.
+    pub fn partition_observed(
+        &self,
+        g: &Graph,
+        observer: &mut dyn FnMut(usize, usize, i64, usize),
+    ) -> PartitionResult {
+        self.validate(g);
+        assert!(
+            self.ntrials <= 1,
+            "an observed partition runs a single trial"
+        );
+        let (edge_cut, part, _) =
+            kway_observed(g, self, self.seed, Some(observer), &mut Workspace::new());
+        let stats = g.partition_stats(&part, self.nparts);
+        PartitionResult {
+            edge_cut,
+            part,
+            stats,
+        }
+    }
+
+    /// Check the options against `g`, panicking on invalid input.
+    pub(crate) fn validate(&self, g: &Graph) {
+        assert!(self.imbalance >= 1.0, "imbalance must be at least 1.0");
+        assert!(
+            self.max_coarse_weight > 0.0,
+            "max_coarse_weight must be positive"
+        );
+        assert!(
+            self.min_improvement >= 0.0,
+            "min_improvement must not be negative"
+        );
+        if !self.ubvec.is_empty() {
+            assert_eq!(
+                self.ubvec.len(),
+                g.ncon(),
+                "ubvec must have one entry per constraint"
+            );
+            assert!(
+                self.ubvec.iter().all(|&ub| ub >= 1.0),
+                "ubvec entries must be at least 1.0"
+            );
+        }
+        if !self.tpwgts.is_empty() {
+            check_tpwgts(&self.tpwgts, self.nparts);
+        }
+        if !self.fixed.is_empty() {
+            check_fixed(&self.fixed, g.n, self.nparts.max(1));
+            assert!(
+                !self.contiguous,
+                "contiguous partitions cannot have pinned vertices"
+            );
+        }
+        if self.min_part_weight > 0 {
+            let feasible = (0..g.ncon()).all(|c| {
+                let total: i64 = (0..g.n).map(|u| g.constraint_weight(u, c)).sum();
+                total / self.nparts.max(1) as i64 >= self.min_part_weight
+            });
+            assert!(feasible, "min_part_weight exceeds the average part weight");
+        }
+    }
+}
+
+/// Alternative name for [`PartitionConfig`], as taken by
+/// [`crate::partition_with_options`].
+pub type PartitionOptions = PartitionConfig;
+
+/// Outcome of [`PartitionConfig::partition`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct PartitionResult {
+    /// Total weight of edges crossing part boundaries.
+    pub edge_cut: i64,
+    /// `part[u]` is the 0-based part ID of vertex `u`.
+    pub part: Vec<usize>,
+    /// Quality statistics of `part`: per-part weights, imbalance, boundary
+    /// vertex count and communication volume.
+    pub stats: PartitionStats,
+}
+
+impl From<PartitionResult> for (i64, Vec<usize>) {
+    /// The `(edge_cut, partition)` tuple of the plain [`crate::partition`] API.
+    fn from(result: PartitionResult) -> Self {
+        (result.edge_cut, result.part)
+    }
+}
+
+/// Reason [`crate::try_partition`] rejected its input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PartitionError {
+    /// The CSR arrays or weights are malformed; see [`Graph::validate`].
+    Graph(GraphError),
+    /// `nparts` is 0.
+    ZeroParts,
+    /// Some edge `u -> v` has no matching `v -> u` of the same weight; see
+    /// [`Graph::is_symmetric`] and [`Graph::canonicalize`].
+    Asymmetric,
+}
+
+impl fmt::Display for PartitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PartitionError::Graph(e) => write!(f, "invalid graph: {}", e),
+            PartitionError::ZeroParts => write!(f, "nparts must be at least 1"),
+            PartitionError::Asymmetric => write!(f, "graph adjacency is not symmetric"),
+        }
+    }
+}
+
+impl std::error::Error for PartitionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PartitionError::Graph(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<GraphError> for PartitionError {
+    fn from(e: GraphError) -> Self {
+        PartitionError::Graph(e)
+    }
+}
@@ -1,5 +1,265 @@
 //! CSR graph representation for partitioning.
 
+use std::fmt;
+
+/// Vertices per block of [`Graph::try_edge_cut`], summed as one task.
+const CUT_BLOCK: usize = 4096;
+
+/// Reason a set of CSR arrays does not describe a valid [`Graph`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GraphError {
+    /// `xadj` does not have `n + 1` entries.
+    XadjLength { expected: usize, actual: usize },
+    /// `xadj[index + 1] < xadj[index]`.
+    XadjNotMonotonic { index: usize },
+    /// The last `xadj` entry does not equal `adjncy.len()`.
+    XadjEnd { expected: usize, actual: usize },
+    /// Vertex `vertex` lists `neighbor`, which is not in `0..n`.
+    NeighborOutOfRange { vertex: usize, neighbor: usize },
+    /// `adjwgt` is neither empty nor aligned with `adjncy`.
+    AdjwgtLength { expected: usize, actual: usize },
+    /// `vwgt` is neither empty nor of length `n`.
+    VwgtLength { expected: usize, actual: usize },
+    /// `vsize` is neither empty nor of length `n`.
+    VsizeLength { expected: usize, actual: usize },
+    /// `vwgt_multi` is neither empty nor of length `n`.
+    VwgtMultiLength { expected: usize, actual: usize },
+    /// Vertex `vertex` has a different number of constraint weights than
+    /// vertex 0, or none at all.
+    NconMismatch {
+        vertex: usize,
+        expected: usize,
+        actual: usize,
+    },
+    /// `adjwgt[index]` is negative.
+    NegativeEdgeWeight { index: usize },
+    /// Vertex `vertex` has a negative weight in `vwgt` or `vwgt_multi`.
+    NegativeVertexWeight { vertex: usize },
+    /// Vertex `vertex` has a negative size in `vsize`.
+    NegativeVertexSize { vertex: usize },
+    /// A sum of edge or vertex weights does not fit in an `i64`.
+    WeightOverflow,
+    /// An index or weight does not fit the 32-bit storage of a
+    /// [`CompactGraph`](crate::compact::CompactGraph).
+    CompactOverflow,
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphError::XadjLength { expected, actual } => {
+                write!(f, "xadj has length {}, expected {}", actual, expected)
+            }
+            GraphError::XadjNotMonotonic { index } => {
+                write!(
+                    f,
+                    "xadj decreases between entries {} and {}",
+                    index,
+                    index + 1
+                )
+            }
+            GraphError::XadjEnd { expected, actual } => {
+                write!(
+                    f,
+                    "xadj ends at {}, expected adjncy length {}",
+                    actual, expected
+                )
+            }
+            GraphError::NeighborOutOfRange { vertex, neighbor } => {
+                write!(
+                    f,
+                    "vertex {} has out-of-range neighbor {}",
+                    vertex, neighbor
+                )
+            }
+            GraphError::AdjwgtLength { expected, actual } => {
+                write!(f, "adjwgt has length {}, expected {}", actual, expected)
+            }
+            GraphError::VwgtLength { expected, actual } => {
+                write!(f, "vwgt has length {}, expected {}", actual, expected)
+            }
+            GraphError::VsizeLength { expected, actual } => {
+                write!(f, "vsize has length {}, expected {}", actual, expected)
+            }
+            GraphError::VwgtMultiLength { expected, actual } => {
+                write!(f, "vwgt_multi has length {}, expected {}", actual, expected)
+            }
+            GraphError::NconMismatch {
+                vertex,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "vertex {} has {} constraint weights, expected {}",
+                vertex, actual, expected
+            ),
+            GraphError::NegativeEdgeWeight { index } => {
+                write!(f, "adjwgt entry {} is negative", index)
+            }
+            GraphError::NegativeVertexWeight { vertex } => {
+                write!(f, "vertex {} has a negative weight", vertex)
+            }
+            GraphError::NegativeVertexSize { vertex } => {
+                write!(f, "vertex {} has a negative size", vertex)
+            }
+            GraphError::WeightOverflow => write!(f, "weight sum overflows i64"),
+            GraphError::CompactOverflow => write!(f, "graph does not fit in 32-bit storage"),
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
+
+/// Check that `xadj` has `n + 1` non-decreasing entries ending at
+/// `adjncy.len()` and that every neighbor is in `0..n`.
+fn check_csr(n: usize, xadj: &[usize], adjncy: &[usize]) -> Result<(), GraphError> {
+    check_xadj(n, xadj, adjncy.len())?;
+    for u in 0..n {
+        if let Some(&v) = adjncy[xadj[u]..xadj[u + 1]].iter().find(|&&v| v >= n) {
+            return Err(GraphError::NeighborOutOfRange {
+                vertex: u,
+                neighbor: v,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Check that `xadj` has `n + 1` non-decreasing entries ending at `nadj`.
+fn check_xadj(n: usize, xadj: &[usize], nadj: usize) -> Result<(), GraphError> {
+    if xadj.len() != n + 1 {
+        return Err(GraphError::XadjLength {
+            expected: n + 1,
+            actual: xadj.len(),
+        });
+    }
+    if let Some(index) = xadj.windows(2).position(|w| w[1] < w[0]) {
+        return Err(GraphError::XadjNotMonotonic { index });
+    }
+    if xadj[n] != nadj {
+        return Err(GraphError::XadjEnd {
+            expected: nadj,
+            actual: xadj[n],
+        });
+    }
+    Ok(())
+}
+
+/// Check that `vwgt_multi` has one equally sized, non-empty vector of
+/// non-negative weights per vertex.
+fn check_vwgt_multi(n: usize, vwgt_multi: &[Vec<i64>]) -> Result<(), GraphError> {
+    if vwgt_multi.len() != n {
+        return Err(GraphError::VwgtMultiLength {
+            expected: n,
+            actual: vwgt_multi.len(),
+        });
+    }
+    let ncon = vwgt_multi.first().map_or(1, Vec::len).max(1);
+    if let Some(u) = vwgt_multi.iter().position(|w| w.len() != ncon) {
+        return Err(GraphError::NconMismatch {
+            vertex: u,
+            expected: ncon,
+            actual: vwgt_multi[u].len(),
+        });
+    }
+    if let Some(vertex) = vwgt_multi.iter().position(|w| w.iter().any(|&x| x < 0)) {
+        return Err(GraphError::NegativeVertexWeight { vertex });
+    }
+    Ok(())
+}
+
+/// Position of the first negative entry of `weights`, if any.
+fn first_negative(weights: &[i64]) -> Option<usize> {
+    weights.iter().position(|&w| w < 0)
+}
+
+/// Problems found by [`Graph::check`].
+///
+/// Entries are `(u, v)` pairs for `v` listed among the neighbors of `u`.
+/// Every list is empty, and `layout` is `None`, on a graph the partitioner
+/// handles correctly.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GraphCheck {
+    /// First problem with the shape of the arrays or the sign of a weight,
+    /// as [`Graph::validate`] would report it, other than out-of-range
+    /// neighbors. If `xadj` itself is malformed, the neighbor lists cannot
+    /// be read and the lists below are left empty.
+    pub layout: Option<GraphError>,
+    /// Entries whose neighbor is not in `0..n`, in CSR order.
+    pub out_of_range: Vec<(usize, usize)>,
+    /// Vertices listed among their own neighbors, once per such entry.
+    pub self_loops: Vec<usize>,
+    /// Entries repeating an earlier neighbor of the same vertex, once per
+    /// repeat, sorted.
+    pub duplicates: Vec<(usize, usize)>,
+    /// Entries `u -> v` without an entry `v -> u`, sorted.
+    pub asymmetric: Vec<(usize, usize)>,
+    /// Pairs with `u < v` listed in both directions with different weights
+    /// (summed over repeats), sorted.
+    pub weight_mismatches: Vec<(usize, usize)>,
+}
+
+impl GraphCheck {
+    /// Whether no problem was found.
+    pub fn is_ok(&self) -> bool {
+        self.layout.is_none()
+            && self.out_of_range.is_empty()
+            && self.self_loops.is_empty()
+            && self.duplicates.is_empty()
+            && self.asymmetric.is_empty()
+            && self.weight_mismatches.is_empty()
+    }
+}
+
+/// Which pairs of a directed graph become edges in [`Graph::symmetrized`],
+/// and with what weight.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Symmetrize {
+    /// Every pair listed in either direction; a pair listed in both keeps
+    /// the larger of the two weights. This is what [`Graph::canonicalize`]
+    /// does.
+    #[default]
+    Union,
+    /// Only pairs listed in both directions, with the smaller of the two
+    /// weights.
+    Intersection,
+    /// Every pair listed in either direction, weighing the sum of both
+    /// directions: the adjacency matrix `A + A^T`, saturating at `i64::MAX`.
+    Sum,
+}
+
+/// What [`Graph::normalize`] removed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NormalizeReport {
+    /// Number of self-loop entries dropped.
+    pub self_loops: usize,
+    /// Number of repeated neighbor entries merged into an earlier entry of
+    /// the same neighbor list.
+    pub merged: usize,
+}
+
+impl NormalizeReport {
+    /// Whether the graph was already free of self-loops and parallel edges.
+    pub fn is_clean(&self) -> bool {
+        self.self_loops == 0 && self.merged == 0
+    }
+}
+
+/// External connections of an induced subgraph, from
+/// [`Graph::induced_subgraph_with_ghosts`].
+///
+/// Edges leaving the vertex subset are dropped from the subgraph itself; this
+/// records their weight per neighboring part so the subgraph can still be
+/// refined in the context of the rest of the partition.
+#[derive(Clone, Debug, Default)]
+pub struct GhostInfo {
+    /// Local IDs of subgraph vertices with at least one edge leaving the subset.
+    pub boundary: Vec<usize>,
+    /// For each entry of `boundary`, `(part, weight)` pairs giving the total
+    /// edge weight to outside vertices in each part, sorted by part.
+    pub external: Vec<Vec<(usize, i64)>>,
+}
+
 /// A graph stored in Compressed Sparse Row (CSR) format.
 ///
 /// Vertices are numbered `0..n`. For vertex `u`, its neighbors are
@@ -17,33 +277,310 @@ pub struct Graph {
     pub adjwgt: Vec<i64>,
     /// Vertex weights. If empty, all vertices have weight 1.
     pub vwgt: Vec<i64>,
+    /// Multi-constraint vertex weights: `vwgt_multi[u][c]` is the weight of
+    /// `u` on constraint `c`, with the same number of constraints for every
+    /// vertex. If non-empty, the partitioner balances every constraint and
+    /// uses these instead of `vwgt`.
+    pub vwgt_multi: Vec<Vec<i64>>,
+    /// Vertex sizes: the amount of data a vertex sends to each other part it
+    /// neighbors, counted by the communication volume. If empty, each
+    /// vertex's size is its weight.
+    pub vsize: Vec<i64>,
 }
 
 impl Graph {
     /// Create a graph from CSR arrays.
+    ///
+    /// Panics if the arrays are malformed; see [`Graph::try_new`].
     pub fn new(n: usize, xadj: Vec<usize>, adjncy: Vec<usize>) -> Self {
-        assert_eq!(xadj.len(), n + 1);
-        Self {
+        Self::try_new(n, xadj, adjncy).unwrap_or_else(|e| panic!("invalid graph: {}", e))
+    }
+
+    /// Create a graph from CSR arrays, checking that they are well formed.
+    ///
+    /// `xadj` must have `n + 1` non-decreasing entries ending at
+    /// `adjncy.len()`, and every neighbor must be in `0..n`.
+    /// Add weights with the `try_with_*` setters, which check their lengths
+    /// and reject negative entries.
+    pub fn try_new(n: usize, xadj: Vec<usize>, adjncy: Vec<usize>) -> Result<Self, GraphError> {
+        check_csr(n, &xadj, &adjncy)?;
+        Ok(Self {
             n,
             xadj,
             adjncy,
             adjwgt: Vec::new(),
             vwgt: Vec::new(),
+            vwgt_multi: Vec::new(),
+            vsize: Vec::new(),
+        })
+    }
+
+    /// Check every array of the graph, as [`Graph::try_new`] and the
+    /// `try_with_*` setters do: the CSR structure, the length of every
+    /// weight array, and that no weight or size is negative.
+    ///
+    /// The fields are public, so a graph built or modified by hand may be
+    /// malformed; the partitioner may panic or return garbage on such a
+    /// graph.
+    pub fn validate(&self) -> Result<(), GraphError> {
+        check_csr(self.n, &self.xadj, &self.adjncy)?;
+        self.check_weights()
+    }
+
+    /// Check the length and sign of every weight array.
+    fn check_weights(&self) -> Result<(), GraphError> {
+        if !self.adjwgt.is_empty() && self.adjwgt.len() != self.adjncy.len() {
+            return Err(GraphError::AdjwgtLength {
+                expected: self.adjncy.len(),
+                actual: self.adjwgt.len(),
+            });
+        }
+        if let Some(index) = first_negative(&self.adjwgt) {
+            return Err(GraphError::NegativeEdgeWeight { index });
+        }
+        if !self.vwgt.is_empty() && self.vwgt.len() != self.n {
+            return Err(GraphError::VwgtLength {
+                expected: self.n,
+                actual: self.vwgt.len(),
+            });
+        }
+        if let Some(vertex) = first_negative(&self.vwgt) {
+            return Err(GraphError::NegativeVertexWeight { vertex });
+        }
+        if !self.vwgt_multi.is_empty() {
+            check_vwgt_multi(self.n, &self.vwgt_multi)?;
+        }
+        if !self.vsize.is_empty() && self.vsize.len() != self.n {
+            return Err(GraphError::VsizeLength {
+                expected: self.n,
+                actual: self.vsize.len(),
+            });
+        }
+        if let Some(vertex) = first_negative(&self.vsize) {
+            return Err(GraphError::NegativeVertexSize { vertex });
+        }
+        Ok(())
+    }
+
+    /// Diagnose the graph, listing every problem instead of stopping at the
+    /// first, like METIS's `graphchk`.
+    ///
+    /// Besides the errors [`Graph::validate`] reports, this finds self-loops,
+    /// repeated neighbors, entries without a reverse entry, and pairs whose
+    /// two directions disagree on the weight, none of which
+    /// [`Graph::validate`] rejects but all of which distort edge cuts. See
+    /// [`GraphCheck`].
+    pub fn check(&self) -> GraphCheck {
+        let mut report = GraphCheck::default();
+        if let Err(e) = check_xadj(self.n, &self.xadj, self.adjncy.len()) {
+            report.layout = Some(e);
+            return report;
+        }
+        report.layout = self.check_weights().err();
+
+        // Misaligned weights are reported above; compare unit weights then
+        let weighted = self.adjwgt.len() == self.adjncy.len();
+        let mut directed: Vec<(usize, usize, i64)> = Vec::with_capacity(self.adjncy.len());
+        for u in 0..self.n {
+            for j in self.xadj[u]..self.xadj[u + 1] {
+                let v = self.adjncy[j];
+                if v >= self.n {
+                    report.out_of_range.push((u, v));
+                } else if v == u {
+                    report.self_loops.push(u);
+                } else {
+                    directed.push((u, v, if weighted { self.adjwgt[j] } else { 1 }));
+                }
+            }
+        }
+        directed.sort_unstable_by_key(|&(u, v, _)| (u, v));
+        directed.dedup_by(|next, kept| {
+            let same = (next.0, next.1) == (kept.0, kept.1);
+            if same {
+                kept.2 = kept.2.saturating_add(next.2);
+                report.duplicates.push((kept.0, kept.1));
+            }
+            same
+        });
+
+        for &(u, v, w) in &directed {
+            match directed.binary_search_by_key(&(v, u), |&(a, b, _)| (a, b)) {
+                Err(_) => report.asymmetric.push((u, v)),
+                Ok(i) if u < v && directed[i].2 != w => report.weight_mismatches.push((u, v)),
+                Ok(_) => {}
+            }
         }
+        report
+    }
+
+    /// Build a graph from an undirected, weighted edge list.
+    ///
+    /// Each `(u, v, w)` triple is inserted in both directions. Repeated pairs
+    /// (in either orientation) are merged by summing their weights,
+    /// saturating at `i64::MAX`, and self-loops are dropped since they never
+    /// contribute to a cut. Neighbor lists come out sorted. Panics if an
+    /// endpoint is not in `0..n` or a weight is negative, which
+    /// [`Graph::try_with_adjwgt`] would reject.
+    pub fn from_edges(n: usize, edges: &[(usize, usize, i64)]) -> Self {
+        let mut directed: Vec<(usize, usize, i64)> = Vec::with_capacity(2 * edges.len());
+        for &(u, v, w) in edges {
+            assert!(u < n && v < n, "edge ({}, {}) out of range", u, v);
+            assert!(w >= 0, "edge ({}, {}) has negative weight {}", u, v, w);
+            if u != v {
+                directed.push((u, v, w));
+                directed.push((v, u, w));
+            }
+        }
+        directed.sort_unstable_by_key(|&(u, v, _)| (u, v));
+
+        let mut xadj = vec![0usize; n + 1];
+        let mut adjncy: Vec<usize> = Vec::with_capacity(directed.len());
+        let mut adjwgt: Vec<i64> = Vec::with_capacity(directed.len());
+        let mut last: Option<(usize, usize)> = None;
+        for (u, v, w) in directed {
+            if last == Some((u, v)) {
+                let total = adjwgt.last_mut().unwrap();
+                *total = total.saturating_add(w);
+            } else {
+                adjncy.push(v);
+                adjwgt.push(w);
+                xadj[u + 1] += 1;
+                last = Some((u, v));
+            }
+        }
+        for u in 0..n {
+            xadj[u + 1] += xadj[u];
+        }
+
+        Self {
+            n,
+            xadj,
+            adjncy,
+            adjwgt,
+            vwgt: Vec::new(),
+            vwgt_multi: Vec::new(),
+            vsize: Vec::new(),
+        }
+    }
+
+    /// Build a graph from an undirected, unweighted edge list.
+    ///
+    /// Same as [`Graph::from_edges`] with every weight 1, so a pair listed
+    /// `k` times (in either orientation) becomes one edge of weight `k`.
+    /// Edge weights stay implicit (empty `adjwgt`) if no pair repeats.
+    /// Panics if an endpoint is not in `0..n`.
+    pub fn from_unweighted_edges(n: usize, edges: &[(usize, usize)]) -> Self {
+        let weighted: Vec<(usize, usize, i64)> = edges.iter().map(|&(u, v)| (u, v, 1)).collect();
+        let mut g = Self::from_edges(n, &weighted);
+        if g.adjwgt.iter().all(|&w| w == 1) {
+            g.adjwgt = Vec::new();
+        }
+        g
     }
 
     /// Set edge weights.
-    pub fn with_adjwgt(mut self, adjwgt: Vec<i64>) -> Self {
-        assert_eq!(adjwgt.len(), self.adjncy.len());
+    ///
+    /// Panics if `adjwgt` is not aligned with `adjncy` or has a negative
+    /// entry.
+    pub fn with_adjwgt(self, adjwgt: Vec<i64>) -> Self {
+        self.try_with_adjwgt(adjwgt)
+            .unwrap_or_else(|e| panic!("invalid graph: {}", e))
+    }
+
+    /// Set edge weights, checking that they are aligned with `adjncy` and
+    /// non-negative.
+    pub fn try_with_adjwgt(mut self, adjwgt: Vec<i64>) -> Result<Self, GraphError> {
+        if adjwgt.len() != self.adjncy.len() {
+            return Err(GraphError::AdjwgtLength {
+                expected: self.adjncy.len(),
+                actual: adjwgt.len(),
+            });
+        }
+        if let Some(index) = first_negative(&adjwgt) {
+            return Err(GraphError::NegativeEdgeWeight { index });
+        }
         self.adjwgt = adjwgt;
-        self
+        Ok(self)
     }
 
     /// Set vertex weights.
-    pub fn with_vwgt(mut self, vwgt: Vec<i64>) -> Self {
-        assert_eq!(vwgt.len(), self.n);
+    ///
+    /// Panics unless there is one non-negative weight per vertex.
+    pub fn with_vwgt(self, vwgt: Vec<i64>) -> Self {
+        self.try_with_vwgt(vwgt)
+            .unwrap_or_else(|e| panic!("invalid graph: {}", e))
+    }
+
+    /// Set vertex weights, checking that there is one non-negative weight
+    /// per vertex.
+    pub fn try_with_vwgt(mut self, vwgt: Vec<i64>) -> Result<Self, GraphError> {
+        if vwgt.len() != self.n {
+            return Err(GraphError::VwgtLength {
+                expected: self.n,
+                actual: vwgt.len(),
+            });
+        }
+        if let Some(vertex) = first_negative(&vwgt) {
+            return Err(GraphError::NegativeVertexWeight { vertex });
+        }
         self.vwgt = vwgt;
-        self
+        Ok(self)
+    }
+
+    /// Set multi-constraint vertex weights, one vector per vertex.
+    ///
+    /// Panics unless there is one equally sized, non-empty vector of
+    /// non-negative weights per vertex.
+    pub fn with_vwgt_multi(self, vwgt_multi: Vec<Vec<i64>>) -> Self {
+        self.try_with_vwgt_multi(vwgt_multi)
+            .unwrap_or_else(|e| panic!("invalid graph: {}", e))
+    }
+
+    /// Set multi-constraint vertex weights, checking their shape.
+    pub fn try_with_vwgt_multi(mut self, vwgt_multi: Vec<Vec<i64>>) -> Result<Self, GraphError> {
+        check_vwgt_multi(self.n, &vwgt_multi)?;
+        self.vwgt_multi = vwgt_multi;
+        Ok(self)
+    }
+
+    /// Set vertex sizes for the communication volume.
+    ///
+    /// Panics unless there is one non-negative size per vertex.
+    pub fn with_vsize(self, vsize: Vec<i64>) -> Self {
+        self.try_with_vsize(vsize)
+            .unwrap_or_else(|e| panic!("invalid graph: {}", e))
+    }
+
+    /// Set vertex sizes, checking that there is one non-negative size per
+    /// vertex.
+    pub fn try_with_vsize(mut self, vsize: Vec<i64>) -> Result<Self, GraphError> {
+        if vsize.len() != self.n {
+            return Err(GraphError::VsizeLength {
+                expected: self.n,
+                actual: vsize.len(),
+            });
+        }
+        if let Some(vertex) = first_negative(&vsize) {
+            return Err(GraphError::NegativeVertexSize { vertex });
+        }
+        self.vsize = vsize;
+        Ok(self)
+    }
+
+    /// Number of balance constraints (1 unless `vwgt_multi` is set).
+    pub fn ncon(&self) -> usize {
+        self.vwgt_multi.first().map_or(1, Vec::len)
+    }
+
+    /// Weight of `u` on constraint `c`.
+    ///
+    /// Without `vwgt_multi` there is a single constraint, the vertex weight.
+    pub fn constraint_weight(&self, u: usize, c: usize) -> i64 {
+        if self.vwgt_multi.is_empty() {
+            self.vertex_weight(u)
+        } else {
+            self.vwgt_multi[u][c]
+        }
     }
 
     /// Degree of vertex `u`.
@@ -56,6 +593,17 @@ impl Graph {
         &self.adjncy[self.xadj[u]..self.xadj[u + 1]]
     }
 
+    /// Neighbors of vertex `u` together with the weights of the edges to
+    /// them, in the order of [`Graph::neighbors`].
+    pub fn edges(&self, u: usize) -> impl Iterator<Item = (usize, i64)> + '_ {
+        // Empty for unit weights, where every lookup falls back to 1
+        let weights = self.edge_weights(u);
+        self.neighbors(u)
+            .iter()
+            .enumerate()
+            .map(move |(k, &v)| (v, weights.get(k).copied().unwrap_or(1)))
+    }
+
     /// Edge weight for the `k`-th neighbor of `u` (0-indexed within neighbor list).
     pub fn edge_weight(&self, u: usize, k: usize) -> i64 {
         if self.adjwgt.is_empty() {
@@ -65,6 +613,16 @@ impl Graph {
         }
     }
 
+    /// Edge weights of `u`'s neighbors, in the order of [`Graph::neighbors`],
+    /// or an empty slice if every edge has weight 1.
+    pub(crate) fn edge_weights(&self, u: usize) -> &[i64] {
+        if self.adjwgt.is_empty() {
+            &[]
+        } else {
+            &self.adjwgt[self.xadj[u]..self.xadj[u + 1]]
+        }
+    }
+
     /// Vertex weight for `u`.
     pub fn vertex_weight(&self, u: usize) -> i64 {
         if self.vwgt.is_empty() {
@@ -74,28 +632,421 @@ impl Graph {
         }
     }
 
-    /// Total weight of all edges incident to `u`.
+    /// Size of `u` for the communication volume: `vsize[u]`, or the vertex
+    /// weight if `vsize` is empty.
+    pub fn vertex_size(&self, u: usize) -> i64 {
+        if self.vsize.is_empty() {
+            self.vertex_weight(u)
+        } else {
+            self.vsize[u]
+        }
+    }
+
+    /// Total weight of all edges incident to `u`, saturating at `i64::MAX`.
     pub fn weighted_degree(&self, u: usize) -> i64 {
         let start = self.xadj[u];
         let end = self.xadj[u + 1];
         if self.adjwgt.is_empty() {
             (end - start) as i64
         } else {
-            self.adjwgt[start..end].iter().sum()
+            self.adjwgt[start..end]
+                .iter()
+                .fold(0i64, |acc, &w| acc.saturating_add(w))
+        }
+    }
+
+    /// Whether every directed entry `u -> v` with weight `w` is matched by an
+    /// entry `v -> u` with the same weight.
+    ///
+    /// Duplicate entries must be matched by equally many reverse entries.
+    /// [`Graph::edge_cut`] and the partitioner assume a symmetric graph; use
+    /// [`Graph::canonicalize`] to repair one that is not.
+    pub fn is_symmetric(&self) -> bool {
+        let mut forward: Vec<(usize, usize, i64)> = Vec::with_capacity(self.adjncy.len());
+        for u in 0..self.n {
+            for (v, w) in self.edges(u) {
+                forward.push((u, v, w));
+            }
         }
+        let mut reverse: Vec<(usize, usize, i64)> =
+            forward.iter().map(|&(u, v, w)| (v, u, w)).collect();
+        forward.sort_unstable();
+        reverse.sort_unstable();
+        forward == reverse
+    }
+
+    /// Rewrite the adjacency into canonical form.
+    ///
+    /// Self-loops are dropped, duplicate `u -> v` entries in a neighbor list
+    /// are merged by summing their weights, and each neighbor list is sorted.
+    /// Edges are then symmetrized: a missing reverse entry is added with the
+    /// same weight, and when `u -> v` and `v -> u` disagree both get the
+    /// **larger** of the two weights. Vertex weights are unchanged. Edge
+    /// weights stay implicit (empty `adjwgt`) if they were and every
+    /// resulting weight is 1. See [`Graph::symmetrized`] for other ways of
+    /// combining the two directions.
+    pub fn canonicalize(&mut self) {
+        let canonical = self.symmetric_edges(Symmetrize::Union);
+        self.xadj = canonical.xadj;
+        self.adjncy = canonical.adjncy;
+        self.adjwgt = canonical.adjwgt;
+    }
+
+    /// An undirected copy of a directed graph.
+    ///
+    /// Self-loops are dropped and duplicate `u -> v` entries merged by
+    /// summing their weights, as in [`Graph::canonicalize`]; `mode` then
+    /// decides which pairs become edges and with what weight. Neighbor
+    /// lists come out sorted, vertex weights and sizes are copied
+    /// unchanged, and edge weights stay implicit (empty `adjwgt`) if they
+    /// were and every resulting weight is 1.
+    pub fn symmetrized(&self, mode: Symmetrize) -> Graph {
+        let edges = self.symmetric_edges(mode);
+        Graph {
+            vwgt: self.vwgt.clone(),
+            vwgt_multi: self.vwgt_multi.clone(),
+            vsize: self.vsize.clone(),
+            ..edges
+        }
+    }
+
+    /// Adjacency of [`Graph::symmetrized`], without vertex weights.
+    fn symmetric_edges(&self, mode: Symmetrize) -> Graph {
+        let mut directed: Vec<(usize, usize, i64)> = Vec::with_capacity(self.adjncy.len());
+        for u in 0..self.n {
+            for (v, w) in self.edges(u) {
+                if u != v {
+                    directed.push((u, v, w));
+                }
+            }
+        }
+        directed.sort_unstable_by_key(|&(u, v, _)| (u, v));
+        directed.dedup_by(|next, kept| {
+            let same = (next.0, next.1) == (kept.0, kept.1);
+            if same {
+                kept.2 = kept.2.saturating_add(next.2);
+            }
+            same
+        });
+
+        // At most two entries per unordered pair, one per direction
+        let mut pairs: Vec<(usize, usize, i64)> = directed
+            .into_iter()
+            .map(|(u, v, w)| (u.min(v), u.max(v), w))
+            .collect();
+        pairs.sort_unstable_by_key(|&(u, v, _)| (u, v));
+        let mut undirected: Vec<(usize, usize, i64)> = Vec::with_capacity(pairs.len());
+        let mut i = 0;
+        while i < pairs.len() {
+            let (u, v, w) = pairs[i];
+            let reverse = pairs
+                .get(i + 1)
+                .filter(|&&(a, b, _)| (a, b) == (u, v))
+                .map(|&(_, _, w)| w);
+            i += 1 + usize::from(reverse.is_some());
+            let w = match (mode, reverse) {
+                (Symmetrize::Union, Some(r)) => w.max(r),
+                (Symmetrize::Intersection, Some(r)) => w.min(r),
+                (Symmetrize::Intersection, None) => continue,
+                (Symmetrize::Sum, Some(r)) => w.saturating_add(r),
+                (_, None) => w,
+            };
+            undirected.push((u, v, w));
+        }
+
+        let implicit = self.adjwgt.is_empty() && undirected.iter().all(|&(_, _, w)| w == 1);
+        let mut g = Graph::from_edges(self.n, &undirected);
+        if implicit {
+            g.adjwgt = Vec::new();
+        }
+        g
+    }
+
+    /// A copy of the graph without self-loops or parallel edges, with a
+    /// count of what was removed.
+    ///
+    /// Self-loops are dropped, and repeated neighbors of a vertex are merged
+    /// into one entry whose weight is the sum of theirs, saturating at
+    /// `i64::MAX`; each neighbor list comes out sorted. Unlike
+    /// [`Graph::canonicalize`] this never adds or reweighs reverse entries,
+    /// so an asymmetric graph stays asymmetric. Vertex weights and sizes are
+    /// copied unchanged, and edge weights stay implicit (empty `adjwgt`) if
+    /// they were and no entry was merged.
+    pub fn normalize(&self) -> (Graph, NormalizeReport) {
+        let mut report = NormalizeReport::default();
+        let mut xadj = Vec::with_capacity(self.n + 1);
+        let mut adjncy = Vec::with_capacity(self.adjncy.len());
+        let mut adjwgt = Vec::with_capacity(self.adjncy.len());
+        let mut row: Vec<(usize, i64)> = Vec::new();
+        xadj.push(0);
+        for u in 0..self.n {
+            row.clear();
+            for (v, w) in self.edges(u) {
+                if v == u {
+                    report.self_loops += 1;
+                } else {
+                    row.push((v, w));
+                }
+            }
+            row.sort_unstable_by_key(|&(v, _)| v);
+            for &(v, w) in &row {
+                if adjncy.len() > xadj[u] && adjncy.last() == Some(&v) {
+                    let total: &mut i64 = adjwgt.last_mut().unwrap();
+                    *total = total.saturating_add(w);
+                    report.merged += 1;
+                } else {
+                    adjncy.push(v);
+                    adjwgt.push(w);
+                }
+            }
+            xadj.push(adjncy.len());
+        }
+        if self.adjwgt.is_empty() && report.merged == 0 {
+            adjwgt = Vec::new();
+        }
+        let g = Graph {
+            n: self.n,
+            xadj,
+            adjncy,
+            adjwgt,
+            vwgt: self.vwgt.clone(),
+            vwgt_multi: self.vwgt_multi.clone(),
+            vsize: self.vsize.clone(),
+        };
+        (g, report)
     }
 
     /// Total edge cut for a given partition assignment.
+    ///
+    /// Each edge is counted once, from its lower-numbered endpoint, so the
+    /// result is only meaningful for a symmetric graph (see
+    /// [`Graph::is_symmetric`]). With the `rayon` feature, blocks of
+    /// consecutive vertices are summed in parallel; the total is the same
+    /// for any number of threads.
+    ///
+    /// The sum is accumulated in `i128`, so large weights cannot wrap; a cut
+    /// above `i64::MAX` saturates to `i64::MAX`. See [`Graph::try_edge_cut`]
+    /// to detect that case instead.
     pub fn edge_cut(&self, part: &[usize]) -> i64 {
-        let mut cut = 0i64;
-        for u in 0..self.n {
-            for k in 0..self.degree(u) {
-                let v = self.adjncy[self.xadj[u] + k];
-                if part[u] != part[v] {
-                    cut += self.edge_weight(u, k);
+        self.try_edge_cut(part).unwrap_or(i64::MAX)
+    }
+
+    /// Total edge cut, or [`GraphError::WeightOverflow`] if it does not fit
+    /// in an `i64`.
+    pub fn try_edge_cut(&self, part: &[usize]) -> Result<i64, GraphError> {
+        let block_cut = |b: usize| {
+            let mut cut = 0i128;
+            for u in b * CUT_BLOCK..self.n.min((b + 1) * CUT_BLOCK) {
+                let pu = part[u];
+                let edges = self.xadj[u]..self.xadj[u + 1];
+                let cut_edge = |&v: &usize| u < v && part[v] != pu;
+                if self.adjwgt.is_empty() {
+                    cut += self.adjncy[edges].iter().filter(|v| cut_edge(v)).count() as i128;
+                } else {
+                    for (v, &w) in self.adjncy[edges.clone()].iter().zip(&self.adjwgt[edges]) {
+                        if cut_edge(v) {
+                            cut += i128::from(w);
+                        }
+                    }
+                }
+            }
+            cut
+        };
+        let blocks = self.n.div_ceil(CUT_BLOCK);
+        #[cfg(feature = "rayon")]
+        let cut: i128 = {
+            use rayon::prelude::*;
+            (0..blocks).into_par_iter().map(block_cut).sum()
+        };
+        #[cfg(not(feature = "rayon"))]
+        let cut: i128 = (0..blocks).map(block_cut).sum();
+        i64::try_from(cut).map_err(|_| GraphError::WeightOverflow)
+    }
+
+    /// The graph with its vertices renumbered by `perm`.
+    ///
+    /// New vertex `i` is old vertex `perm[i]`, the convention of
+    /// [`crate::ordering::nested_dissection`], so `g.permuted(&order)` is the
+    /// reordered matrix. Neighbor lists keep their order with their entries
+    /// relabeled, and every weight array is permuted along with the
+    /// vertices. Panics unless `perm` lists each vertex of `0..n` exactly
+    /// once.
+    pub fn permuted(&self, perm: &[usize]) -> Graph {
+        assert_eq!(perm.len(), self.n, "permutation has the wrong length");
+        let mut iperm = vec![usize::MAX; self.n];
+        for (i, &u) in perm.iter().enumerate() {
+            assert!(
+                u < self.n && iperm[u] == usize::MAX,
+                "not a permutation: vertex {} is out of range or repeated",
+                u
+            );
+            iperm[u] = i;
+        }
+
+        let mut xadj = Vec::with_capacity(self.n + 1);
+        let mut adjncy = Vec::with_capacity(self.adjncy.len());
+        let mut adjwgt = Vec::with_capacity(self.adjwgt.len());
+        xadj.push(0);
+        for &u in perm {
+            adjncy.extend(self.neighbors(u).iter().map(|&v| iperm[v]));
+            adjwgt.extend_from_slice(self.edge_weights(u));
+            xadj.push(adjncy.len());
+        }
+        let pick = |w: &[i64]| -> Vec<i64> {
+            if w.is_empty() {
+                Vec::new()
+            } else {
+                perm.iter().map(|&u| w[u]).collect()
+            }
+        };
+        Graph {
+            n: self.n,
+            xadj,
+            adjncy,
+            adjwgt,
+            vwgt: pick(&self.vwgt),
+            vwgt_multi: if self.vwgt_multi.is_empty() {
+                Vec::new()
+            } else {
+                perm.iter().map(|&u| self.vwgt_multi[u].clone()).collect()
+            },
+            vsize: pick(&self.vsize),
+        }
+    }
+
+    /// Induced subgraph on `verts`, with the map from its vertices back to
+    /// this graph's.
+    ///
+    /// Local vertex `i` of the returned graph is `map[i] == verts[i]`. Edges
+    /// to vertices outside the subset are dropped, and every weight array is
+    /// carried over (vertex and edge weights come out explicit). Runs in
+    /// `O(n)` plus the degrees of `verts`. Panics unless the vertices are in
+    /// `0..n` and distinct.
+    pub fn induced_subgraph(&self, verts: &[usize]) -> (Graph, Vec<usize>) {
+        let sub = self.extract_subgraph(verts, |_, _, _| {});
+        (sub, verts.to_vec())
+    }
+
+    /// Induced subgraph on `verts`, plus the weight of the dropped edges.
+    ///
+    /// Local vertex `i` of the returned graph is `verts[i]`. Every edge from
+    /// `verts[i]` to a vertex outside the subset is accumulated into
+    /// [`GhostInfo`] under that vertex's part in `part`. Panics unless the
+    /// vertices are in `0..n` and distinct.
+    pub fn induced_subgraph_with_ghosts(
+        &self,
+        verts: &[usize],
+        part: &[usize],
+    ) -> (Graph, GhostInfo) {
+        let mut external: Vec<Vec<(usize, i64)>> = vec![Vec::new(); verts.len()];
+        let sub = self.extract_subgraph(verts, |i, v, w| {
+            match external[i].iter_mut().find(|(p, _)| *p == part[v]) {
+                Some((_, total)) => *total += w,
+                None => external[i].push((part[v], w)),
+            }
+        });
+
+        let mut ghosts = GhostInfo::default();
+        for (i, mut ext) in external.into_iter().enumerate() {
+            if !ext.is_empty() {
+                ext.sort_unstable();
+                ghosts.boundary.push(i);
+                ghosts.external.push(ext);
+            }
+        }
+        (sub, ghosts)
+    }
+
+    /// Induced subgraph on `verts`, calling `dropped(i, v, w)` for every
+    /// edge of weight `w` from `verts[i]` to a vertex `v` outside the subset.
+    fn extract_subgraph(
+        &self,
+        verts: &[usize],
+        mut dropped: impl FnMut(usize, usize, i64),
+    ) -> Graph {
+        let mut local = vec![usize::MAX; self.n];
+        for (i, &u) in verts.iter().enumerate() {
+            assert!(
+                u < self.n && local[u] == usize::MAX,
+                "subgraph vertex {} is out of range or repeated",
+                u
+            );
+            local[u] = i;
+        }
+
+        let mut xadj = vec![0usize; verts.len() + 1];
+        let mut adjncy = Vec::new();
+        let mut adjwgt = Vec::new();
+        let mut vwgt = Vec::with_capacity(verts.len());
+        let mut vwgt_multi = Vec::new();
+        let mut vsize = Vec::new();
+
+        for (i, &u) in verts.iter().enumerate() {
+            vwgt.push(self.vertex_weight(u));
+            if !self.vwgt_multi.is_empty() {
+                vwgt_multi.push(self.vwgt_multi[u].clone());
+            }
+            if !self.vsize.is_empty() {
+                vsize.push(self.vsize[u]);
+            }
+            for (v, w) in self.edges(u) {
+                if local[v] != usize::MAX {
+                    adjncy.push(local[v]);
+                    adjwgt.push(w);
+                } else {
+                    dropped(i, v, w);
+                }
+            }
+            xadj[i + 1] = adjncy.len();
+        }
+
+        Graph {
+            n: verts.len(),
+            xadj,
+            adjncy,
+            adjwgt,
+            vwgt,
+            vwgt_multi,
+            vsize,
+        }
+    }
+
+    /// Label each vertex with the ID of its connected component.
+    ///
+    /// Components are numbered `0..count` in order of their lowest vertex.
+    pub fn connected_components(&self) -> Vec<usize> {
+        self.connected_component_sizes().0
+    }
+
+    /// Label each vertex with the ID of its connected component, and count
+    /// the vertices of each component.
+    ///
+    /// Returns `(label, size)`: `label` is as in
+    /// [`Graph::connected_components`] and `size[c]` is the number of
+    /// vertices labeled `c`, so `size.len()` is the number of components.
+    pub fn connected_component_sizes(&self) -> (Vec<usize>, Vec<usize>) {
+        let mut label = vec![usize::MAX; self.n];
+        let mut size = Vec::new();
+        let mut queue = std::collections::VecDeque::new();
+        for s in 0..self.n {
+            if label[s] != usize::MAX {
+                continue;
+            }
+            let c = size.len();
+            let mut count = 1usize;
+            label[s] = c;
+            queue.push_back(s);
+            while let Some(u) = queue.pop_front() {
+                for &v in self.neighbors(u) {
+                    if label[v] == usize::MAX {
+                        label[v] = c;
+                        count += 1;
+                        queue.push_back(v);
+                    }
                 }
             }
+            size.push(count);
         }
-        cut / 2 // each edge counted twice
+        (label, size)
     }
 }
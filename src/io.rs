@@ -0,0 +1,464 @@
+//! Reading and writing graphs and partitions in the METIS text formats, and
+//! reading sparse matrices in the Matrix Market coordinate format.
+//!
+//! A METIS graph file starts with a header line `n m [fmt] [ncon]`, where
+//! `m` is the number of undirected edges and the optional `fmt` flags select
+//! vertex sizes (`100`), vertex weights (`10`) and/or edge weights (`1`).
+//! Line `i` of the body then lists vertex `i`'s optional size and `ncon`
+//! weights followed by its 1-based neighbors, each optionally followed by the
+//! edge weight. Lines starting with `%` are comments.
+//!
+//! A partition file, as written by `gpmetis`, holds one 0-based part ID per
+//! line, line `i` giving the part of vertex `i`.
+
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use crate::graph::{Graph, GraphError, Symmetrize};
+
+/// Most entries reserved up front from counts in a file header, which may
+/// be wrong; larger inputs grow as their lines are read.
+const MAX_RESERVE: usize = 1 << 20;
+
+/// Reason a METIS graph file could not be read.
+///
+/// Line numbers are 1-based and count comment lines.
+#[derive(Debug)]
+pub enum ParseError {
+    /// Reading from the underlying reader failed.
+    Io(io::Error),
+    /// The input has no header line.
+    MissingHeader,
+    /// The header does not have the form `n m [fmt] [ncon]`.
+    InvalidHeader { line: usize },
+    /// `token` is not a valid integer.
+    InvalidNumber { line: usize, token: String },
+    /// A vertex line ends before all of its weights or an edge weight.
+    MissingField { line: usize },
+    /// A neighbor index is not in `1..=n`.
+    NeighborOutOfRange { line: usize, neighbor: usize },
+    /// The body has a different number of vertex lines than the header.
+    VertexCount { expected: usize, actual: usize },
+    /// The body lists a different number of edges (or, in a Matrix Market
+    /// file, of entries) than the header declares.
+    EdgeCount { expected: usize, actual: usize },
+    /// The parsed arrays do not form a valid graph.
+    Graph(GraphError),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Io(e) => write!(f, "read error: {}", e),
+            ParseError::MissingHeader => write!(f, "missing header line"),
+            ParseError::InvalidHeader { line } => write!(f, "line {}: invalid header", line),
+            ParseError::InvalidNumber { line, token } => {
+                write!(f, "line {}: invalid number {:?}", line, token)
+            }
+            ParseError::MissingField { line } => {
+                write!(f, "line {}: missing weight field", line)
+            }
+            ParseError::NeighborOutOfRange { line, neighbor } => {
+                write!(f, "line {}: neighbor {} out of range", line, neighbor)
+            }
+            ParseError::VertexCount { expected, actual } => {
+                write!(
+                    f,
+                    "found {} vertex lines, header declares {}",
+                    actual, expected
+                )
+            }
+            ParseError::EdgeCount { expected, actual } => {
+                write!(f, "found {} edges, header declares {}", actual, expected)
+            }
+            ParseError::Graph(e) => write!(f, "invalid graph: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::Io(e) => Some(e),
+            ParseError::Graph(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ParseError {
+    fn from(e: io::Error) -> Self {
+        ParseError::Io(e)
+    }
+}
+
+impl From<GraphError> for ParseError {
+    fn from(e: GraphError) -> Self {
+        ParseError::Graph(e)
+    }
+}
+
+/// Read a partition file: one 0-based part ID per line.
+///
+/// Blank lines and lines starting with `%` are skipped. A line holding
+/// anything other than a single non-negative integer is rejected with
+/// [`ParseError::InvalidNumber`].
+pub fn read_partition<R: BufRead>(r: R) -> Result<Vec<usize>, ParseError> {
+    let mut part = Vec::new();
+    for (i, line) in r.lines().enumerate() {
+        let line = line?;
+        let token = line.trim();
+        if token.is_empty() || token.starts_with('%') {
+            continue;
+        }
+        part.push(token.parse().map_err(|_| ParseError::InvalidNumber {
+            line: i + 1,
+            token: token.to_string(),
+        })?);
+    }
+    Ok(part)
+}
+
+/// Write a partition file: `part[i]` on line `i + 1`.
+pub fn write_partition<W: Write>(part: &[usize], mut w: W) -> io::Result<()> {
+    for p in part {
+        writeln!(w, "{}", p)?;
+    }
+    Ok(())
+}
+
+/// Parse every whitespace-separated token of `line` as a `T`.
+fn parse_fields<T: std::str::FromStr>(line: &str, lineno: usize) -> Result<Vec<T>, ParseError> {
+    line.split_whitespace()
+        .map(|tok| {
+            tok.parse().map_err(|_| ParseError::InvalidNumber {
+                line: lineno,
+                token: tok.to_string(),
+            })
+        })
+        .collect()
+}
+
+impl Graph {
+    /// Parse a graph in the METIS text format.
+    ///
+    /// Neighbor indices are read 1-based and stored 0-based. Vertex sizes
+    /// (`fmt` flag `100`) go to `vsize`. With `ncon > 1` the
+    /// vertex weights go to `vwgt_multi`, otherwise to `vwgt`. Each edge must
+    /// appear in both endpoints' lines, so the body holds exactly `2 * m`
+    /// neighbor entries; anything else is rejected with
+    /// [`ParseError::EdgeCount`].
+    pub fn from_metis_reader<R: BufRead>(r: R) -> Result<Graph, ParseError> {
+        let mut lines = r
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line))
+            .filter(|(_, line)| !matches!(line, Ok(l) if l.starts_with('%')));
+
+        // Header: the first non-comment, non-blank line
+        let (header_line, header) = loop {
+            match lines.next() {
+                None => return Err(ParseError::MissingHeader),
+                Some((i, line)) => {
+                    let line = line?;
+                    if !line.trim().is_empty() {
+                        break (i, line);
+                    }
+                }
+            }
+        };
+        let fields: Vec<usize> = parse_fields(&header, header_line)?;
+        if fields.len() < 2 || fields.len() > 4 {
+            return Err(ParseError::InvalidHeader { line: header_line });
+        }
+        let (n, m) = (fields[0], fields[1]);
+        // n + 1 row pointers and 2 * m neighbor entries must be addressable
+        let Some(entries) = m.checked_mul(2).filter(|_| n < usize::MAX) else {
+            return Err(ParseError::InvalidHeader { line: header_line });
+        };
+        let fmt = fields.get(2).copied().unwrap_or(0);
+        if fmt > 111 || fmt % 10 > 1 || fmt / 10 % 10 > 1 {
+            return Err(ParseError::InvalidHeader { line: header_line });
+        }
+        let has_vsize = fmt >= 100;
+        let has_vwgt = fmt / 10 % 10 == 1;
+        let has_adjwgt = fmt % 10 == 1;
+        let ncon = match fields.get(3) {
+            Some(&0) => return Err(ParseError::InvalidHeader { line: header_line }),
+            Some(&c) => c,
+            None => 1,
+        };
+
+        let mut xadj = Vec::with_capacity(n.min(MAX_RESERVE) + 1);
+        xadj.push(0);
+        let mut adjncy = Vec::with_capacity(entries.min(MAX_RESERVE));
+        let mut adjwgt = Vec::new();
+        let mut vwgt_multi: Vec<Vec<i64>> = Vec::new();
+        let mut vsize = Vec::new();
+
+        let mut vertex_lines = 0usize;
+        for (lineno, line) in lines {
+            let line = line?;
+            if vertex_lines == n {
+                // Only blank lines may follow the last vertex
+                if line.trim().is_empty() {
+                    continue;
+                }
+                vertex_lines += 1;
+                break;
+            }
+            vertex_lines += 1;
+
+            let values: Vec<i64> = parse_fields(&line, lineno)?;
+            let mut rest = &values[..];
+            if has_vsize {
+                let (&size, tail) = rest
+                    .split_first()
+                    .ok_or(ParseError::MissingField { line: lineno })?;
+                vsize.push(size);
+                rest = tail;
+            }
+            if has_vwgt {
+                let w = rest
+                    .get(..ncon)
+                    .ok_or(ParseError::MissingField { line: lineno })?;
+                vwgt_multi.push(w.to_vec());
+                rest = &rest[ncon..];
+            }
+
+            let stride = if has_adjwgt { 2 } else { 1 };
+            if !rest.len().is_multiple_of(stride) {
+                return Err(ParseError::MissingField { line: lineno });
+            }
+            for entry in rest.chunks(stride) {
+                let neighbor = entry[0];
+                if neighbor < 1 || neighbor as usize > n {
+                    return Err(ParseError::NeighborOutOfRange {
+                        line: lineno,
+                        neighbor: neighbor.max(0) as usize,
+                    });
+                }
+                adjncy.push(neighbor as usize - 1);
+                if has_adjwgt {
+                    adjwgt.push(entry[1]);
+                }
+            }
+            xadj.push(adjncy.len());
+        }
+        if vertex_lines != n {
+            return Err(ParseError::VertexCount {
+                expected: n,
+                actual: vertex_lines,
+            });
+        }
+        if adjncy.len() != entries {
+            return Err(ParseError::EdgeCount {
+                expected: m,
+                actual: adjncy.len() / 2,
+            });
+        }
+
+        let mut g = Graph::try_new(n, xadj, adjncy)?;
+        if has_adjwgt {
+            g = g.try_with_adjwgt(adjwgt)?;
+        }
+        if has_vwgt {
+            if ncon == 1 {
+                g = g.try_with_vwgt(vwgt_multi.into_iter().map(|w| w[0]).collect())?;
+            } else {
+                g = g.try_with_vwgt_multi(vwgt_multi)?;
+            }
+        }
+        if has_vsize {
+            g = g.try_with_vsize(vsize)?;
+        }
+        Ok(g)
+    }
+
+    /// Read the graph of a square sparse matrix in the Matrix Market
+    /// coordinate format.
+    ///
+    /// Vertex `i` is row and column `i`, and every off-diagonal entry
+    /// `(i, j)` becomes an edge; the diagonal is dropped. Entries of a
+    /// `general` matrix are symmetrized with [`Symmetrize::Union`], and
+    /// `symmetric` and `skew-symmetric` matrices, which store one triangle,
+    /// are mirrored. Repeated entries are summed.
+    ///
+    /// With `weight_scale` of `None` every edge has weight 1 and the values
+    /// are ignored. With `Some(scale)` an entry of value `x` weighs
+    /// `round(|x| * scale)`, but at least 1 so that no stored entry loses
+    /// its edge; `pattern` matrices then weigh 1 per entry. `complex`
+    /// matrices, the dense `array` format and non-square matrices are
+    /// rejected with [`ParseError::InvalidHeader`].
+    pub fn from_matrix_market<R: BufRead>(
+        r: R,
+        weight_scale: Option<f64>,
+    ) -> Result<Graph, ParseError> {
+        let mut lines = r.lines().enumerate().map(|(i, line)| (i + 1, line));
+
+        let (banner_line, banner) = match lines.next() {
+            Some((i, line)) => (i, line?.to_ascii_lowercase()),
+            None => return Err(ParseError::MissingHeader),
+        };
+        let words: Vec<&str> = banner.split_whitespace().collect();
+        let (pattern, mirror) = match words[..] {
+            ["%%matrixmarket", "matrix", "coordinate", field, symmetry] => {
+                let pattern = match field {
+                    "real" | "integer" => false,
+                    "pattern" => true,
+                    _ => return Err(ParseError::InvalidHeader { line: banner_line }),
+                };
+                let mirror = match symmetry {
+                    "general" => false,
+                    "symmetric" | "skew-symmetric" => true,
+                    _ => return Err(ParseError::InvalidHeader { line: banner_line }),
+                };
+                (pattern, mirror)
+            }
+            _ => return Err(ParseError::InvalidHeader { line: banner_line }),
+        };
+        let mut lines = lines.filter(
+            |(_, line)| !matches!(line, Ok(l) if l.starts_with('%') || l.trim().is_empty()),
+        );
+
+        // Size line: rows columns entries
+        let (size_line, size) = lines.next().ok_or(ParseError::MissingHeader)?;
+        let fields: Vec<usize> = parse_fields(&size?, size_line)?;
+        let (n, nnz) = match fields[..] {
+            // n + 1 row pointers must be addressable
+            [rows, cols, nnz] if rows == cols && rows < usize::MAX => (rows, nnz),
+            _ => return Err(ParseError::InvalidHeader { line: size_line }),
+        };
+
+        let mut directed: Vec<(usize, usize, i64)> =
+            Vec::with_capacity(nnz.min(MAX_RESERVE / 2) * 2);
+        let mut entries = 0usize;
+        for (lineno, line) in lines {
+            let line = line?;
+            let mut tokens = line.split_whitespace();
+            let mut index = || -> Result<usize, ParseError> {
+                let tok = tokens
+                    .next()
+                    .ok_or(ParseError::MissingField { line: lineno })?;
+                let i: usize = tok.parse().map_err(|_| ParseError::InvalidNumber {
+                    line: lineno,
+                    token: tok.to_string(),
+                })?;
+                if i < 1 || i > n {
+                    return Err(ParseError::NeighborOutOfRange {
+                        line: lineno,
+                        neighbor: i,
+                    });
+                }
+                Ok(i - 1)
+            };
+            let (u, v) = (index()?, index()?);
+            let w = match weight_scale {
+                Some(scale) if !pattern => {
+                    let tok = tokens
+                        .next()
+                        .ok_or(ParseError::MissingField { line: lineno })?;
+                    let x: f64 = tok.parse().map_err(|_| ParseError::InvalidNumber {
+                        line: lineno,
+                        token: tok.to_string(),
+                    })?;
+                    (x.abs() * scale).round().max(1.0) as i64
+                }
+                _ => 1,
+            };
+            entries += 1;
+            if u != v {
+                directed.push((u, v, w));
+                if mirror {
+                    directed.push((v, u, w));
+                }
+            }
+        }
+        if entries != nnz {
+            return Err(ParseError::EdgeCount {
+                expected: nnz,
+                actual: entries,
+            });
+        }
+
+        directed.sort_unstable_by_key(|&(u, v, _)| (u, v));
+        let mut xadj = vec![0usize; n + 1];
+        for &(u, _, _) in &directed {
+            xadj[u + 1] += 1;
+        }
+        for u in 0..n {
+            xadj[u + 1] += xadj[u];
+        }
+        let adjncy = directed.iter().map(|&(_, v, _)| v).collect();
+        let adjwgt = directed.iter().map(|&(_, _, w)| w).collect();
+        let mut g = Graph::try_new(n, xadj, adjncy)?
+            .try_with_adjwgt(adjwgt)?
+            .symmetrized(Symmetrize::Union);
+        if weight_scale.is_none() {
+            g.adjwgt = Vec::new();
+        }
+        Ok(g)
+    }
+
+    /// Parse a graph from a string in the METIS text format.
+    ///
+    /// See [`Graph::from_metis_reader`].
+    pub fn from_metis_str(s: &str) -> Result<Graph, ParseError> {
+        Self::from_metis_reader(s.as_bytes())
+    }
+
+    /// Serialize the graph to the METIS text format.
+    ///
+    /// See [`Graph::write_metis`].
+    pub fn to_metis_string(&self) -> String {
+        let mut buf = Vec::new();
+        self.write_metis(&mut buf)
+            .expect("writing to a Vec<u8> cannot fail");
+        String::from_utf8(buf).expect("METIS output is ASCII")
+    }
+
+    /// Write the graph in the METIS text format.
+    ///
+    /// Neighbor indices are written 1-based. The `fmt` field is derived from
+    /// which arrays are present: it is omitted for unweighted graphs and
+    /// otherwise sums `100` (vertex sizes), `10` (vertex weights) and `1`
+    /// (edge weights). Multi-constraint weights are written as `ncon` vertex
+    /// weights per line, with `ncon` added to the header.
+    pub fn write_metis<W: Write>(&self, mut w: W) -> io::Result<()> {
+        let multi = !self.vwgt_multi.is_empty();
+        let has_vsize = !self.vsize.is_empty();
+        let has_vwgt = multi || !self.vwgt.is_empty();
+        let has_adjwgt = !self.adjwgt.is_empty();
+
+        write!(w, "{} {}", self.n, self.adjncy.len() / 2)?;
+        let fmt =
+            100 * usize::from(has_vsize) + 10 * usize::from(has_vwgt) + usize::from(has_adjwgt);
+        if fmt > 0 {
+            write!(w, " {}", fmt)?;
+        }
+        if multi {
+            write!(w, " {}", self.ncon())?;
+        }
+        writeln!(w)?;
+
+        for u in 0..self.n {
+            let mut fields: Vec<String> = Vec::new();
+            if has_vsize {
+                fields.push(self.vsize[u].to_string());
+            }
+            if multi {
+                fields.extend(self.vwgt_multi[u].iter().map(|w| w.to_string()));
+            } else if has_vwgt {
+                fields.push(self.vwgt[u].to_string());
+            }
+            for (v, w) in self.edges(u) {
+                fields.push((v + 1).to_string());
+                if has_adjwgt {
+                    fields.push(w.to_string());
+                }
+            }
+            writeln!(w, "{}", fields.join(" "))?;
+        }
+        Ok(())
+    }
+}
@@ -3,16 +3,79 @@
 //! This is the main entry point that orchestrates coarsening, initial
 //! partitioning, projection, and refinement.
 
-use crate::coarsen::multilevel_coarsen;
+use std::time::{Duration, Instant};
+
+use crate::coarsen::{coarse_fixed, hierarchy};
+use crate::config::PartitionConfig;
 use crate::graph::Graph;
-use crate::partition::initial_partition;
-use crate::refine::fm_refine;
+use crate::partition::{bubble_kway, greedy_kway, partition_recursive, InitialMethod, Recursion};
+use crate::refine::{
+    enforce_contiguity, excess_weight, flow_passes, rebalance, refine_passes,
+    refine_passes_observed, Objective, PassConfig,
+};
+use crate::workspace::Workspace;
 
 /// Default coarsening threshold: stop when graph has this many vertices or fewer.
-const COARSEN_THRESHOLD: usize = 20;
+pub(crate) const COARSEN_THRESHOLD: usize = 20;
+
+/// Default coarsest graph size per part: the pipeline stops coarsening at
+/// this many vertices per part, if that is above the threshold.
+pub(crate) const COARSEN_PER_PART: usize = 10;
 
 /// Default number of FM refinement passes per level.
-const REFINE_PASSES: usize = 10;
+pub(crate) const REFINE_PASSES: usize = 10;
+
+/// FM passes given to each initial partition when several are compared
+/// (see [`PartitionConfig::ncuts`]).
+const INITIAL_CUT_PASSES: usize = 2;
+
+/// Wall-clock time spent in each phase of [`part_kway_timed`].
+///
+/// Timings are informational only and never influence the partition.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Timings {
+    /// Time spent building the coarsening hierarchy.
+    pub coarsen: Duration,
+    /// Time spent partitioning the coarsest graph.
+    pub initial_partition: Duration,
+    /// Time spent in FM refinement, summed over all levels.
+    pub refine: Duration,
+    /// Total time for the whole call.
+    pub total: Duration,
+}
+
+/// Uncoarsening levels at which the multilevel pipeline runs FM refinement.
+///
+/// Levels are numbered as in [`part_kway_observed`]: level 0 is the input
+/// graph and the highest level the coarsest graph. Skipped levels are still
+/// rebalanced, only their FM passes are left out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RefineLevels {
+    /// Refine every level.
+    #[default]
+    All,
+    /// Refine the coarsest level and every second level below it, plus the
+    /// input graph.
+    EveryOther,
+    /// Refine only the input graph.
+    FinestOnly,
+    /// Refine the given number of coarsest levels and the input graph.
+    CoarsestAndFinest(usize),
+}
+
+impl RefineLevels {
+    /// Whether `level` is refined when `coarsest` is the coarsest level.
+    fn refines(self, level: usize, coarsest: usize) -> bool {
+        let from_top = coarsest - level;
+        level == 0
+            || match self {
+                RefineLevels::All => true,
+                RefineLevels::EveryOther => from_top.is_multiple_of(2),
+                RefineLevels::FinestOnly => false,
+                RefineLevels::CoarsestAndFinest(k) => from_top < k,
+            }
+    }
+}
 
 /// Partition a graph into `nparts` parts using multilevel k-way partitioning.
 ///
@@ -22,58 +85,530 @@ const REFINE_PASSES: usize = 10;
 /// # Algorithm
 ///
 /// 1. **Coarsening**: Repeatedly contract the graph via heavy-edge matching
-///    until it has fewer than `COARSEN_THRESHOLD` vertices.
+///    until it has at most `max(COARSEN_THRESHOLD, COARSEN_PER_PART * nparts)`
+///    vertices, or becomes too dense to coarsen usefully.
 /// 2. **Initial partitioning**: Partition the small coarsened graph using
 ///    recursive greedy graph growing.
 /// 3. **Uncoarsening + refinement**: Project the partition back through each
 ///    coarsening level, running FM boundary refinement at each step.
 pub fn part_kway(g: &Graph, nparts: usize) -> (i64, Vec<usize>) {
+    let (cut, part, _) = part_kway_timed(g, nparts);
+    (cut, part)
+}
+
+/// Partition a graph into `nparts` parts of unequal target sizes.
+///
+/// Part `p` aims for `tpwgts[p]` of the total vertex weight, e.g.
+/// `[0.5, 0.25, 0.25]` gives part 0 half of the weight. `tpwgts` must have
+/// `nparts` positive entries and is used relative to its sum.
+pub fn part_kway_tpwgts(g: &Graph, nparts: usize, tpwgts: &[f64]) -> (i64, Vec<usize>) {
+    let config = PartitionConfig::default().nparts(nparts).tpwgts(tpwgts);
+    config.validate(g);
+    let (cut, part, _) = kway(g, &config, None, &mut Workspace::new());
+    (cut, part)
+}
+
+/// Partition a graph into `nparts` parts with randomized coarsening.
+///
+/// Coarsening visits vertices in an order shuffled by `seed`. The same seed
+/// always yields the same partition, while different seeds explore different
+/// matchings, so several seeds can be tried and the best result kept.
+pub fn part_kway_seeded(g: &Graph, nparts: usize, seed: u64) -> (i64, Vec<usize>) {
+    let config = PartitionConfig::default().nparts(nparts);
+    let (cut, part, _) = kway(g, &config, Some(seed), &mut Workspace::new());
+    (cut, part)
+}
+
+/// Partition a graph into `nparts` parts, refining for `objective`.
+///
+/// Coarsening and the initial partition are the same as in [`part_kway`];
+/// every refinement level then minimizes the chosen [`Objective`].
+/// `Objective::EdgeCut` gives exactly [`part_kway`]'s result. The returned
+/// value is always the edge cut; use [`Graph::partition_stats`] for the
+/// communication volume.
+pub fn part_kway_objective(g: &Graph, nparts: usize, objective: Objective) -> (i64, Vec<usize>) {
+    let config = PartitionConfig::default()
+        .nparts(nparts)
+        .objective(objective);
+    let (cut, part, _) = kway(g, &config, None, &mut Workspace::new());
+    (cut, part)
+}
+
+/// Partition a graph into `nparts` parts with some vertices pre-assigned.
+///
+/// Whenever `fixed[u] == Some(k)`, the result has `partition[u] == k`; only
+/// the remaining vertices are placed by the partitioner. Vertices pinned to
+/// different parts are never merged during coarsening, the initial partition
+/// keeps every pin, and refinement never moves a pinned vertex. `fixed` must
+/// have one entry per vertex, each pinned part below `nparts`.
+pub fn part_kway_fixed(g: &Graph, nparts: usize, fixed: &[Option<usize>]) -> (i64, Vec<usize>) {
+    let config = PartitionConfig::default().nparts(nparts).fixed(fixed);
+    config.validate(g);
+    let (cut, part, _) = kway(g, &config, None, &mut Workspace::new());
+    (cut, part)
+}
+
+/// Refine an existing partition, trading edge cut against migration.
+///
+/// Skips coarsening and the initial partition: `initial` is refined on `g`
+/// directly by FM passes whose move gains are reduced by `migration_weight`
+/// times the vertex weight for every vertex moved off its `initial` part
+/// (and raised by the same amount for moving one back). A weight of `0.0`
+/// is plain refinement, as by [`crate::refine::fm_refine`], while a large
+/// weight leaves almost every vertex in place. Moves keep the usual 5%
+/// balance tolerance but overweight parts are not actively rebalanced.
+///
+/// Returns `(edge_cut, partition)`. Panics if `initial` does not have one
+/// part below `nparts` per vertex or `migration_weight` is negative.
+pub fn repartition(
+    g: &Graph,
+    nparts: usize,
+    initial: &[usize],
+    migration_weight: f64,
+) -> (i64, Vec<usize>) {
+    assert_eq!(initial.len(), g.n, "initial must have one entry per vertex");
+    assert!(
+        initial.iter().all(|&p| p < nparts),
+        "initial part out of range"
+    );
+    assert!(
+        migration_weight >= 0.0,
+        "migration_weight must be non-negative"
+    );
+    let mut part = initial.to_vec();
+    let cfg = PassConfig {
+        origin: initial,
+        migration_weight,
+        ..PassConfig::default()
+    };
+    refine_passes(g, &mut part, nparts, REFINE_PASSES, &cfg);
+    (g.edge_cut(&part), part)
+}
+
+/// Partition a graph into `nparts` parts without splitting small components.
+///
+/// A single-component graph is partitioned exactly as by [`part_kway`].
+/// Otherwise, with `W = total_weight / nparts`:
+///
+/// 1. Each component heavier than `W` gets `floor(weight / W)` parts of its
+///    own; spare parts then go one at a time to the component with the
+///    heaviest average part, and each is partitioned independently.
+/// 2. The remaining components are packed whole, heaviest first, into the
+///    currently lightest part.
+///
+/// Small components are never split, so parts can stay empty when there are
+/// fewer small components than spare parts.
+pub fn part_kway_components(g: &Graph, nparts: usize) -> (i64, Vec<usize>) {
+    let (comp, size) = g.connected_component_sizes();
+    let ncomp = size.len();
+    if ncomp <= 1 || nparts <= 1 {
+        return part_kway(g, nparts);
+    }
+
+    let mut members: Vec<Vec<usize>> = size.iter().map(|&s| Vec::with_capacity(s)).collect();
+    let mut comp_weight = vec![0i64; ncomp];
+    for u in 0..g.n {
+        members[comp[u]].push(u);
+        comp_weight[comp[u]] += g.vertex_weight(u);
+    }
+    let total_weight: i64 = comp_weight.iter().sum();
+    let ideal = total_weight as f64 / nparts as f64;
+
+    // Parts allotted to each large component
+    let mut alloc = vec![0usize; ncomp];
+    for c in 0..ncomp {
+        if comp_weight[c] as f64 > ideal {
+            alloc[c] = (comp_weight[c] as f64 / ideal).floor() as usize;
+        }
+    }
+    let mut spare = nparts - alloc.iter().sum::<usize>();
+    while spare > 0 {
+        let heaviest = (0..ncomp).filter(|&c| alloc[c] > 0).max_by(|&a, &b| {
+            let wa = comp_weight[a] as f64 / alloc[a] as f64;
+            let wb = comp_weight[b] as f64 / alloc[b] as f64;
+            wa.total_cmp(&wb).then(b.cmp(&a))
+        });
+        match heaviest {
+            Some(c) if comp_weight[c] as f64 / alloc[c] as f64 > ideal => {
+                alloc[c] += 1;
+                spare -= 1;
+            }
+            _ => break,
+        }
+    }
+
+    let mut part = vec![0usize; g.n];
+    let mut part_weight = vec![0i64; nparts];
+    let mut next_part = 0usize;
+    for c in 0..ncomp {
+        if alloc[c] == 0 {
+            continue;
+        }
+        let sub = g.induced_subgraph(&members[c]).0;
+        let (_, sub_part) = part_kway(&sub, alloc[c]);
+        for (local, &u) in members[c].iter().enumerate() {
+            part[u] = next_part + sub_part[local];
+            part_weight[part[u]] += g.vertex_weight(u);
+        }
+        next_part += alloc[c];
+    }
+
+    let mut small: Vec<usize> = (0..ncomp).filter(|&c| alloc[c] == 0).collect();
+    small.sort_by_key(|&c| (std::cmp::Reverse(comp_weight[c]), c));
+    for c in small {
+        let lightest = (0..nparts).min_by_key(|&p| (part_weight[p], p)).unwrap();
+        for &u in &members[c] {
+            part[u] = lightest;
+        }
+        part_weight[lightest] += comp_weight[c];
+    }
+
+    let cut = g.edge_cut(&part);
+    (cut, part)
+}
+
+/// Run [`part_kway_seeded`] with seeds `seed..seed + ntrials` and keep the
+/// best result.
+///
+/// Returns `(edge_cut, partition, trial)` for the lowest edge cut, where
+/// trial `trial` ran with seed `seed + trial` (wrapping). Ties are broken by
+/// the lighter heaviest part, then by the earlier trial. `ntrials == 0` is
+/// treated as 1, so a single trial is exactly
+/// `part_kway_seeded(g, nparts, seed)`. Another `seed` gives another set of
+/// independent trials.
+///
+/// With the `rayon` feature the trials run in parallel. This is the same as
+/// [`PartitionConfig::seed`] with [`PartitionConfig::ntrials`].
+pub fn part_kway_best_of(
+    g: &Graph,
+    nparts: usize,
+    ntrials: usize,
+    seed: u64,
+) -> (i64, Vec<usize>, usize) {
+    let config = PartitionConfig::default()
+        .nparts(nparts)
+        .seed(seed)
+        .ntrials(ntrials);
+    best_of(g, &config, &mut Workspace::new())
+}
+
+/// Run `config.ntrials` trials of [`kway`] and keep the best.
+///
+/// With a single trial the configured seed (or none) is used as is.
+/// Otherwise trial `t` uses seed `base + t`, with `base` the configured seed
+/// or 0. Trials are ranked by the configured objective, then by the lighter
+/// heaviest part, then by the earlier trial, even when `rayon` finishes
+/// them out of order. Returns `(edge_cut, partition, trial)`. Trials run
+/// one after another reuse `workspace`.
+pub(crate) fn best_of(
+    g: &Graph,
+    config: &PartitionConfig,
+    workspace: &mut Workspace,
+) -> (i64, Vec<usize>, usize) {
+    let ntrials = config.ntrials.max(1);
+    if ntrials == 1 {
+        let (cut, part, _) = kway(g, config, config.seed, workspace);
+        return (cut, part, 0);
+    }
+
+    let base = config.seed.unwrap_or(0);
+    // Trials are independent, so the `rayon` feature runs them in parallel;
+    // the trial index in the key keeps the winner the same either way
+    let run = |workspace: &mut Workspace, trial: usize| {
+        let seed = Some(base.wrapping_add(trial as u64));
+        let (cut, part, _) = kway(g, config, seed, workspace);
+        let score = match config.objective {
+            Objective::EdgeCut => cut,
+            Objective::CommVolume => g.partition_stats(&part, config.nparts).comm_volume,
+        };
+        let max_weight = max_part_weight(g, &part, config.nparts);
+        (score, max_weight, trial, cut, part)
+    };
+    #[cfg(feature = "rayon")]
+    let best = {
+        use rayon::prelude::*;
+        (0..ntrials)
+            .into_par_iter()
+            .map_init(Workspace::new, run)
+            .min_by_key(|&(score, max_weight, trial, ..)| (score, max_weight, trial))
+    };
+    #[cfg(not(feature = "rayon"))]
+    let best = (0..ntrials)
+        .map(|trial| run(workspace, trial))
+        .min_by_key(|&(score, max_weight, trial, ..)| (score, max_weight, trial));
+    let (_, _, trial, cut, part) = best.expect("at least one trial runs");
+    (cut, part, trial)
+}
+
+/// Weight of the heaviest part of `part`.
+fn max_part_weight(g: &Graph, part: &[usize], nparts: usize) -> i64 {
+    let mut weights = vec![0i64; nparts.max(1)];
+    for u in 0..g.n {
+        weights[part[u]] += g.vertex_weight(u);
+    }
+    weights.into_iter().max().unwrap_or(0)
+}
+
+/// Same as [`part_kway`], additionally returning per-phase [`Timings`].
+///
+/// Intended for performance regression tracking across graph sizes.
+pub fn part_kway_timed(g: &Graph, nparts: usize) -> (i64, Vec<usize>, Timings) {
+    kway(
+        g,
+        &PartitionConfig::default().nparts(nparts),
+        None,
+        &mut Workspace::new(),
+    )
+}
+
+/// Same as [`part_kway`], reporting every refinement pass to `observer`.
+///
+/// `observer(level, pass, edge_cut, moves)` is called after each FM pass
+/// with the level being refined, the 0-based pass index within that level,
+/// the edge cut of that level's graph after the pass, and the number of
+/// moves the pass kept. Level 0 is `g` itself and level `i` the graph after
+/// `i` coarsening steps; reports arrive from the coarsest level down to 0.
+/// The partition is the same as [`part_kway`]'s.
+pub fn part_kway_observed(
+    g: &Graph,
+    nparts: usize,
+    observer: &mut dyn FnMut(usize, usize, i64, usize),
+) -> (i64, Vec<usize>) {
+    let config = PartitionConfig::default().nparts(nparts);
+    let (cut, part, _) = kway_observed(g, &config, None, Some(observer), &mut Workspace::new());
+    (cut, part)
+}
+
+/// Shared multilevel pipeline behind the public k-way entry points.
+///
+/// Runs a single trial of `config`, ignoring its `seed` and `ntrials`:
+/// coarsening is randomized only when `seed` is given. Working arrays come
+/// from `workspace`. The config must already be validated against `g`.
+fn kway(
+    g: &Graph,
+    config: &PartitionConfig,
+    seed: Option<u64>,
+    workspace: &mut Workspace,
+) -> (i64, Vec<usize>, Timings) {
+    kway_observed(g, config, seed, None, workspace)
+}
+
+/// [`kway`], reporting refinement passes as in [`part_kway_observed`].
+pub(crate) fn kway_observed(
+    g: &Graph,
+    config: &PartitionConfig,
+    seed: Option<u64>,
+    mut observer: Option<&mut dyn FnMut(usize, usize, i64, usize)>,
+    workspace: &mut Workspace,
+) -> (i64, Vec<usize>, Timings) {
+    let nparts = config.nparts;
+    let fixed = &config.fixed[..];
+    let tpwgts = if config.tpwgts.is_empty() {
+        None
+    } else {
+        Some(&config.tpwgts[..])
+    };
+
+    let start = Instant::now();
+    let mut timings = Timings::default();
+
     if g.n == 0 {
-        return (0, Vec::new());
+        timings.total = start.elapsed();
+        return (0, Vec::new(), timings);
     }
     if nparts <= 1 {
-        return (0, vec![0; g.n]);
+        timings.total = start.elapsed();
+        return (0, vec![0; g.n], timings);
     }
     if g.n <= nparts {
-        let part: Vec<usize> = (0..g.n).collect();
+        let part: Vec<usize> = (0..g.n)
+            .map(|u| fixed.get(u).copied().flatten().unwrap_or(u))
+            .collect();
         let cut = g.edge_cut(&part);
-        return (cut, part);
+        timings.total = start.elapsed();
+        return (cut, part, timings);
     }
 
     // Phase 1: Coarsen
-    let levels = multilevel_coarsen(g, COARSEN_THRESHOLD.max(nparts * 2));
+    let t = Instant::now();
+    let mut levels = hierarchy(g, config, seed, &mut workspace.coarsen).into_levels();
+    // fixed_levels[i] holds the pins of the graph that levels[i] coarsens
+    let mut fixed_levels = vec![fixed.to_vec()];
+    for level in &levels {
+        let cfixed = coarse_fixed(fixed_levels.last().unwrap(), level);
+        fixed_levels.push(cfixed);
+    }
+    timings.coarsen = t.elapsed();
 
     // Phase 2: Initial partition of the coarsest graph
-    let coarsest = if levels.is_empty() {
-        g.clone()
+    let coarsest = levels.last().map_or(g, |l| &l.graph);
+
+    let t = Instant::now();
+    let coarsest_fixed = fixed_levels.last().unwrap();
+    let pass_config = |fixed| PassConfig {
+        tpwgts: &config.tpwgts,
+        fixed,
+        mode: config.refine_mode,
+        objective: config.objective,
+        imbalance: config.imbalance,
+        ubvec: &config.ubvec,
+        min_weight: config.min_part_weight,
+        minconn: config.minconn,
+        seed: seed.unwrap_or(0),
+        min_improvement: config.min_improvement,
+        ..PassConfig::default()
+    };
+    let initial = |cut_seed| match config.initial_method {
+        InitialMethod::RecursiveBisection | InitialMethod::Spectral => partition_recursive(
+            coarsest,
+            nparts,
+            tpwgts,
+            coarsest_fixed,
+            &Recursion {
+                min_weight: config.min_part_weight,
+                imbalance: config.imbalance,
+                ubvec: &config.ubvec,
+                spectral: config.initial_method == InitialMethod::Spectral,
+                seed: cut_seed,
+                seeds: config.bisection_seeds,
+                strategy: config.seed_strategy,
+            },
+        ),
+        InitialMethod::GreedyKway => {
+            greedy_kway(coarsest, nparts, tpwgts, coarsest_fixed, cut_seed)
+        }
+        InitialMethod::Bubble => bubble_kway(coarsest, nparts, tpwgts, coarsest_fixed, cut_seed),
+    };
+    let ncuts = config.ncuts.max(1);
+    let mut current_part = if ncuts == 1 {
+        initial(None)
     } else {
-        levels.last().unwrap().graph.clone()
+        let base = seed.unwrap_or(0);
+        let cfg = pass_config(coarsest_fixed);
+        let mut best: Option<((i64, i64), Vec<usize>)> = None;
+        for cut in 0..ncuts {
+            let mut part = initial((cut > 0).then(|| base.wrapping_add(cut as u64)));
+            rebalance(coarsest, &mut part, nparts, &cfg);
+            refine_passes_observed(
+                coarsest,
+                &mut part,
+                nparts,
+                INITIAL_CUT_PASSES,
+                &cfg,
+                None,
+                &mut workspace.refine,
+            );
+            let score = (
+                excess_weight(coarsest, &part, nparts, &cfg),
+                coarsest.edge_cut(&part),
+            );
+            if best.as_ref().is_none_or(|(b, _)| score < *b) {
+                best = Some((score, part));
+            }
+        }
+        best.expect("at least one cut runs").1
     };
+    timings.initial_partition = t.elapsed();
 
-    let mut current_part = initial_partition(&coarsest, nparts);
-    fm_refine(&coarsest, &mut current_part, nparts, REFINE_PASSES);
+    let t = Instant::now();
+    // Bring overweight parts back within bounds before refining for cut
+    rebalance(
+        coarsest,
+        &mut current_part,
+        nparts,
+        &pass_config(coarsest_fixed),
+    );
+    // Edge cut of the latest level, if refinement tracked it to the end
+    let mut tracked_cut = None;
+    let level = levels.len();
+    if config.refine_levels.refines(level, levels.len()) {
+        let mut report = observer
+            .as_deref_mut()
+            .map(|obs| move |pass, cut, moves| obs(level, pass, cut, moves));
+        let cut = refine_passes_observed(
+            coarsest,
+            &mut current_part,
+            nparts,
+            config.refine_passes,
+            &pass_config(coarsest_fixed),
+            report
+                .as_mut()
+                .map(|f| f as &mut dyn FnMut(usize, i64, usize)),
+            &mut workspace.refine,
+        );
+        tracked_cut = (!config.flow_refinement).then_some(cut);
+        if config.flow_refinement {
+            flow_passes(
+                coarsest,
+                &mut current_part,
+                nparts,
+                &pass_config(coarsest_fixed),
+            );
+        }
+    }
+    timings.refine += t.elapsed();
 
     // Phase 3: Uncoarsen and refine
     // levels[0].cmap maps original vertices -> level 0 coarse vertices
     // levels[1].cmap maps level 0 coarse vertices -> level 1 coarse vertices
-    // etc. We project back in reverse order.
-    for (i, level) in levels.iter().enumerate().rev() {
-        let fine_graph = if i == 0 {
-            g.clone()
-        } else {
-            levels[i - 1].graph.clone()
-        };
+    // etc. We project back in reverse order, dropping each coarse graph
+    // once its partition has been projected.
+    let nlevels = levels.len();
+    while let Some(level) = levels.pop() {
+        let i = levels.len();
+        let fine_graph = levels.last().map_or(g, |l| &l.graph);
+
+        let mut fine_part = std::mem::take(&mut workspace.part);
+        fine_part.clear();
+        fine_part.extend(level.cmap.iter().map(|&c| current_part[c]));
 
-        let fine_n = fine_graph.n;
-        let mut fine_part = vec![0usize; fine_n];
-        for u in 0..fine_n {
-            fine_part[u] = current_part[level.cmap[u]];
+        let t = Instant::now();
+        rebalance(
+            fine_graph,
+            &mut fine_part,
+            nparts,
+            &pass_config(&fixed_levels[i]),
+        );
+        tracked_cut = None;
+        if config.refine_levels.refines(i, nlevels) {
+            let mut report = observer
+                .as_deref_mut()
+                .map(|obs| move |pass, cut, moves| obs(i, pass, cut, moves));
+            let cut = refine_passes_observed(
+                fine_graph,
+                &mut fine_part,
+                nparts,
+                config.refine_passes,
+                &pass_config(&fixed_levels[i]),
+                report
+                    .as_mut()
+                    .map(|f| f as &mut dyn FnMut(usize, i64, usize)),
+                &mut workspace.refine,
+            );
+            tracked_cut = (!config.flow_refinement).then_some(cut);
+            if config.flow_refinement {
+                flow_passes(
+                    fine_graph,
+                    &mut fine_part,
+                    nparts,
+                    &pass_config(&fixed_levels[i]),
+                );
+            }
         }
+        timings.refine += t.elapsed();
+        workspace.part = std::mem::replace(&mut current_part, fine_part);
+    }
 
-        fm_refine(&fine_graph, &mut fine_part, nparts, REFINE_PASSES);
-        current_part = fine_part;
+    if config.contiguous {
+        let t = Instant::now();
+        enforce_contiguity(g, &mut current_part, nparts);
+        timings.refine += t.elapsed();
     }
 
-    let cut = g.edge_cut(&current_part);
-    (cut, current_part)
+    let cut = match tracked_cut {
+        Some(cut) if !config.contiguous => cut,
+        _ => g.edge_cut(&current_part),
+    };
+    timings.total = start.elapsed();
+    (cut, current_part, timings)
 }
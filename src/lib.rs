@@ -22,20 +22,121 @@
 //! assert!(part.iter().all(|&p| p < 2));
 //! ```
 
+#![cfg_attr(feature = "simd", feature(portable_simd))]
+
+pub mod builder;
 pub mod coarsen;
+pub mod compact;
+pub mod config;
 pub mod graph;
+pub mod io;
 pub mod kway;
+pub mod ordering;
 pub mod partition;
+pub mod quality;
 pub mod refine;
+pub mod workspace;
+
+mod kernels;
+mod rng;
+mod spectral;
 
-pub use graph::Graph;
-pub use kway::part_kway;
+pub use builder::GraphBuilder;
+pub use coarsen::CoarsenScheme;
+pub use compact::CompactGraph;
+pub use config::{PartitionConfig, PartitionError, PartitionOptions, PartitionResult};
+pub use graph::{GhostInfo, Graph, GraphCheck, GraphError, NormalizeReport, Symmetrize};
+pub use io::ParseError;
+pub use kway::{
+    part_kway, part_kway_best_of, part_kway_components, part_kway_fixed, part_kway_objective,
+    part_kway_observed, part_kway_seeded, part_kway_timed, part_kway_tpwgts, repartition,
+    RefineLevels, Timings,
+};
+pub use partition::{InitialMethod, SeedStrategy};
+pub use quality::PartitionStats;
+pub use refine::{Objective, RefineMode};
+pub use workspace::Workspace;
 
 /// Partition a graph into `nparts` parts.
 ///
 /// Returns `(edge_cut, partition)` where:
 /// - `edge_cut` is the total weight of edges crossing partition boundaries
 /// - `partition[u]` is the 0-based part ID for vertex `u`
+///
+/// Uses the default [`PartitionConfig`]; this is the same partition as
+/// `PartitionConfig::default().nparts(nparts).partition(g)`. See
+/// [`partition_detailed`] for quality statistics as well.
 pub fn partition(g: &Graph, nparts: usize) -> (i64, Vec<usize>) {
     part_kway(g, nparts)
 }
+
+/// Partition a graph into `nparts` parts, returning the partition with its
+/// quality statistics.
+///
+/// Same partition as [`partition`]; converting the result `.into()` a
+/// tuple gives exactly [`partition`]'s return value.
+pub fn partition_detailed(g: &Graph, nparts: usize) -> PartitionResult {
+    PartitionConfig::default().nparts(nparts).partition(g)
+}
+
+/// Partition a graph into `nparts` parts, rejecting malformed input.
+///
+/// Same as [`partition`], but first checks the graph with
+/// [`Graph::validate`] and [`Graph::is_symmetric`] and requires
+/// `nparts >= 1`, returning a [`PartitionError`] instead of panicking or
+/// producing a meaningless partition.
+pub fn try_partition(g: &Graph, nparts: usize) -> Result<(i64, Vec<usize>), PartitionError> {
+    g.validate()?;
+    if nparts == 0 {
+        return Err(PartitionError::ZeroParts);
+    }
+    if !g.is_symmetric() {
+        return Err(PartitionError::Asymmetric);
+    }
+    Ok(partition(g, nparts))
+}
+
+/// Partition a graph into `nparts` parts, reusing the working memory of
+/// `workspace` across calls.
+///
+/// Same return value as [`partition`]; see [`Workspace`].
+pub fn partition_with_workspace(
+    g: &Graph,
+    nparts: usize,
+    workspace: &mut Workspace,
+) -> (i64, Vec<usize>) {
+    let result = PartitionConfig::default()
+        .nparts(nparts)
+        .partition_with_workspace(g, workspace);
+    (result.edge_cut, result.part)
+}
+
+/// Partition a graph into `nparts` parts with seeded, randomized coarsening.
+///
+/// Same return value as [`partition`]. The same `seed` always yields the
+/// same partition.
+pub fn partition_seeded(g: &Graph, nparts: usize, seed: u64) -> (i64, Vec<usize>) {
+    part_kway_seeded(g, nparts, seed)
+}
+
+/// Partition a graph `ntrials` times with different seeds and keep the best.
+///
+/// Trial `t` runs with seed `seed + t`. Returns `(edge_cut, partition,
+/// trial)`; see [`part_kway_best_of`].
+pub fn partition_best_of(
+    g: &Graph,
+    nparts: usize,
+    ntrials: usize,
+    seed: u64,
+) -> (i64, Vec<usize>, usize) {
+    part_kway_best_of(g, nparts, ntrials, seed)
+}
+
+/// Partition a graph into `opts.nparts` parts under tuned `opts`.
+///
+/// Same return value as [`partition`]. Every option applies, including the
+/// number of seeded trials ([`PartitionConfig::ntrials`]).
+pub fn partition_with_options(g: &Graph, opts: &PartitionOptions) -> (i64, Vec<usize>) {
+    let result = opts.partition(g);
+    (result.edge_cut, result.part)
+}
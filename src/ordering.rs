@@ -0,0 +1,320 @@
+//! Fill-reducing orderings via nested dissection.
+//!
+//! Recursively bisects the graph, extracts a vertex separator from each
+//! bisection, and numbers the two halves before the separator.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::graph::Graph;
+use crate::partition::initial_bisection;
+use crate::refine::fm_refine_2way;
+
+/// Subgraphs with this many vertices or fewer are not dissected further.
+const LEAF_SIZE: usize = 3;
+
+/// Number of FM passes used to improve each bisection before extracting
+/// its separator.
+const REFINE_PASSES: usize = 4;
+
+/// Heaviest side allowed when shifting separator vertices, relative to half
+/// the total vertex weight.
+const SEPARATOR_IMBALANCE: f64 = 1.05;
+
+/// Label of separator vertices in the `side` vector of [`refine_separator`].
+const SEP: usize = 2;
+
+/// A node of the separator tree produced by nested dissection.
+#[derive(Clone, Debug)]
+pub struct SeparatorNode {
+    /// Vertices owned by this node, in elimination order. For an internal
+    /// node these form the separator; for a leaf they are the whole subdomain.
+    pub vertices: Vec<usize>,
+    /// Index of the subtree covering the first half, if non-empty.
+    pub left: Option<usize>,
+    /// Index of the subtree covering the second half, if non-empty.
+    pub right: Option<usize>,
+}
+
+impl SeparatorNode {
+    /// Whether this node is a leaf subdomain rather than a separator.
+    pub fn is_leaf(&self) -> bool {
+        self.left.is_none() && self.right.is_none()
+    }
+}
+
+/// Separator tree of a nested dissection ordering.
+///
+/// Nodes are stored in post-order (children before parents), matching the
+/// order in which their vertices appear in the permutation.
+#[derive(Clone, Debug, Default)]
+pub struct SeparatorTree {
+    /// All nodes of the tree.
+    pub nodes: Vec<SeparatorNode>,
+    /// Index of the root node, or `None` for an empty graph.
+    pub root: Option<usize>,
+}
+
+/// Split `g` into `(left, right, separator)` vertex sets.
+///
+/// No edge joins a vertex of `left` to a vertex of `right`, so removing the
+/// separator disconnects the two sides. The separator starts as the boundary
+/// of one side of a refined edge bisection (whichever side has fewer boundary
+/// vertices) and is then shrunk greedily: a separator vertex moves into a side
+/// when its neighbors on the opposite side, which take its place in the
+/// separator, weigh less than it does. Moves never push the heavier side past
+/// 5% above half the total weight, unless it was already heavier.
+///
+/// Each returned set is sorted.
+pub fn vertex_separator(g: &Graph) -> (Vec<usize>, Vec<usize>, Vec<usize>) {
+    let (_, right, sep) = bisection_separator(g);
+    let mut side = vec![0usize; g.n];
+    for &u in &right {
+        side[u] = 1;
+    }
+    for &u in &sep {
+        side[u] = SEP;
+    }
+    refine_separator(g, &mut side);
+
+    let mut sets = [Vec::new(), Vec::new(), Vec::new()];
+    for (u, &s) in side.iter().enumerate() {
+        sets[s].push(u);
+    }
+    let [left, right, sep] = sets;
+    (left, right, sep)
+}
+
+/// Greedily shrink the separator of `side` (labels `0`, `1` and [`SEP`]).
+///
+/// Repeatedly applies the best positive-gain move of a separator vertex `s`
+/// into side `to`: the neighbors of `s` on the other side join the
+/// separator, so the gain is `w(s)` minus their weight. Every move keeps the
+/// no-edge-between-sides invariant.
+///
+/// Candidate moves are kept in a max-heap. A move only changes the gains of
+/// separator vertices next to `s` or to the vertices that joined the
+/// separator, so only those are re-scored. Side weights change with every
+/// move; as in the FM passes of [`crate::refine`], an entry is re-scored
+/// when it reaches the top of the heap and pushed back if its move changed.
+fn refine_separator(g: &Graph, side: &mut [usize]) {
+    let mut weight = [0i64; 3];
+    for u in 0..g.n {
+        weight[side[u]] += g.vertex_weight(u);
+    }
+    let total: i64 = weight.iter().sum();
+    let bound = (total as f64 * SEPARATOR_IMBALANCE / 2.0).ceil() as i64;
+
+    let mut heap: BinaryHeap<SeparatorMove> = (0..g.n)
+        .filter(|&s| side[s] == SEP)
+        .filter_map(|s| separator_move(g, side, &weight, bound, s))
+        .collect();
+    let mut joined = Vec::new();
+    while let Some(mv) = heap.pop() {
+        let s = mv.vertex.0;
+        if side[s] != SEP {
+            continue;
+        }
+        // The entry may be stale: re-score and requeue if it changed
+        match separator_move(g, side, &weight, bound, s) {
+            Some(cur) if cur == mv => {}
+            Some(cur) => {
+                heap.push(cur);
+                continue;
+            }
+            None => continue,
+        }
+
+        let to = mv.to.0;
+        let other = 1 - to;
+        for &v in g.neighbors(s) {
+            if side[v] == other {
+                side[v] = SEP;
+                weight[other] -= g.vertex_weight(v);
+                weight[SEP] += g.vertex_weight(v);
+                joined.push(v);
+            }
+        }
+        side[s] = to;
+        weight[SEP] -= g.vertex_weight(s);
+        weight[to] += g.vertex_weight(s);
+
+        // Only separator vertices next to a vertex that changed sides have
+        // new gains; the joined vertices are among the neighbors of `s`
+        let changed = g
+            .neighbors(s)
+            .iter()
+            .chain(joined.iter().flat_map(|&v| g.neighbors(v)));
+        for &v in changed {
+            if side[v] == SEP {
+                heap.extend(separator_move(g, side, &weight, bound, v));
+            }
+        }
+        joined.clear();
+    }
+}
+
+/// A candidate move of separator vertex `vertex` into side `to`.
+///
+/// Ordered by gain, then by the lighter heavier side after the move, then
+/// by the lower vertex ID and side.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct SeparatorMove {
+    gain: i64,
+    heavier: Reverse<i64>,
+    vertex: Reverse<usize>,
+    to: Reverse<usize>,
+}
+
+/// Best positive-gain move of separator vertex `s` under the side weights
+/// `weight`, if any.
+///
+/// A move may not push the heavier side past `bound`, unless that side was
+/// already heavier than `bound`.
+fn separator_move(
+    g: &Graph,
+    side: &[usize],
+    weight: &[i64; 3],
+    bound: i64,
+    s: usize,
+) -> Option<SeparatorMove> {
+    let limit = bound.max(weight[0].max(weight[1]));
+    let mut best: Option<SeparatorMove> = None;
+    for to in 0..2 {
+        let other = 1 - to;
+        let cost: i64 = g
+            .neighbors(s)
+            .iter()
+            .filter(|&&v| side[v] == other)
+            .map(|&v| g.vertex_weight(v))
+            .sum();
+        let gain = g.vertex_weight(s) - cost;
+        if gain <= 0 {
+            continue;
+        }
+        let mut after = *weight;
+        after[to] += g.vertex_weight(s);
+        after[other] -= cost;
+        let heavier = after[0].max(after[1]);
+        if heavier > limit {
+            continue;
+        }
+        let mv = SeparatorMove {
+            gain,
+            heavier: Reverse(heavier),
+            vertex: Reverse(s),
+            to: Reverse(to),
+        };
+        best = best.max(Some(mv));
+    }
+    best
+}
+
+/// Compute a fill-reducing nested dissection ordering.
+///
+/// Returns `perm` where `perm[i]` is the original vertex eliminated `i`-th.
+/// Each subdomain is bisected, the boundary of one side becomes a vertex
+/// separator, and the two halves are ordered (recursively) before the
+/// separator. See [`nested_dissection_tree`] for the separator tree as well.
+pub fn nested_dissection(g: &Graph) -> Vec<usize> {
+    nested_dissection_tree(g).0
+}
+
+/// Compute a nested dissection ordering together with its separator tree.
+///
+/// Returns `(perm, tree)` where `perm[i]` is the original vertex eliminated
+/// `i`-th. Each subdomain's two halves are numbered before its separator.
+pub fn nested_dissection_tree(g: &Graph) -> (Vec<usize>, SeparatorTree) {
+    let mut perm = Vec::with_capacity(g.n);
+    let mut tree = SeparatorTree::default();
+    if g.n > 0 {
+        let verts: Vec<usize> = (0..g.n).collect();
+        tree.root = Some(dissect(g, &verts, &mut perm, &mut tree));
+    }
+    (perm, tree)
+}
+
+/// Recursively order `sub`, whose local vertex `i` is original vertex
+/// `global[i]`. Returns the index of the created tree node.
+fn dissect(
+    sub: &Graph,
+    global: &[usize],
+    perm: &mut Vec<usize>,
+    tree: &mut SeparatorTree,
+) -> usize {
+    if sub.n > LEAF_SIZE {
+        let (left, right, sep) = vertex_separator(sub);
+        // Stop if the split does not shrink the subproblem
+        if left.len() < sub.n && right.len() < sub.n {
+            let left_node = dissect_side(sub, global, &left, perm, tree);
+            let right_node = dissect_side(sub, global, &right, perm, tree);
+            let vertices: Vec<usize> = sep.iter().map(|&u| global[u]).collect();
+            perm.extend_from_slice(&vertices);
+            tree.nodes.push(SeparatorNode {
+                vertices,
+                left: left_node,
+                right: right_node,
+            });
+            return tree.nodes.len() - 1;
+        }
+    }
+
+    perm.extend_from_slice(global);
+    tree.nodes.push(SeparatorNode {
+        vertices: global.to_vec(),
+        left: None,
+        right: None,
+    });
+    tree.nodes.len() - 1
+}
+
+/// Recurse into the local vertex subset `side` of `sub`, if non-empty.
+fn dissect_side(
+    sub: &Graph,
+    global: &[usize],
+    side: &[usize],
+    perm: &mut Vec<usize>,
+    tree: &mut SeparatorTree,
+) -> Option<usize> {
+    if side.is_empty() {
+        return None;
+    }
+    let side_graph = sub.induced_subgraph(side).0;
+    let side_global: Vec<usize> = side.iter().map(|&u| global[u]).collect();
+    Some(dissect(&side_graph, &side_global, perm, tree))
+}
+
+/// Split `g` into `(left, right, separator)` vertex sets with no edge
+/// between `left` and `right`.
+///
+/// The separator is the set of boundary vertices on whichever side of a
+/// refined edge bisection has fewer of them.
+fn bisection_separator(g: &Graph) -> (Vec<usize>, Vec<usize>, Vec<usize>) {
+    let mut part = initial_bisection(g);
+    fm_refine_2way(g, &mut part, REFINE_PASSES);
+
+    let mut boundary = [Vec::new(), Vec::new()];
+    let mut is_boundary = vec![false; g.n];
+    for u in 0..g.n {
+        if g.neighbors(u).iter().any(|&v| part[v] != part[u]) {
+            is_boundary[u] = true;
+            boundary[part[u]].push(u);
+        }
+    }
+    let sep_side = usize::from(boundary[1].len() < boundary[0].len());
+
+    let mut left = Vec::new();
+    let mut right = Vec::new();
+    for u in 0..g.n {
+        if is_boundary[u] && part[u] == sep_side {
+            continue;
+        }
+        if part[u] == 0 {
+            left.push(u);
+        } else {
+            right.push(u);
+        }
+    }
+    let sep = std::mem::take(&mut boundary[sep_side]);
+    (left, right, sep)
+}
@@ -1,9 +1,80 @@
 //! Initial partitioning of small (coarsened) graphs.
 //!
 //! Implements greedy graph growing (GGP) bisection for the coarsest graph
-//! in the multilevel hierarchy.
+//! in the multilevel hierarchy, spectral bisection along the Fiedler vector,
+//! and a direct k-way variant that grows all parts at once.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
 
 use crate::graph::Graph;
+use crate::kernels::weight_to_part;
+use crate::refine::{
+    excess_weight, max_part_weights, rebalance, refine_passes, PassConfig, MAX_IMBALANCE,
+};
+use crate::rng::Rng;
+use crate::spectral::fiedler_vector;
+
+/// How the coarsest graph is split into its initial `nparts` parts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InitialMethod {
+    /// Recursive greedy-growing bisection ([`initial_partition`]).
+    #[default]
+    RecursiveBisection,
+    /// Grow all parts simultaneously from `nparts` seeds
+    /// ([`initial_partition_greedy`]).
+    GreedyKway,
+    /// Recursive bisection, splitting each graph along its Fiedler vector
+    /// ([`spectral_bisection`]) instead of by greedy growing.
+    Spectral,
+    /// Grow all parts breadth-first from centers that are moved to the
+    /// middle of their parts and regrown ([`initial_partition_bubble`]).
+    Bubble,
+}
+
+/// Default number of seed vertices tried per greedy-growing bisection.
+pub(crate) const BISECTION_SEEDS: usize = 7;
+
+/// How greedy-growing bisection picks the seed vertices of its candidates.
+///
+/// Each seed grows one candidate bisection and the best refined candidate
+/// wins, so diverse seeds matter most on long, thin meshes, where only a
+/// seed near one end grows a short cut across.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SeedStrategy {
+    /// Vertices 0, `n / 2` and `n - 1`, then the vertices of highest
+    /// weighted degree.
+    #[default]
+    Spread,
+    /// Uniformly random vertices, drawn from the seed of the initial cut
+    /// (0 unless [`crate::PartitionConfig::ncuts`] asks for several).
+    Random,
+    /// A pseudo-peripheral vertex, found by repeated breadth-first search
+    /// from vertex 0, then each vertex farthest in hops from all earlier
+    /// seeds.
+    PseudoPeripheral,
+}
+
+/// FM passes run on every grown candidate bisection before the best one is
+/// picked.
+const CANDIDATE_FM_PASSES: usize = 2;
+
+/// Rounds of recentering and regrowing in [`initial_partition_bubble`].
+const BUBBLE_ROUNDS: usize = 8;
+
+/// Offset adjustments per round of [`initial_partition_bubble`].
+const BUBBLE_BALANCE_STEPS: usize = 16;
+
+/// Largest part weight relative to its share that bubble growth accepts.
+const BUBBLE_IMBALANCE: f64 = 1.03;
+
+/// One hop in the fixed-point distances of bubble growth, so that offsets
+/// can move part boundaries by fractions of a hop.
+const HOP: i64 = 1024;
+
+/// Bisections with at most this many unpinned vertices are solved exactly
+/// by [`exact_bisection`] (2^15 splits) instead of by growing candidates.
+const EXACT_BISECTION_VERTICES: usize = 15;
 
 /// Bisect a small graph using greedy graph growing.
 ///
@@ -11,36 +82,108 @@ use crate::graph::Graph;
 /// Attempts to balance vertex weight across the two parts.
 /// Tries multiple seed vertices and returns the best bisection.
 pub fn initial_bisection(g: &Graph) -> Vec<usize> {
+    let cfg = PassConfig {
+        tpwgts: &[0.5, 0.5],
+        ..PassConfig::default()
+    };
+    bisection_with_fraction(g, 0.5, &[], [0, 0], &cfg, &Recursion::default())
+}
+
+/// Bisect a small graph along its Fiedler vector.
+///
+/// Estimates the Fiedler vector (the eigenvector of the second smallest
+/// eigenvalue of the weighted graph Laplacian) with a short Lanczos run,
+/// then gives part 0 the vertices with the smallest entries, up to half of
+/// the vertex weight, i.e. splits at the weighted median. The split from
+/// the other end of the vector is tried too, and both are refined as in
+/// [`initial_bisection`]. On meshes the split follows the graph's geometry,
+/// which often gives a better starting cut than greedy growing, though not
+/// on every graph.
+///
+/// Returns a partition vector where each entry is 0 or 1.
+pub fn spectral_bisection(g: &Graph) -> Vec<usize> {
+    let cfg = PassConfig {
+        tpwgts: &[0.5, 0.5],
+        ..PassConfig::default()
+    };
+    let opts = Recursion {
+        spectral: true,
+        ..Recursion::default()
+    };
+    bisection_with_fraction(g, 0.5, &[], [0, 0], &cfg, &opts)
+}
+
+/// Bisect `g` so that part 0 receives about `frac0` of the total vertex weight.
+///
+/// `side` is either empty or pins vertex `u` to side `k` when
+/// `side[u] == Some(k)`. Side `k` should keep at least `floors[k]` weight on
+/// every constraint, and part 0 is grown no heavier than its bound under
+/// `cfg` on any constraint; see [`grow_bisection`].
+///
+/// Graphs with at most [`EXACT_BISECTION_VERTICES`] unpinned vertices and
+/// no floors are bisected optimally by [`exact_bisection`]. Otherwise
+/// candidates are grown from the seeds of [`bisection_seeds`], or with
+/// `opts.spectral` split from either end of the Fiedler vector by
+/// [`split_along`]. Each candidate is rebalanced and given a quick FM
+/// refinement under `cfg`, as in METIS, so the candidates compete on their
+/// refined cuts. With nonzero floors, which FM cannot enforce per side,
+/// candidates are compared as grown. The candidate least over the bounds of
+/// `cfg` wins, then the one with the lowest cut.
+fn bisection_with_fraction(
+    g: &Graph,
+    frac0: f64,
+    side: &[Option<usize>],
+    floors: [i64; 2],
+    cfg: &PassConfig,
+    opts: &Recursion,
+) -> Vec<usize> {
     let n = g.n;
     if n == 0 {
         return Vec::new();
     }
     if n == 1 {
-        return vec![0];
+        return vec![side.first().copied().flatten().unwrap_or(0)];
     }
 
-    // Collect candidate seeds: several high-degree vertices for diversity
-    let mut candidates: Vec<usize> = Vec::new();
-    candidates.push(0);
-    candidates.push(n / 2);
-    candidates.push(n - 1);
-    // Add top-degree vertices
-    let mut by_degree: Vec<usize> = (0..n).collect();
-    by_degree.sort_by(|&a, &b| g.weighted_degree(b).cmp(&g.weighted_degree(a)));
-    for &v in by_degree.iter().take(4) {
-        candidates.push(v);
+    // Target weight of part 0 on each balance constraint
+    let targets: Vec<i64> = (0..g.ncon())
+        .map(|c| {
+            let total: i64 = (0..n).map(|u| g.constraint_weight(u, c)).sum();
+            (total as f64 * frac0) as i64
+        })
+        .collect();
+
+    let free = (0..n).filter(|&u| side.get(u).copied().flatten().is_none());
+    if floors == [0, 0] && free.count() <= EXACT_BISECTION_VERTICES {
+        return exact_bisection(g, &targets, side, cfg);
     }
-    candidates.sort_unstable();
-    candidates.dedup();
+
+    let max0 = &max_part_weights(g, 2, cfg)[..g.ncon()];
+    let candidates: Vec<Vec<usize>> = if opts.spectral {
+        let fiedler = fiedler_vector(g, opts.seed.unwrap_or(0));
+        let mut order: Vec<usize> = (0..n).collect();
+        order.sort_by(|&a, &b| fiedler[a].total_cmp(&fiedler[b]).then(a.cmp(&b)));
+        let first = split_along(g, &order, &targets, max0, side, floors);
+        order.reverse();
+        vec![first, split_along(g, &order, &targets, max0, side, floors)]
+    } else {
+        bisection_seeds(g, opts)
+            .into_iter()
+            .map(|seed| grow_bisection(g, seed, &targets, max0, side, floors))
+            .collect()
+    };
 
     let mut best_part = vec![0usize; n];
-    let mut best_cut = i64::MAX;
+    let mut best_score = (i64::MAX, i64::MAX);
 
-    for &seed in &candidates {
-        let part = grow_bisection(g, seed);
-        let cut = g.edge_cut(&part);
-        if cut < best_cut {
-            best_cut = cut;
+    for mut part in candidates {
+        if floors == [0, 0] {
+            rebalance(g, &mut part, 2, cfg);
+            refine_passes(g, &mut part, 2, CANDIDATE_FM_PASSES, cfg);
+        }
+        let score = (excess_weight(g, &part, 2, cfg), g.edge_cut(&part));
+        if score < best_score {
+            best_score = score;
             best_part = part;
         }
     }
@@ -48,74 +191,863 @@ pub fn initial_bisection(g: &Graph) -> Vec<usize> {
     best_part
 }
 
-/// Grow a bisection from a given seed vertex.
-fn grow_bisection(g: &Graph, seed: usize) -> Vec<usize> {
+/// Optimal bisection of a tiny graph by enumerating every split.
+///
+/// Visits all `2^k` assignments of the `k` vertices not pinned by `side` in
+/// Gray code order, so each step moves one vertex and updates the cut and
+/// part weights incrementally. Returns the split least over the bounds of
+/// `cfg` (see [`excess_weight`]), then with the lowest cut, then closest to
+/// the weight `targets[c]` of part 0 on each constraint `c`. Like a grown
+/// bisection, neither side is left empty unless the pins force it.
+fn exact_bisection(
+    g: &Graph,
+    targets: &[i64],
+    side: &[Option<usize>],
+    cfg: &PassConfig,
+) -> Vec<usize> {
     let n = g.n;
-    let mut part = vec![1usize; n];
-    let mut in_part0 = vec![false; n];
+    let ncon = g.ncon();
+    let max = max_part_weights(g, 2, cfg);
+    let pinned = |u: usize| if side.is_empty() { None } else { side[u] };
+    let free: Vec<usize> = (0..n).filter(|&u| pinned(u).is_none()).collect();
+
+    // Start with every free vertex in part 1; bit i of `mask` moves free[i]
+    // to part 0
+    let mut part: Vec<usize> = (0..n).map(|u| pinned(u).unwrap_or(1)).collect();
+    let mut weight = vec![0i64; 2 * ncon];
+    for u in 0..n {
+        for c in 0..ncon {
+            weight[part[u] * ncon + c] += g.constraint_weight(u, c);
+        }
+    }
+    let score = |weight: &[i64], cut: i64| {
+        let excess: i64 = weight.iter().zip(&max).map(|(w, m)| (w - m).max(0)).sum();
+        let off: i64 = targets.iter().zip(weight).map(|(t, w)| (w - t).abs()).sum();
+        (excess, cut, off)
+    };
+    let mut cut = g.edge_cut(&part);
+    let mut count0 = part.iter().filter(|&&p| p == 0).count();
+    let allowed = |count0: usize| (count0 > 0 && count0 < n) || free.is_empty();
+    let mut best = (i64::MAX, i64::MAX, i64::MAX);
+    if allowed(count0) {
+        best = score(&weight, cut);
+    }
+    let mut best_mask = 0u64;
+    let mut mask = 0u64;
+    for step in 1..1u64 << free.len() {
+        let bit = step.trailing_zeros() as usize;
+        let u = free[bit];
+        mask ^= 1 << bit;
+        let from = part[u];
+        for (k, &v) in g.neighbors(u).iter().enumerate() {
+            if v == u {
+                continue;
+            }
+            if part[v] == from {
+                cut += g.edge_weight(u, k);
+            } else {
+                cut -= g.edge_weight(u, k);
+            }
+        }
+        part[u] = 1 - from;
+        if from == 0 {
+            count0 -= 1;
+        } else {
+            count0 += 1;
+        }
+        for c in 0..ncon {
+            weight[from * ncon + c] -= g.constraint_weight(u, c);
+            weight[(1 - from) * ncon + c] += g.constraint_weight(u, c);
+        }
+        let current = score(&weight, cut);
+        if allowed(count0) && current < best {
+            best = current;
+            best_mask = mask;
+        }
+    }
 
-    let total_weight: i64 = (0..n).map(|u| g.vertex_weight(u)).sum();
-    let target = total_weight / 2;
-    let mut weight0: i64 = 0;
+    for (i, &u) in free.iter().enumerate() {
+        part[u] = if best_mask >> i & 1 == 1 { 0 } else { 1 };
+    }
+    part
+}
 
-    in_part0[seed] = true;
-    part[seed] = 0;
-    weight0 += g.vertex_weight(seed);
+/// Distinct seed vertices for the grown candidates of a bisection, in
+/// increasing order: `opts.seeds` of them (at least one) chosen by
+/// `opts.strategy`, or at random if `opts.seed` is set.
+fn bisection_seeds(g: &Graph, opts: &Recursion) -> Vec<usize> {
+    let n = g.n;
+    let count = opts.seeds.max(1);
+    let mut seeds: Vec<usize> = match (opts.seed, opts.strategy) {
+        (Some(_), _) | (None, SeedStrategy::Random) => {
+            let mut rng = Rng::new(opts.seed.unwrap_or(0));
+            (0..count).map(|_| rng.below(n)).collect()
+        }
+        (None, SeedStrategy::Spread) => {
+            let mut seeds: Vec<usize> = [0, n / 2, n - 1].into_iter().take(count).collect();
+            let mut by_degree: Vec<usize> = (0..n).collect();
+            by_degree.sort_by_key(|&v| Reverse(g.weighted_degree(v)));
+            seeds.extend(by_degree.iter().take(count.saturating_sub(3)));
+            seeds
+        }
+        (None, SeedStrategy::PseudoPeripheral) => {
+            // Mark seeds as assigned so each new one is farthest from all
+            let mut marked = vec![usize::MAX; n];
+            let mut seeds = vec![pseudo_peripheral(g, 0)];
+            marked[seeds[0]] = 0;
+            while seeds.len() < count {
+                match farthest_unassigned(g, &marked) {
+                    Some(v) => {
+                        marked[v] = 0;
+                        seeds.push(v);
+                    }
+                    None => break,
+                }
+            }
+            seeds
+        }
+    };
+    seeds.sort_unstable();
+    seeds.dedup();
+    seeds
+}
 
+/// A pseudo-peripheral vertex of the component of `start`: repeatedly
+/// jump to the farthest vertex (in hops) from the current one, preferring
+/// low degree and then the lowest ID, until the distance stops growing.
+fn pseudo_peripheral(g: &Graph, start: usize) -> usize {
+    let whole = vec![0usize; g.n];
+    let mut root = start;
+    let mut eccentricity = 0;
     loop {
-        if weight0 >= target {
+        let (dist, _) = sweep_within_parts(g, &whole, std::iter::once(root));
+        let far = (0..g.n)
+            .filter(|&u| dist[u] != usize::MAX)
+            .max_by_key(|&u| (dist[u], Reverse(g.degree(u)), Reverse(u)))
+            .unwrap();
+        if dist[far] <= eccentricity {
+            return root;
+        }
+        eccentricity = dist[far];
+        root = far;
+    }
+}
+
+/// Grow a bisection from a given seed vertex until part 0 reaches its
+/// target weight on every constraint (`targets[c]` for constraint `c`).
+///
+/// Greedy graph growing with gains (GGGP): each step moves the vertex
+/// adjacent to part 0 whose move reduces the cut the most, i.e. with the
+/// largest edge weight into part 0 minus that into part 1, ties going to
+/// the lowest vertex ID. Only when no vertex touches part 0 (a disconnected
+/// graph) is a non-adjacent vertex taken, by the same gain.
+///
+/// A vertex is never taken if it would put part 0 over its bound `max0[c]`
+/// on some constraint `c`, so growth towards one target stops short of
+/// overshooting another; growth ends early when no vertex fits.
+///
+/// Vertices pinned by `side` (empty for none) start on their side and never
+/// change it; a seed pinned to side 1 is skipped.
+///
+/// Part 0 keeps growing past its target until it weighs at least
+/// `floors[0]` on every constraint, and never takes a vertex that would
+/// leave part 1 below `floors[1]` on some constraint. When the two floors
+/// conflict with the targets, the floors win.
+fn grow_bisection(
+    g: &Graph,
+    seed: usize,
+    targets: &[i64],
+    max0: &[i64],
+    side: &[Option<usize>],
+    floors: [i64; 2],
+) -> Vec<usize> {
+    let n = g.n;
+    let mut part = vec![1usize; n];
+    let mut weight0 = vec![0i64; targets.len()];
+    let mut weight1: Vec<i64> = (0..targets.len())
+        .map(|c| (0..n).map(|u| g.constraint_weight(u, c)).sum())
+        .collect();
+    let add = |weight0: &mut [i64], weight1: &mut [i64], u: usize| {
+        for c in 0..weight0.len() {
+            weight0[c] += g.constraint_weight(u, c);
+            weight1[c] -= g.constraint_weight(u, c);
+        }
+    };
+    let pinned = |u: usize| if side.is_empty() { None } else { side[u] };
+
+    for (u, p) in part.iter_mut().enumerate() {
+        if pinned(u) == Some(0) || (u == seed && pinned(u).is_none()) {
+            *p = 0;
+            add(&mut weight0, &mut weight1, u);
+        }
+    }
+
+    loop {
+        let reached = weight0.iter().zip(targets).all(|(w, t)| w >= t);
+        if reached && weight0.iter().all(|&w| w >= floors[0]) {
             break;
         }
 
-        let mut best_u = None;
-        let mut best_gain = -1i64;
+        // Best move as (touches part 0, gain, vertex)
+        let mut best: Option<(bool, i64, usize)> = None;
 
         for u in 0..n {
-            if in_part0[u] {
+            if part[u] == 0 || pinned(u) == Some(1) {
                 continue;
             }
-            let mut gain = 0i64;
-            for k in 0..g.degree(u) {
-                let v = g.adjncy[g.xadj[u] + k];
-                if in_part0[v] {
-                    gain += g.edge_weight(u, k);
-                }
+            if (0..weight1.len()).any(|c| weight1[c] - g.constraint_weight(u, c) < floors[1]) {
+                continue;
             }
-            if gain > best_gain || (gain == best_gain && best_u.is_none()) {
-                best_gain = gain;
-                best_u = Some(u);
+            if (0..weight0.len()).any(|c| weight0[c] + g.constraint_weight(u, c) > max0[c]) {
+                continue;
+            }
+            let (to0, edges_to0) = weight_to_part(g.neighbors(u), g.edge_weights(u), &part, 0);
+            let touches = edges_to0 > 0;
+            let gain = 2 * to0 - g.weighted_degree(u);
+            if best.is_none_or(|(t, b, _)| (touches, gain) > (t, b)) {
+                best = Some((touches, gain, u));
             }
         }
+        let best_u = best.map(|(_, _, u)| u);
 
         match best_u {
-            Some(u) if best_gain > 0 || weight0 < target => {
-                in_part0[u] = true;
+            Some(u) => {
                 part[u] = 0;
-                weight0 += g.vertex_weight(u);
+                add(&mut weight0, &mut weight1, u);
             }
-            _ => break,
+            None => break,
         }
     }
 
     part
 }
 
+/// Split `g` by giving part 0 a prefix of `order` until it reaches its
+/// target weight on every constraint (`targets[c]` for constraint `c`).
+///
+/// Bounds, pins and floors are honored as in [`grow_bisection`]: vertices
+/// pinned by `side` keep their side, and vertices that would put part 0
+/// over `max0` or leave part 1 below `floors[1]` are passed over.
+fn split_along(
+    g: &Graph,
+    order: &[usize],
+    targets: &[i64],
+    max0: &[i64],
+    side: &[Option<usize>],
+    floors: [i64; 2],
+) -> Vec<usize> {
+    let n = g.n;
+    let mut part = vec![1usize; n];
+    let mut weight0 = vec![0i64; targets.len()];
+    let mut weight1: Vec<i64> = (0..targets.len())
+        .map(|c| (0..n).map(|u| g.constraint_weight(u, c)).sum())
+        .collect();
+    let pinned = |u: usize| if side.is_empty() { None } else { side[u] };
+    let add = |weight0: &mut [i64], weight1: &mut [i64], u: usize| {
+        for c in 0..weight0.len() {
+            weight0[c] += g.constraint_weight(u, c);
+            weight1[c] -= g.constraint_weight(u, c);
+        }
+    };
+
+    for (u, p) in part.iter_mut().enumerate() {
+        if pinned(u) == Some(0) {
+            *p = 0;
+            add(&mut weight0, &mut weight1, u);
+        }
+    }
+    for &u in order {
+        let reached = weight0.iter().zip(targets).all(|(w, t)| w >= t);
+        if reached && weight0.iter().all(|&w| w >= floors[0]) {
+            break;
+        }
+        if part[u] == 0 || pinned(u) == Some(1) {
+            continue;
+        }
+        if (0..weight1.len()).any(|c| weight1[c] - g.constraint_weight(u, c) < floors[1]) {
+            continue;
+        }
+        if (0..weight0.len()).any(|c| weight0[c] + g.constraint_weight(u, c) > max0[c]) {
+            continue;
+        }
+        part[u] = 0;
+        add(&mut weight0, &mut weight1, u);
+    }
+
+    part
+}
+
 /// Partition a small graph into `nparts` using recursive bisection.
 ///
 /// Each entry in the returned vector is a partition ID in `0..nparts`.
 pub fn initial_partition(g: &Graph, nparts: usize) -> Vec<usize> {
+    partition_recursive(g, nparts, None, &[], &Recursion::default())
+}
+
+/// Partition a small graph into `nparts` by growing every part at once.
+///
+/// Part `p` starts from one seed: the unassigned vertex farthest (in hops)
+/// from all earlier seeds, starting with vertex 0. Then, repeatedly, the
+/// lightest part that still has unassigned neighbors takes the one with
+/// the highest gain: edge weight into the part minus edge weight to other
+/// parts (the lowest ID on ties). Unlike recursive bisection, no early
+/// split constrains the later ones. Vertices that no part can reach, e.g.
+/// in another connected component, go to the lightest part.
+pub fn initial_partition_greedy(g: &Graph, nparts: usize) -> Vec<usize> {
+    greedy_kway(g, nparts, None, &[], None)
+}
+
+/// Direct k-way growing behind [`initial_partition_greedy`].
+///
+/// Parts grow towards `tpwgts` if given and uniform shares otherwise; the
+/// "lightest" part is the one with the lowest weight relative to its
+/// target. `fixed` is either empty or pins vertices, which are placed
+/// before any growing and replace the seed of their part. With `seed`, the
+/// first seed is a random vertex instead of vertex 0 (when nothing is
+/// pinned), and the later seeds spread out from it as usual.
+pub(crate) fn greedy_kway(
+    g: &Graph,
+    nparts: usize,
+    tpwgts: Option<&[f64]>,
+    fixed: &[Option<usize>],
+    seed: Option<u64>,
+) -> Vec<usize> {
+    let n = g.n;
+    if nparts <= 1 || n == 0 {
+        return vec![0; n];
+    }
+
+    let share = |p: usize| match tpwgts {
+        Some(t) => t[p] / t.iter().sum::<f64>(),
+        None => 1.0 / nparts as f64,
+    };
+    let mut part = vec![usize::MAX; n];
+    let mut weight = vec![0i64; nparts];
+    // frontier[p] holds (growth gain into p, vertex); entries go stale once
+    // the vertex is assigned or its gain changes
+    let mut frontier: Vec<BinaryHeap<(i64, Reverse<usize>)>> = vec![BinaryHeap::new(); nparts];
+    let assign = |part: &mut [usize],
+                  weight: &mut [i64],
+                  frontier: &mut [BinaryHeap<(i64, Reverse<usize>)>],
+                  u: usize,
+                  p: usize| {
+        part[u] = p;
+        weight[p] += g.vertex_weight(u);
+        for &v in g.neighbors(u) {
+            if part[v] == usize::MAX {
+                frontier[p].push((growth_gain(g, part, v, p), Reverse(v)));
+            }
+        }
+    };
+
+    for (u, &p) in kway_seeds(g, nparts, fixed, seed).iter().enumerate() {
+        if p != usize::MAX {
+            assign(&mut part, &mut weight, &mut frontier, u, p);
+        }
+    }
+
+    let mut remaining = part.iter().filter(|&&p| p == usize::MAX).count();
+    while remaining > 0 {
+        // Drop stale tops so every non-empty heap offers a valid move
+        for (p, heap) in frontier.iter_mut().enumerate() {
+            while let Some(&(c, Reverse(v))) = heap.peek() {
+                if part[v] != usize::MAX {
+                    heap.pop();
+                    continue;
+                }
+                let gain = growth_gain(g, &part, v, p);
+                if c == gain {
+                    break;
+                }
+                heap.pop();
+                // A gain that grew was pushed again when it grew
+                if gain < c {
+                    heap.push((gain, Reverse(v)));
+                }
+            }
+        }
+        let load = |p: usize| weight[p] as f64 / share(p);
+        let lightest = |candidates: &mut dyn Iterator<Item = usize>| {
+            candidates.min_by(|&a, &b| load(a).total_cmp(&load(b)).then(a.cmp(&b)))
+        };
+        let grow = lightest(&mut (0..nparts).filter(|&p| !frontier[p].is_empty()));
+        let (u, p) = match grow {
+            Some(p) => {
+                let (_, Reverse(u)) = frontier[p].pop().unwrap();
+                (u, p)
+            }
+            None => {
+                let u = part.iter().position(|&p| p == usize::MAX).unwrap();
+                (u, lightest(&mut (0..nparts)).unwrap())
+            }
+        };
+        assign(&mut part, &mut weight, &mut frontier, u, p);
+        remaining -= 1;
+    }
+
+    part
+}
+
+/// Partition a small graph into `nparts` by growing bubbles.
+///
+/// Each part starts from one center, chosen like the seeds of
+/// [`initial_partition_greedy`], and all parts grow breadth-first at once:
+/// every vertex joins the part whose center is nearest in hops plus a
+/// per-part offset. The offsets start at zero and are adjusted, like a
+/// diffusion of load, to bring every part within 3% of its share: an
+/// overweight part pushes its offset up and gives up its outer layers, an
+/// underweight one pulls them in. Then every part moves its center to its
+/// middle (the midpoint of a longest shortest path found by two
+/// breadth-first sweeps inside the part) and the parts regrow, for a few
+/// rounds or until no center moves. The best-balanced growth is returned.
+///
+/// Parts grown this way are connected and compact, which suits mesh-like
+/// graphs, but boundaries move by whole layers of vertices, so balance is
+/// only approximate. Vertices that no center can reach go to the lightest
+/// part.
+pub fn initial_partition_bubble(g: &Graph, nparts: usize) -> Vec<usize> {
+    bubble_kway(g, nparts, None, &[], None)
+}
+
+/// Bubble growing behind [`initial_partition_bubble`].
+///
+/// `tpwgts`, `fixed` and `seed` act as in [`greedy_kway`]. Parts with
+/// pinned vertices always grow from their pins and are never recentered.
+pub(crate) fn bubble_kway(
+    g: &Graph,
+    nparts: usize,
+    tpwgts: Option<&[f64]>,
+    fixed: &[Option<usize>],
+    seed: Option<u64>,
+) -> Vec<usize> {
+    let n = g.n;
+    if nparts <= 1 || n == 0 {
+        return vec![0; n];
+    }
+
+    let shares: Vec<f64> = (0..nparts)
+        .map(|p| match tpwgts {
+            Some(t) => t[p] / t.iter().sum::<f64>(),
+            None => 1.0 / nparts as f64,
+        })
+        .collect();
+    let mut has_pins = vec![false; nparts];
+    for &k in fixed.iter().flatten() {
+        has_pins[k] = true;
+    }
+    let mut offsets = vec![0i64; nparts];
+    let mut centers = kway_seeds(g, nparts, fixed, seed);
+    let (mut part, mut worst) = balanced_bubbles(g, &shares, &centers, &mut offsets);
+    let mut best = (worst, part.clone());
+    for _ in 0..BUBBLE_ROUNDS {
+        let middle = part_middles(g, &part, nparts);
+        let mut next: Vec<usize> = (0..n)
+            .map(|u| fixed.get(u).copied().flatten().unwrap_or(usize::MAX))
+            .collect();
+        for p in (0..nparts).filter(|&p| !has_pins[p]) {
+            match middle[p] {
+                Some(c) => next[c] = p,
+                // Keep the old center of a part that could not be measured
+                None => (0..n)
+                    .filter(|&u| centers[u] == p)
+                    .for_each(|u| next[u] = p),
+            }
+        }
+        if next == centers {
+            break;
+        }
+        centers = next;
+        (part, worst) = balanced_bubbles(g, &shares, &centers, &mut offsets);
+        if worst < best.0 {
+            best = (worst, part.clone());
+        }
+    }
+    best.1
+}
+
+/// Grow bubbles from `centers`, adjusting `offsets` until every part is
+/// within [`BUBBLE_IMBALANCE`] of its share, for at most
+/// [`BUBBLE_BALANCE_STEPS`] steps. Returns the best-balanced growth seen
+/// with its largest part weight relative to the part's share.
+fn balanced_bubbles(
+    g: &Graph,
+    shares: &[f64],
+    centers: &[usize],
+    offsets: &mut [i64],
+) -> (Vec<usize>, f64) {
+    let nparts = shares.len();
+    let total: i64 = (0..g.n).map(|u| g.vertex_weight(u)).sum();
+    let mut best: Option<(f64, Vec<usize>)> = None;
+    for _ in 0..BUBBLE_BALANCE_STEPS {
+        let part = grow_bubbles(g, shares, centers, offsets);
+        let mut weight = vec![0i64; nparts];
+        for u in 0..g.n {
+            weight[part[u]] += g.vertex_weight(u);
+        }
+        let load: Vec<f64> = (0..nparts)
+            .map(|p| weight[p] as f64 / (shares[p] * total as f64))
+            .collect();
+        let worst = load.iter().copied().fold(0.0, f64::max);
+        if best.as_ref().is_none_or(|(b, _)| worst < *b) {
+            best = Some((worst, part));
+        }
+        if worst <= BUBBLE_IMBALANCE {
+            break;
+        }
+        // Move each boundary by about a third of the distance that would fix
+        // the load, taking a part's radius as the square root of its size;
+        // larger steps overshoot by whole layers and oscillate
+        for p in 0..nparts {
+            let radius = (shares[p] * g.n as f64).sqrt();
+            offsets[p] += ((load[p] - 1.0) * 0.3 * radius * HOP as f64) as i64;
+        }
+    }
+    let (worst, part) = best.expect("at least one growth runs");
+    (part, worst)
+}
+
+/// Assign every vertex to the part whose center (`centers[u] == p`, or
+/// `usize::MAX` for none) is nearest in hops plus `offsets[p] / HOP`, ties
+/// going to the lower part ID. Centers always keep their part.
+///
+/// A multi-source Dijkstra search; since every vertex joins the part that
+/// reached it first, parts are connected. Vertices no center reaches go to
+/// the part with the lowest weight relative to `shares`.
+fn grow_bubbles(g: &Graph, shares: &[f64], centers: &[usize], offsets: &[i64]) -> Vec<usize> {
+    let mut part = centers.to_vec();
+    let mut heap: BinaryHeap<Reverse<(i64, usize, usize)>> = BinaryHeap::new();
+    for u in 0..g.n {
+        if part[u] != usize::MAX {
+            for &v in g.neighbors(u) {
+                heap.push(Reverse((offsets[part[u]] + HOP, part[u], v)));
+            }
+        }
+    }
+    while let Some(Reverse((dist, p, u))) = heap.pop() {
+        if part[u] != usize::MAX {
+            continue;
+        }
+        part[u] = p;
+        for &v in g.neighbors(u) {
+            if part[v] == usize::MAX {
+                heap.push(Reverse((dist + HOP, p, v)));
+            }
+        }
+    }
+
+    let mut weight = vec![0i64; shares.len()];
+    for u in 0..g.n {
+        if part[u] != usize::MAX {
+            weight[part[u]] += g.vertex_weight(u);
+        }
+    }
+    for (u, p) in part.iter_mut().enumerate() {
+        if *p == usize::MAX {
+            let load = |p: usize| weight[p] as f64 / shares[p];
+            let lightest = (0..shares.len())
+                .min_by(|&a, &b| load(a).total_cmp(&load(b)).then(a.cmp(&b)))
+                .unwrap();
+            *p = lightest;
+            weight[lightest] += g.vertex_weight(u);
+        }
+    }
+    part
+}
+
+/// Middle vertex of every part, or `None` for an empty part.
+///
+/// A breadth-first sweep inside each part from its lowest vertex finds the
+/// farthest vertex `a`; a second sweep from `a` finds the farthest vertex
+/// `b`, and the middle is halfway back along the path from `b` to `a`. Ties
+/// go to the lowest vertex ID. Only the piece of a part that contains its
+/// lowest vertex is considered.
+fn part_middles(g: &Graph, part: &[usize], nparts: usize) -> Vec<Option<usize>> {
+    let farthest = |dist: &[usize]| {
+        let mut best: Vec<Option<(usize, usize)>> = vec![None; nparts];
+        for u in 0..g.n {
+            if dist[u] != usize::MAX && best[part[u]].is_none_or(|(d, _)| dist[u] > d) {
+                best[part[u]] = Some((dist[u], u));
+            }
+        }
+        best
+    };
+    let mut lowest: Vec<Option<usize>> = vec![None; nparts];
+    for u in (0..g.n).rev() {
+        lowest[part[u]] = Some(u);
+    }
+    let (dist, _) = sweep_within_parts(g, part, lowest.iter().flatten().copied());
+    let ends: Vec<usize> = farthest(&dist).iter().flatten().map(|&(_, u)| u).collect();
+    let (dist, parent) = sweep_within_parts(g, part, ends.into_iter());
+    farthest(&dist)
+        .into_iter()
+        .map(|end| {
+            end.map(|(d, mut u)| {
+                for _ in 0..d / 2 {
+                    u = parent[u];
+                }
+                u
+            })
+        })
+        .collect()
+}
+
+/// Breadth-first search from `sources` along edges inside parts only.
+///
+/// Returns the hop distance of every vertex (`usize::MAX` if unreached) and
+/// its parent in the search tree (itself for a source).
+fn sweep_within_parts(
+    g: &Graph,
+    part: &[usize],
+    sources: impl Iterator<Item = usize>,
+) -> (Vec<usize>, Vec<usize>) {
+    let mut dist = vec![usize::MAX; g.n];
+    let mut parent: Vec<usize> = (0..g.n).collect();
+    let mut queue: VecDeque<usize> = sources.collect();
+    for &u in &queue {
+        dist[u] = 0;
+    }
+    while let Some(u) = queue.pop_front() {
+        for &v in g.neighbors(u) {
+            if part[v] == part[u] && dist[v] == usize::MAX {
+                dist[v] = dist[u] + 1;
+                parent[v] = u;
+                queue.push_back(v);
+            }
+        }
+    }
+    (dist, parent)
+}
+
+/// Starting vertices of a direct k-way partition, as a partition vector
+/// with `usize::MAX` for every vertex not yet assigned.
+///
+/// Pinned vertices go to their parts, and every part without pins gets
+/// one seed: the unassigned vertex farthest from all earlier ones (see
+/// [`farthest_unassigned`]). With `seed` and no pins, the first of them is a
+/// random vertex instead of vertex 0.
+fn kway_seeds(g: &Graph, nparts: usize, fixed: &[Option<usize>], seed: Option<u64>) -> Vec<usize> {
+    let mut part = vec![usize::MAX; g.n];
+    let mut seeded = vec![false; nparts];
+    for (u, p) in part.iter_mut().enumerate() {
+        if let Some(k) = fixed.get(u).copied().flatten() {
+            *p = k;
+            seeded[k] = true;
+        }
+    }
+    let mut rng = seed.map(Rng::new);
+    let mut placed = seeded.contains(&true);
+    for p in (0..nparts).filter(|&p| !seeded[p]) {
+        let next = match rng.as_mut() {
+            Some(rng) if !placed => Some(rng.below(g.n)),
+            _ => farthest_unassigned(g, &part),
+        };
+        if let Some(v) = next {
+            part[v] = p;
+            placed = true;
+        }
+    }
+    part
+}
+
+/// Gain of adding unassigned `u` to part `p`: its edge weight into `p`
+/// minus its edge weight to other, already assigned parts.
+///
+/// Grows when a neighbor of `u` joins `p` and shrinks when one joins
+/// another part.
+fn growth_gain(g: &Graph, part: &[usize], u: usize, p: usize) -> i64 {
+    let (neighbors, weights) = (g.neighbors(u), g.edge_weights(u));
+    let (to_p, _) = weight_to_part(neighbors, weights, part, p);
+    let (unassigned, _) = weight_to_part(neighbors, weights, part, usize::MAX);
+    let total: i64 = if weights.is_empty() {
+        neighbors.len() as i64
+    } else {
+        weights.iter().sum()
+    };
+    to_p - (total - unassigned - to_p)
+}
+
+/// Unassigned vertex (`part[u] == usize::MAX`) farthest in hops from every
+/// assigned one, preferring unreachable vertices and then the lowest ID.
+fn farthest_unassigned(g: &Graph, part: &[usize]) -> Option<usize> {
+    let mut dist = vec![usize::MAX; g.n];
+    let mut queue: VecDeque<usize> = (0..g.n).filter(|&u| part[u] != usize::MAX).collect();
+    for &u in &queue {
+        dist[u] = 0;
+    }
+    while let Some(u) = queue.pop_front() {
+        for &v in g.neighbors(u) {
+            if dist[v] == usize::MAX {
+                dist[v] = dist[u] + 1;
+                queue.push_back(v);
+            }
+        }
+    }
+    (0..g.n)
+        .filter(|&u| part[u] == usize::MAX)
+        .max_by_key(|&u| (dist[u], Reverse(u)))
+}
+
+/// Partition a small graph into `nparts`, honoring pre-assigned vertices.
+///
+/// Whenever `fixed[u] == Some(k)`, vertex `u` ends up in part `k`; every
+/// bisection keeps pinned vertices on the side that contains their part.
+/// See [`check_fixed`] for the accepted input.
+pub fn initial_partition_fixed(g: &Graph, nparts: usize, fixed: &[Option<usize>]) -> Vec<usize> {
+    check_fixed(fixed, g.n, nparts);
+    partition_recursive(g, nparts, None, fixed, &Recursion::default())
+}
+
+/// Partition a small graph into `nparts` with target part weight fractions.
+///
+/// Part `p` aims for `tpwgts[p]` of the total vertex weight. Each recursive
+/// bisection splits the weight in proportion to the summed targets of the
+/// parts on either side. See [`check_tpwgts`] for the accepted input.
+pub fn initial_partition_tpwgts(g: &Graph, nparts: usize, tpwgts: &[f64]) -> Vec<usize> {
+    check_tpwgts(tpwgts, nparts);
+    partition_recursive(g, nparts, Some(tpwgts), &[], &Recursion::default())
+}
+
+/// Validate a target part weight vector.
+///
+/// Panics unless `tpwgts` has exactly `nparts` entries, all positive and
+/// finite. The entries need not sum to exactly 1.0; they are used relative to
+/// their sum.
+pub fn check_tpwgts(tpwgts: &[f64], nparts: usize) {
+    assert_eq!(tpwgts.len(), nparts, "tpwgts must have nparts entries");
+    assert!(
+        tpwgts.iter().all(|&w| w > 0.0 && w.is_finite()),
+        "tpwgts entries must be positive"
+    );
+}
+
+/// Validate a fixed-vertex vector.
+///
+/// Panics unless `fixed` has exactly `n` entries and every pinned part is
+/// below `nparts`.
+pub fn check_fixed(fixed: &[Option<usize>], n: usize, nparts: usize) {
+    assert_eq!(fixed.len(), n, "fixed must have one entry per vertex");
+    assert!(
+        fixed.iter().flatten().all(|&k| k < nparts),
+        "fixed part out of range"
+    );
+}
+
+/// Validate a list of frozen parts.
+///
+/// Panics unless every frozen part is below `nparts`.
+pub fn check_frozen(frozen_parts: &[usize], nparts: usize) {
+    if let Some(&p) = frozen_parts.iter().find(|&&p| p >= nparts) {
+        panic!("frozen part {} out of range for {} parts", p, nparts);
+    }
+}
+
+/// Settings shared by every bisection of [`partition_recursive`].
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Recursion<'a> {
+    /// Minimum weight of every final part, while feasible.
+    pub(crate) min_weight: i64,
+    /// Allowed imbalance of every final part.
+    pub(crate) imbalance: f64,
+    /// Per-constraint imbalance overriding `imbalance`, or empty.
+    pub(crate) ubvec: &'a [f64],
+    /// Split along the Fiedler vector instead of by greedy growing.
+    pub(crate) spectral: bool,
+    /// Randomize every bisection with this seed: greedy growing starts from
+    /// random vertices and the Fiedler estimate from a random vector.
+    pub(crate) seed: Option<u64>,
+    /// Number of seed vertices greedy growing tries per bisection.
+    pub(crate) seeds: usize,
+    /// How greedy growing picks its seed vertices.
+    pub(crate) strategy: SeedStrategy,
+}
+
+impl Default for Recursion<'_> {
+    fn default() -> Self {
+        Self {
+            min_weight: 0,
+            imbalance: MAX_IMBALANCE,
+            ubvec: &[],
+            spectral: false,
+            seed: None,
+            seeds: BISECTION_SEEDS,
+            strategy: SeedStrategy::Spread,
+        }
+    }
+}
+
+/// Recursive bisection, splitting by `tpwgts` if given and in proportion to
+/// the number of parts on each side otherwise.
+///
+/// `fixed` is either empty or pins vertices to parts, as in
+/// [`initial_partition_fixed`]. Every bisection asks each side for at least
+/// `opts.min_weight` per part it will be split into, so no final part falls
+/// below it while that is feasible.
+///
+/// Imbalance compounds down the recursion, so each bisection is held to
+/// `opts.imbalance` to the power `1 / depth`, where
+/// `depth = ceil(log2(nparts))` is the number of bisection levels: a part
+/// that is at most that much too heavy at every level ends up within
+/// `opts.imbalance` of its target. `opts.ubvec` is either empty or a
+/// per-constraint imbalance that overrides it, and is split across levels
+/// the same way.
+pub(crate) fn partition_recursive(
+    g: &Graph,
+    nparts: usize,
+    tpwgts: Option<&[f64]>,
+    fixed: &[Option<usize>],
+    opts: &Recursion,
+) -> Vec<usize> {
+    let depth = (nparts.max(2) as f64).log2().ceil();
+    let level_ubvec: Vec<f64> = opts.ubvec.iter().map(|ub| ub.powf(1.0 / depth)).collect();
+    let level = Recursion {
+        imbalance: opts.imbalance.powf(1.0 / depth),
+        ubvec: &level_ubvec,
+        ..*opts
+    };
+    bisect_recursive(g, nparts, tpwgts, fixed, &level)
+}
+
+/// One level of [`partition_recursive`], holding each bisection to
+/// `level.imbalance`, or per constraint to `level.ubvec` if nonempty.
+fn bisect_recursive(
+    g: &Graph,
+    nparts: usize,
+    tpwgts: Option<&[f64]>,
+    fixed: &[Option<usize>],
+    level: &Recursion,
+) -> Vec<usize> {
     if nparts <= 1 || g.n == 0 {
         return vec![0; g.n];
     }
 
-    let bisect = initial_bisection(g);
+    let left_parts = nparts / 2;
+    let frac0 = match tpwgts {
+        Some(t) => t[..left_parts].iter().sum::<f64>() / t.iter().sum::<f64>(),
+        None => left_parts as f64 / nparts as f64,
+    };
+    let side: Vec<Option<usize>> = fixed
+        .iter()
+        .map(|f| f.map(|k| usize::from(k >= left_parts)))
+        .collect();
+    let floors = [
+        level.min_weight * left_parts as i64,
+        level.min_weight * (nparts - left_parts) as i64,
+    ];
+    let cfg = PassConfig {
+        tpwgts: &[frac0, 1.0 - frac0],
+        fixed: &side,
+        imbalance: level.imbalance,
+        ubvec: level.ubvec,
+        ..PassConfig::default()
+    };
+    let mut bisect = bisection_with_fraction(g, frac0, &side, floors, &cfg, level);
+    rebalance(g, &mut bisect, 2, &cfg);
 
     if nparts == 2 {
         return bisect;
     }
 
     // Recursive bisection: split into two subsets, then partition each
-    let left_parts = nparts / 2;
     let right_parts = nparts - left_parts;
 
     // Collect vertices for each side
@@ -123,11 +1055,45 @@ pub fn initial_partition(g: &Graph, nparts: usize) -> Vec<usize> {
     let right_verts: Vec<usize> = (0..g.n).filter(|&u| bisect[u] == 1).collect();
 
     // Build subgraphs and recursively partition
-    let left_sub = build_subgraph(g, &left_verts);
-    let right_sub = build_subgraph(g, &right_verts);
+    let left_sub = g.induced_subgraph(&left_verts).0;
+    let right_sub = g.induced_subgraph(&right_verts).0;
 
-    let left_part = initial_partition(&left_sub, left_parts);
-    let right_part = initial_partition(&right_sub, right_parts);
+    let (left_fixed, right_fixed): (Vec<Option<usize>>, Vec<Option<usize>>) = if fixed.is_empty() {
+        (Vec::new(), Vec::new())
+    } else {
+        (
+            left_verts.iter().map(|&u| fixed[u]).collect(),
+            right_verts
+                .iter()
+                .map(|&u| fixed[u].map(|k| k - left_parts))
+                .collect(),
+        )
+    };
+
+    // The two halves are independent, so the `rayon` feature partitions
+    // them on separate threads
+    let left = || {
+        bisect_recursive(
+            &left_sub,
+            left_parts,
+            tpwgts.map(|t| &t[..left_parts]),
+            &left_fixed,
+            level,
+        )
+    };
+    let right = || {
+        bisect_recursive(
+            &right_sub,
+            right_parts,
+            tpwgts.map(|t| &t[left_parts..]),
+            &right_fixed,
+            level,
+        )
+    };
+    #[cfg(feature = "rayon")]
+    let (left_part, right_part) = rayon::join(left, right);
+    #[cfg(not(feature = "rayon"))]
+    let (left_part, right_part) = (left(), right());
 
     // Map back to original vertex IDs
     let mut part = vec![0usize; g.n];
@@ -141,41 +1107,35 @@ pub fn initial_partition(g: &Graph, nparts: usize) -> Vec<usize> {
     part
 }
 
-/// Build an induced subgraph from a subset of vertices.
-fn build_subgraph(g: &Graph, verts: &[usize]) -> Graph {
-    use std::collections::HashMap;
-
-    let n_sub = verts.len();
-    if n_sub == 0 {
-        return Graph::new(0, vec![0], Vec::new());
+/// Partition a graph into `nparts`, keeping `frozen_parts` as in `current`.
+///
+/// Vertices whose `current` part is frozen keep their assignment. All other
+/// vertices are partitioned from scratch among the remaining parts. If every
+/// part is frozen, `current` is returned unchanged. See [`check_frozen`] for
+/// the accepted input.
+pub fn initial_partition_frozen(
+    g: &Graph,
+    nparts: usize,
+    current: &[usize],
+    frozen_parts: &[usize],
+) -> Vec<usize> {
+    check_frozen(frozen_parts, nparts);
+    let mut frozen = vec![false; nparts];
+    for &p in frozen_parts {
+        frozen[p] = true;
     }
-
-    // Map global -> local vertex index
-    let mut global_to_local: HashMap<usize, usize> = HashMap::with_capacity(n_sub);
-    for (local, &global) in verts.iter().enumerate() {
-        global_to_local.insert(global, local);
+    let free_parts: Vec<usize> = (0..nparts).filter(|&p| !frozen[p]).collect();
+    if free_parts.is_empty() {
+        return current.to_vec();
     }
 
-    let mut xadj = vec![0usize; n_sub + 1];
-    let mut adjncy = Vec::new();
-    let mut adjwgt = Vec::new();
-    let mut vwgt = Vec::with_capacity(n_sub);
-
-    for (local_u, &global_u) in verts.iter().enumerate() {
-        vwgt.push(g.vertex_weight(global_u));
+    let free_verts: Vec<usize> = (0..g.n).filter(|&u| !frozen[current[u]]).collect();
+    let sub = g.induced_subgraph(&free_verts).0;
+    let sub_part = initial_partition(&sub, free_parts.len());
 
-        for k in 0..g.degree(global_u) {
-            let global_v = g.adjncy[g.xadj[global_u] + k];
-            if let Some(&local_v) = global_to_local.get(&global_v) {
-                adjncy.push(local_v);
-                adjwgt.push(g.edge_weight(global_u, k));
-            }
-        }
-        xadj[local_u + 1] = adjncy.len();
+    let mut part = current.to_vec();
+    for (local_idx, &global_v) in free_verts.iter().enumerate() {
+        part[global_v] = free_parts[sub_part[local_idx]];
     }
-
-    let mut sub = Graph::new(n_sub, xadj, adjncy);
-    sub.adjwgt = adjwgt;
-    sub.vwgt = vwgt;
-    sub
+    part
 }
@@ -0,0 +1,205 @@
+//! Partition quality metrics.
+//!
+//! Helpers for judging a finished partition beyond its edge cut.
+
+use crate::graph::Graph;
+
+/// Summary statistics of a partition, from [`Graph::partition_stats`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct PartitionStats {
+    /// Total weight of edges crossing part boundaries.
+    pub edge_cut: i64,
+    /// Total communication volume: for every vertex, its size (see
+    /// [`Graph::vertex_size`]) times the number of distinct parts other than
+    /// its own among its neighbors.
+    pub comm_volume: i64,
+    /// Total vertex weight of each part.
+    pub part_weights: Vec<i64>,
+    /// Heaviest part weight divided by the average part weight.
+    pub imbalance: f64,
+    /// Number of vertices with a neighbor in a different part.
+    pub boundary_vertices: usize,
+}
+
+impl Graph {
+    /// Compute edge cut, communication volume, balance and boundary size of
+    /// `part`.
+    ///
+    /// Isolated vertices contribute nothing to cut or volume. Empty parts are
+    /// included in `part_weights` with weight 0; the imbalance is `1.0` when
+    /// the total vertex weight is 0.
+    pub fn partition_stats(&self, part: &[usize], nparts: usize) -> PartitionStats {
+        let nparts = nparts.max(1);
+        let mut part_weights = vec![0i64; nparts];
+        let mut comm_volume = 0i64;
+        // seen[p] == u + 1 marks part p as already counted for vertex u
+        let mut seen = vec![0usize; nparts];
+        let mut boundary_vertices = 0usize;
+
+        for u in 0..self.n {
+            part_weights[part[u]] += self.vertex_weight(u);
+            let mut distinct = 0i64;
+            for &v in self.neighbors(u) {
+                let p = part[v];
+                if p != part[u] && seen[p] != u + 1 {
+                    seen[p] = u + 1;
+                    distinct += 1;
+                }
+            }
+            comm_volume += distinct * self.vertex_size(u);
+            boundary_vertices += usize::from(distinct > 0);
+        }
+
+        let total_weight: i64 = part_weights.iter().sum();
+        let max_weight = part_weights.iter().copied().max().unwrap_or(0);
+        PartitionStats {
+            edge_cut: self.edge_cut(part),
+            comm_volume,
+            imbalance: imbalance(max_weight as f64, total_weight as f64, nparts),
+            part_weights,
+            boundary_vertices,
+        }
+    }
+
+    /// Total weight of `u`'s edges to vertices in a different part.
+    ///
+    /// Returns 0 for an interior vertex (all neighbors in its own part) and
+    /// for an isolated vertex. Summed over all vertices this is twice the
+    /// edge cut.
+    pub fn vertex_cut_contribution(&self, u: usize, part: &[usize]) -> i64 {
+        (0..self.degree(u))
+            .filter(|&k| part[self.adjncy[self.xadj[u] + k]] != part[u])
+            .map(|k| self.edge_weight(u, k))
+            .sum()
+    }
+
+    /// Boundary vertices of `part` in increasing order: those with at least
+    /// one neighbor in a different part.
+    ///
+    /// Interior and isolated vertices are excluded. See
+    /// [`Graph::iter_boundary`] to stream them without allocating.
+    pub fn boundary_vertices(&self, part: &[usize]) -> Vec<usize> {
+        self.iter_boundary(part).map(|(u, _)| u).collect()
+    }
+
+    /// Lazily yield `(u, contribution)` for every boundary vertex `u` of
+    /// `part`, in increasing order, where `contribution` is
+    /// [`Graph::vertex_cut_contribution`].
+    ///
+    /// A vertex whose only cut edges have weight 0 is still on the boundary
+    /// and is yielded with contribution 0.
+    pub fn iter_boundary<'a>(
+        &'a self,
+        part: &'a [usize],
+    ) -> impl Iterator<Item = (usize, i64)> + 'a {
+        (0..self.n)
+            .filter(|&u| self.neighbors(u).iter().any(|&v| part[v] != part[u]))
+            .map(|u| (u, self.vertex_cut_contribution(u, part)))
+    }
+
+    /// Whether every non-empty part of `part` induces a connected subgraph.
+    ///
+    /// Empty parts count as contiguous. See
+    /// [`crate::refine::enforce_contiguity`] to repair a partition that is
+    /// not.
+    pub fn part_is_contiguous(&self, part: &[usize], nparts: usize) -> bool {
+        let (fragment, _) = part_fragments(self, part);
+        // First fragment seen in each part; any other means a split part
+        let mut first = vec![usize::MAX; nparts.max(1)];
+        for u in 0..self.n {
+            let p = part[u];
+            if first[p] == usize::MAX {
+                first[p] = fragment[u];
+            } else if first[p] != fragment[u] {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Label the connected pieces of every part of `part`.
+///
+/// Two vertices share a fragment when a path within their part joins them.
+/// Returns `(fragment, count)` with fragments numbered `0..count` in order
+/// of their lowest vertex.
+pub(crate) fn part_fragments(g: &Graph, part: &[usize]) -> (Vec<usize>, usize) {
+    let mut fragment = vec![usize::MAX; g.n];
+    let mut queue = std::collections::VecDeque::new();
+    let mut count = 0usize;
+    for s in 0..g.n {
+        if fragment[s] != usize::MAX {
+            continue;
+        }
+        fragment[s] = count;
+        queue.push_back(s);
+        while let Some(u) = queue.pop_front() {
+            for &v in g.neighbors(u) {
+                if fragment[v] == usize::MAX && part[v] == part[s] {
+                    fragment[v] = count;
+                    queue.push_back(v);
+                }
+            }
+        }
+        count += 1;
+    }
+    (fragment, count)
+}
+
+/// Count- and weight-based balance of a partition, from [`balance_summary`].
+///
+/// Imbalances are the largest part divided by the perfectly balanced part
+/// size, so `1.0` means perfect balance. Empty parts count as 0 toward the
+/// minima.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BalanceSummary {
+    /// Largest vertex count divided by `n / nparts`.
+    pub count_imbalance: f64,
+    /// Largest vertex weight divided by `total_weight / nparts`.
+    pub weight_imbalance: f64,
+    /// Vertex count of the most populated part.
+    pub max_count: usize,
+    /// Vertex count of the least populated part.
+    pub min_count: usize,
+    /// Vertex weight of the heaviest part.
+    pub max_weight: i64,
+    /// Vertex weight of the lightest part.
+    pub min_weight: i64,
+}
+
+/// Summarize both count balance and weight balance of `part`.
+///
+/// Returns imbalances of `1.0` for an empty graph or zero total weight.
+pub fn balance_summary(g: &Graph, part: &[usize], nparts: usize) -> BalanceSummary {
+    let nparts = nparts.max(1);
+    let mut counts = vec![0usize; nparts];
+    let mut weights = vec![0i64; nparts];
+    for u in 0..g.n {
+        counts[part[u]] += 1;
+        weights[part[u]] += g.vertex_weight(u);
+    }
+
+    let max_count = counts.iter().copied().max().unwrap_or(0);
+    let min_count = counts.iter().copied().min().unwrap_or(0);
+    let max_weight = weights.iter().copied().max().unwrap_or(0);
+    let min_weight = weights.iter().copied().min().unwrap_or(0);
+    let total_weight: i64 = weights.iter().sum();
+
+    BalanceSummary {
+        count_imbalance: imbalance(max_count as f64, g.n as f64, nparts),
+        weight_imbalance: imbalance(max_weight as f64, total_weight as f64, nparts),
+        max_count,
+        min_count,
+        max_weight,
+        min_weight,
+    }
+}
+
+/// Ratio of `max` to the average share `total / nparts`.
+fn imbalance(max: f64, total: f64, nparts: usize) -> f64 {
+    if total <= 0.0 {
+        1.0
+    } else {
+        max * nparts as f64 / total
+    }
+}
@@ -4,113 +4,2141 @@
 //! this module improves the partition by swapping boundary vertices between
 //! parts to reduce the edge cut while maintaining balance.
 
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::config::PartitionConfig;
 use crate::graph::Graph;
+use crate::kernels::weight_to_part;
+use crate::partition::{check_frozen, check_tpwgts};
+use crate::quality::part_fragments;
+use crate::rng::Rng;
 
 /// Maximum allowed imbalance factor (5% above perfect balance).
-const MAX_IMBALANCE: f64 = 1.05;
+pub(crate) const MAX_IMBALANCE: f64 = 1.05;
+
+/// Fixed-point scale applied to importance-weighted edges so fractional
+/// importances survive conversion back to integer gains.
+const IMPORTANCE_SCALE: f64 = 1000.0;
+
+/// Fixed-point scale applied to gains when a migration penalty is active,
+/// so fractional migration weights are not rounded away.
+const MIGRATION_SCALE: i64 = 1000;
+
+/// Moves a [`RefineMode::Rollback`] pass may make past its best cut before
+/// it gives up and rolls back.
+const MAX_UNPRODUCTIVE_MOVES: usize = 100;
+
+/// How an FM pass treats moves that do not reduce the edge cut.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RefineMode {
+    /// Hill climbing: end the pass at the first move with non-positive gain.
+    #[default]
+    Greedy,
+    /// Classic FM: keep moving the best unlocked vertex even at negative
+    /// gain, then roll back every move after the lowest cut seen.
+    Rollback,
+    /// Randomized greedy k-way refinement, as in METIS's k-way code: visit
+    /// the boundary vertices in a seeded random order and apply every
+    /// positive-gain move at once. Faster than best-first, usually with a
+    /// similar cut.
+    Random,
+    /// Size-constrained label propagation: sweep the boundary vertices in
+    /// ID order and move each to the neighboring part it is most strongly
+    /// connected to, if that beats its own part, or ties with it and is
+    /// lighter. The cheapest mode, meant for very large graphs.
+    LabelPropagation,
+    /// Tabu search: each pass makes up to `iterations` best moves, negative
+    /// gains included, and a vertex that moved may not move again for the
+    /// next `tenure` moves unless that would beat the best cut of the pass.
+    /// Vertices may move several times per pass; the pass ends at the best
+    /// cut it saw.
+    Tabu { tenure: usize, iterations: usize },
+    /// Parallel greedy refinement: the boundary vertices are colored so
+    /// that no two neighbors share a color, and the vertices of each color
+    /// class are scored at once against the same partition. Their
+    /// positive-gain moves are then made together, best gain first, each
+    /// reserving room in its destination part; moves that no longer fit are
+    /// dropped. With the `rayon` feature each class is scored on multiple
+    /// threads; the partition is the same either way.
+    Colored,
+}
+
+/// Quantity minimized by FM refinement.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Objective {
+    /// Total weight of edges between parts.
+    #[default]
+    EdgeCut,
+    /// Total communication volume: each vertex's size (see
+    /// [`Graph::vertex_size`]) times the number of other parts among its
+    /// neighbors.
+    CommVolume,
+}
 
 /// Refine a k-way partition using boundary FM-style swaps.
 ///
 /// Performs up to `max_passes` passes. Each pass iterates over boundary
 /// vertices and moves them to the neighboring part that yields the greatest
-/// edge-cut reduction while maintaining balance.
+/// edge-cut reduction while maintaining balance. Ties in gain are broken in
+/// favor of the currently lighter destination part.
 pub fn fm_refine(g: &Graph, part: &mut [usize], nparts: usize, max_passes: usize) {
+    refine_passes(g, part, nparts, max_passes, &PassConfig::default());
+}
+
+/// Refine a k-way partition towards target part weight fractions.
+///
+/// Part `p` may grow up to `tpwgts[p]` of the total vertex weight times the
+/// imbalance tolerance, instead of the uniform `1 / nparts` share used by
+/// [`fm_refine`]. See [`check_tpwgts`] for the accepted input.
+pub fn fm_refine_tpwgts(
+    g: &Graph,
+    part: &mut [usize],
+    nparts: usize,
+    max_passes: usize,
+    tpwgts: &[f64],
+) {
+    check_tpwgts(tpwgts, nparts);
+    let cfg = PassConfig {
+        tpwgts,
+        ..PassConfig::default()
+    };
+    refine_passes(g, part, nparts, max_passes, &cfg);
+}
+
+/// Refine a k-way partition with edge weights scaled by `edge_importance`.
+///
+/// `edge_importance` is aligned with `adjncy`; the gain of a move is computed
+/// with each edge weight multiplied by its importance, so cutting important
+/// edges is penalized more heavily. With `None` this is identical to
+/// [`fm_refine`].
+pub fn fm_refine_with_importance(
+    g: &Graph,
+    part: &mut [usize],
+    nparts: usize,
+    max_passes: usize,
+    edge_importance: Option<&[f64]>,
+) {
+    match edge_importance {
+        None => fm_refine(g, part, nparts, max_passes),
+        Some(importance) => {
+            let scaled = scale_by_importance(g, importance);
+            fm_refine(&scaled, part, nparts, max_passes);
+        }
+    }
+}
+
+/// Copy of `g` whose edge weights are multiplied by `importance`, in
+/// fixed point. Products too large for an `i64` saturate.
+fn scale_by_importance(g: &Graph, importance: &[f64]) -> Graph {
+    assert_eq!(
+        importance.len(),
+        g.adjncy.len(),
+        "edge_importance must have one entry per adjncy entry"
+    );
+    assert!(
+        importance.iter().all(|&x| x >= 0.0 && x.is_finite()),
+        "edge_importance entries must be finite and non-negative"
+    );
+    let mut scaled = g.clone();
+    scaled.adjwgt = Vec::with_capacity(g.adjncy.len());
+    for u in 0..g.n {
+        for (k, (_, w)) in g.edges(u).enumerate() {
+            let factor = (importance[g.xadj[u] + k] * IMPORTANCE_SCALE).round() as i64;
+            scaled.adjwgt.push(w.saturating_mul(factor));
+        }
+    }
+    scaled
+}
+
+/// Refine a k-way partition while leaving `frozen_parts` untouched.
+///
+/// No vertex is moved out of a frozen part and no vertex is moved into one,
+/// so the vertex sets of frozen parts are exactly preserved. All other parts
+/// are refined as in [`fm_refine`]. See [`check_frozen`] for the accepted
+/// input.
+pub fn fm_refine_frozen(
+    g: &Graph,
+    part: &mut [usize],
+    nparts: usize,
+    max_passes: usize,
+    frozen_parts: &[usize],
+) {
+    check_frozen(frozen_parts, nparts);
+    let mut frozen = vec![false; nparts];
+    for &p in frozen_parts {
+        frozen[p] = true;
+    }
+    let cfg = PassConfig {
+        frozen: &frozen,
+        ..PassConfig::default()
+    };
+    refine_passes(g, part, nparts, max_passes, &cfg);
+}
+
+/// Refine a k-way partition, choosing how passes handle non-improving moves.
+///
+/// With [`RefineMode::Greedy`] this is identical to [`fm_refine`]. With
+/// [`RefineMode::Rollback`] each pass may climb out of a local minimum by
+/// accepting negative-gain moves; the partition after a pass is the best
+/// prefix of its moves, so the cut never increases. With
+/// [`RefineMode::Random`] the visit order is seeded with 0.
+pub fn fm_refine_mode(
+    g: &Graph,
+    part: &mut [usize],
+    nparts: usize,
+    max_passes: usize,
+    mode: RefineMode,
+) {
+    let cfg = PassConfig {
+        mode,
+        ..PassConfig::default()
+    };
+    refine_passes(g, part, nparts, max_passes, &cfg);
+}
+
+/// Refine a k-way partition for the given [`Objective`].
+///
+/// With [`Objective::EdgeCut`] this is identical to [`fm_refine`]. With
+/// [`Objective::CommVolume`] the gain of a move is the reduction in total
+/// communication volume (see [`crate::PartitionStats::comm_volume`]), so a
+/// vertex already talking to a part can join it without the move being
+/// charged for every cut edge.
+pub fn fm_refine_objective(
+    g: &Graph,
+    part: &mut [usize],
+    nparts: usize,
+    max_passes: usize,
+    objective: Objective,
+) {
+    let cfg = PassConfig {
+        objective,
+        ..PassConfig::default()
+    };
+    refine_passes(g, part, nparts, max_passes, &cfg);
+}
+
+/// Refine a k-way partition while keeping few pairs of parts adjacent.
+///
+/// Like [`fm_refine`], but every pair of parts joined by at least one edge
+/// also counts against the cut: a move that makes two parts adjacent costs
+/// as much as cutting the edges of an average vertex (its weighted degree,
+/// rounded up), and a move that separates two parts saves as much. This
+/// keeps the number of neighboring subdomains of each part down.
+pub fn fm_refine_minconn(g: &Graph, part: &mut [usize], nparts: usize, max_passes: usize) {
+    let cfg = PassConfig {
+        minconn: true,
+        ..PassConfig::default()
+    };
+    refine_passes(g, part, nparts, max_passes, &cfg);
+}
+
+/// Refine a k-way partition without moving pre-assigned vertices.
+///
+/// Vertices with `fixed[u] == Some(k)` are treated as permanently locked, so
+/// they stay wherever `part` has them (normally part `k`). All other vertices
+/// are refined as in [`fm_refine`].
+pub fn fm_refine_fixed(
+    g: &Graph,
+    part: &mut [usize],
+    nparts: usize,
+    max_passes: usize,
+    fixed: &[Option<usize>],
+) {
+    assert_eq!(fixed.len(), g.n, "fixed must have one entry per vertex");
+    let cfg = PassConfig {
+        fixed,
+        ..PassConfig::default()
+    };
+    refine_passes(g, part, nparts, max_passes, &cfg);
+}
+
+/// Refine a k-way partition as in [`fm_refine`], reporting each pass.
+///
+/// After every pass, `observer(pass, edge_cut, moves)` receives the 0-based
+/// pass index, the edge cut after the pass and the number of moves the pass
+/// kept. The last report has `moves == 0` unless `max_passes` ran out
+/// first, since refinement stops after a pass without improvement.
+pub fn fm_refine_with_observer(
+    g: &Graph,
+    part: &mut [usize],
+    nparts: usize,
+    max_passes: usize,
+    observer: &mut dyn FnMut(usize, i64, usize),
+) {
+    refine_passes_observed(
+        g,
+        part,
+        nparts,
+        max_passes,
+        &PassConfig::default(),
+        Some(observer),
+        &mut RefineScratch::default(),
+    );
+}
+
+/// Run up to `max_passes` passes of [`fm_pass`] under `cfg`, stopping early
+/// once a pass makes no improvement. Returns the edge cut of the refined
+/// partition.
+pub(crate) fn refine_passes(
+    g: &Graph,
+    part: &mut [usize],
+    nparts: usize,
+    max_passes: usize,
+    cfg: &PassConfig,
+) -> i64 {
+    refine_passes_observed(
+        g,
+        part,
+        nparts,
+        max_passes,
+        cfg,
+        None,
+        &mut RefineScratch::default(),
+    )
+}
+
+/// [`refine_passes`], calling `observer(pass, edge_cut, moves)` after each
+/// pass if given. Returns the edge cut of the refined partition.
+///
+/// The cut is tracked as vertices move, so it costs one scan of the graph
+/// up front rather than one per pass. Passes also stop once a pass lowers
+/// the cut by less than `cfg.min_improvement` times the cut before it.
+/// Per-vertex working arrays are taken from `scratch` and left there for
+/// the next call.
+pub(crate) fn refine_passes_observed(
+    g: &Graph,
+    part: &mut [usize],
+    nparts: usize,
+    max_passes: usize,
+    cfg: &PassConfig,
+    mut observer: Option<&mut dyn FnMut(usize, i64, usize)>,
+    scratch: &mut RefineScratch,
+) -> i64 {
     if g.n == 0 || nparts <= 1 {
-        return;
+        return g.edge_cut(part);
     }
 
-    for _pass in 0..max_passes {
-        let improved = fm_pass(g, part, nparts);
-        if !improved {
+    scratch.boundary.reset(g, part, nparts);
+    let mut before = scratch.boundary.cut();
+    let mut rng = Rng::new(cfg.seed);
+    for pass in 0..max_passes {
+        let boundary = &mut scratch.boundary;
+        let moves = match cfg.mode {
+            RefineMode::Random => sweep_pass(g, part, nparts, cfg, boundary, Some(&mut rng)),
+            RefineMode::LabelPropagation => sweep_pass(g, part, nparts, cfg, boundary, None),
+            RefineMode::Tabu { tenure, iterations } => {
+                tabu_pass(g, part, nparts, cfg, scratch, tenure, iterations)
+            }
+            RefineMode::Colored => colored_pass(g, part, nparts, cfg, boundary),
+            RefineMode::Greedy | RefineMode::Rollback => fm_pass(g, part, nparts, cfg, scratch),
+        };
+        let cut = scratch.boundary.cut();
+        if let Some(observer) = observer.as_deref_mut() {
+            observer(pass, cut, moves);
+        }
+        let converged = cfg.min_improvement > 0.0
+            && ((before - cut) as f64) < cfg.min_improvement * before as f64;
+        if moves == 0 || converged {
             break;
         }
+        before = cut;
+    }
+    scratch.boundary.cut()
+}
+
+/// Per-vertex working arrays of [`refine_passes_observed`], kept by a
+/// [`crate::Workspace`] so that repeated refinement reuses their memory.
+#[derive(Debug, Default)]
+pub(crate) struct RefineScratch {
+    boundary: Boundary,
+    /// Vertices that may not move for the rest of the current pass.
+    locked: Vec<bool>,
+}
+
+impl RefineScratch {
+    /// Reset `locked` to exactly the pinned vertices of `cfg`.
+    fn lock_pinned(&mut self, n: usize, cfg: &PassConfig) {
+        self.locked.clear();
+        if cfg.fixed.is_empty() {
+            self.locked.resize(n, false);
+        } else {
+            self.locked.extend(cfg.fixed.iter().map(Option::is_some));
+        }
     }
 }
 
-/// Single FM refinement pass. Returns `true` if any improvement was made.
-fn fm_pass(g: &Graph, part: &mut [usize], nparts: usize) -> bool {
-    let n = g.n;
+/// Bring every part within `imbalance` times the average part weight.
+///
+/// FM passes only make moves that keep the destination within bounds, so
+/// they cannot repair a partition that starts out overweight, such as one
+/// projected from a coarser graph. This moves the boundary vertices that
+/// lose the least cut out of overweight parts first, falling back to
+/// interior vertices, and is run by the multilevel pipeline before each
+/// level's refinement. Run it before [`fm_refine`] on an externally built
+/// partition. Balance may be infeasible, e.g. with a vertex heavier than a
+/// part's bound, in which case the excess is only reduced.
+pub fn enforce_balance(g: &Graph, part: &mut [usize], nparts: usize, imbalance: f64) {
+    assert!(imbalance >= 1.0, "imbalance must be at least 1.0");
+    let cfg = PassConfig {
+        imbalance,
+        ..PassConfig::default()
+    };
+    rebalance(g, part, nparts, &cfg);
+}
 
-    // Compute part weights
-    let mut part_weight = vec![0i64; nparts];
-    for u in 0..n {
-        part_weight[part[u]] += g.vertex_weight(u);
+/// Improve an externally supplied k-way partition and return its edge cut.
+///
+/// `part` may come from anywhere and be arbitrarily imbalanced; every entry
+/// must be below `nparts`. Pinned vertices are first put in their parts,
+/// then overweight parts are repaired as by [`enforce_balance`] and the
+/// partition is refined as on the finest level of
+/// [`PartitionConfig::partition`]: `opts` supplies the balance bounds,
+/// target weights, pins, refinement mode, objective and pass count, and
+/// flow refinement and contiguity repair run if enabled. The part count
+/// set in `opts` is ignored in favor of `nparts`.
+pub fn refine_kway(g: &Graph, part: &mut [usize], nparts: usize, opts: &PartitionConfig) -> i64 {
+    assert_eq!(part.len(), g.n, "part must have one entry per vertex");
+    assert!(
+        part.iter().all(|&p| p < nparts),
+        "part IDs must be below nparts"
+    );
+    opts.clone().nparts(nparts).validate(g);
+    for (u, pin) in opts.fixed.iter().enumerate() {
+        if let Some(p) = *pin {
+            part[u] = p;
+        }
+    }
+    let cfg = PassConfig {
+        tpwgts: &opts.tpwgts,
+        fixed: &opts.fixed,
+        mode: opts.refine_mode,
+        objective: opts.objective,
+        imbalance: opts.imbalance,
+        ubvec: &opts.ubvec,
+        min_weight: opts.min_part_weight,
+        minconn: opts.minconn,
+        seed: opts.seed.unwrap_or(0),
+        min_improvement: opts.min_improvement,
+        ..PassConfig::default()
+    };
+    rebalance(g, part, nparts, &cfg);
+    let cut = refine_passes(g, part, nparts, opts.refine_passes, &cfg);
+    if !opts.flow_refinement && !opts.contiguous {
+        return cut;
+    }
+    if opts.flow_refinement {
+        flow_passes(g, part, nparts, &cfg);
     }
-    let total_weight: i64 = part_weight.iter().sum();
-    let max_part_weight = (total_weight as f64 * MAX_IMBALANCE / nparts as f64).ceil() as i64;
+    if opts.contiguous {
+        enforce_contiguity(g, part, nparts);
+    }
+    g.edge_cut(part)
+}
 
-    let mut improved = false;
-    let mut locked = vec![false; n];
+/// Total weight by which the parts of `part` exceed their upper bounds
+/// under `cfg`, summed over parts and constraints; 0 when balanced.
+pub(crate) fn excess_weight(g: &Graph, part: &[usize], nparts: usize, cfg: &PassConfig) -> i64 {
+    Balance::new(g, part, nparts, cfg).excess()
+}
 
-    // Iterate: find best move among all boundary vertices
-    for _iter in 0..n {
-        let mut best_u = None;
-        let mut best_to = 0usize;
-        let mut best_gain = i64::MIN;
+/// Upper bound on every part weight under `cfg`, as used by [`rebalance`]:
+/// entry `p * ncon + c` bounds part `p` on constraint `c`.
+pub(crate) fn max_part_weights(g: &Graph, nparts: usize, cfg: &PassConfig) -> Vec<i64> {
+    Balance::new(g, &vec![0; g.n], nparts, cfg).max
+}
 
-        for u in 0..n {
-            if locked[u] {
-                continue;
-            }
+/// Move vertices out of parts that exceed their upper bound under `cfg`.
+///
+/// Each round scores every movable vertex of an overweight part by its best
+/// move to a part with room: the adjacent part that gains the most edge
+/// cut, ties going to the lighter part. Only if no boundary vertex has such
+/// a move are interior vertices sent to the lightest part with room. Moves
+/// are applied highest gain first while the source is still overweight and
+/// the destination still has room, and rounds repeat until every part fits
+/// or a round moves nothing. The total excess over the bounds drops with
+/// every move, so this terminates even when balance is infeasible.
+///
+/// Pinned vertices, frozen parts and `cfg.min_weight` are respected; gains
+/// are always measured in edge cut.
+pub(crate) fn rebalance(g: &Graph, part: &mut [usize], nparts: usize, cfg: &PassConfig) {
+    if g.n == 0 || nparts <= 1 {
+        return;
+    }
+    let frozen = |p: usize| !cfg.frozen.is_empty() && cfg.frozen[p];
+    let pinned = |u: usize| !cfg.fixed.is_empty() && cfg.fixed[u].is_some();
+    let mut balance = Balance::new(g, part, nparts, cfg);
+    let mut ext = vec![0i64; nparts];
+    let mut touched: Vec<usize> = Vec::new();
 
-            let from = part[u];
+    loop {
+        let over: Vec<bool> = (0..nparts).map(|p| balance.overweight(p)).collect();
+        if !over.contains(&true) {
+            break;
+        }
 
-            // Compute external edges per part
-            let mut ext = vec![0i64; nparts];
+        // Candidate moves as (gain, vertex, destination)
+        let mut moves: Vec<(i64, usize, usize)> = Vec::new();
+        let mut interior: Vec<(i64, usize)> = Vec::new();
+        for u in 0..g.n {
+            let from = part[u];
+            if !over[from] || frozen(from) || pinned(u) || !balance.can_leave(g, u, from) {
+                continue;
+            }
             let mut int = 0i64;
-            for k in 0..g.degree(u) {
-                let v = g.adjncy[g.xadj[u] + k];
-                let w = g.edge_weight(u, k);
-                if part[v] == from {
+            for (v, w) in g.edges(u) {
+                let q = part[v];
+                if q == from {
                     int += w;
                 } else {
-                    ext[part[v]] += w;
+                    if !touched.contains(&q) {
+                        touched.push(q);
+                    }
+                    ext[q] += w;
                 }
             }
+            let best = touched
+                .iter()
+                .copied()
+                .filter(|&q| !frozen(q) && balance.fits(g, u, q))
+                .max_by_key(|&q| (ext[q], Reverse(balance.load(q)), Reverse(q)));
+            match best {
+                Some(q) => moves.push((ext[q] - int, u, q)),
+                None => interior.push((-int, u)),
+            }
+            for &q in &touched {
+                ext[q] = 0;
+            }
+            touched.clear();
+        }
+        if moves.is_empty() {
+            for (gain, u) in interior {
+                let lightest = (0..nparts)
+                    .filter(|&q| q != part[u] && !frozen(q) && balance.fits(g, u, q))
+                    .min_by_key(|&q| (balance.load(q), q));
+                if let Some(q) = lightest {
+                    moves.push((gain, u, q));
+                }
+            }
+        }
+
+        moves.sort_unstable_by_key(|&(gain, u, _)| (Reverse(gain), u));
+        let mut moved = false;
+        for (_, u, to) in moves {
+            let from = part[u];
+            if balance.overweight(from) && balance.can_leave(g, u, from) && balance.fits(g, u, to) {
+                balance.move_vertex(g, u, from, to);
+                part[u] = to;
+                moved = true;
+            }
+        }
+        if !moved {
+            break;
+        }
+    }
+}
+
+/// Restrictions and strategy for a single [`fm_pass`].
+pub(crate) struct PassConfig<'a> {
+    /// Either empty or flags, per part, whether the part is closed to moves
+    /// in both directions.
+    pub(crate) frozen: &'a [bool],
+    /// Either empty (uniform targets) or each part's target fraction of the
+    /// total weight.
+    pub(crate) tpwgts: &'a [f64],
+    /// Either empty or marks pinned vertices, which start the pass locked.
+    pub(crate) fixed: &'a [Option<usize>],
+    pub(crate) mode: RefineMode,
+    pub(crate) objective: Objective,
+    /// Allowed part weight relative to its target, e.g. `1.05` for 5%.
+    pub(crate) imbalance: f64,
+    /// Either empty (every constraint uses `imbalance`) or the allowed
+    /// imbalance of each constraint.
+    pub(crate) ubvec: &'a [f64],
+    /// Either empty or the part each vertex started in. Moving a vertex off
+    /// its original part costs `migration_weight` times its weight.
+    pub(crate) origin: &'a [usize],
+    pub(crate) migration_weight: f64,
+    /// No move may take a part below this weight on any constraint.
+    pub(crate) min_weight: i64,
+    /// Charge each new pair of adjacent parts; see [`fm_refine_minconn`].
+    pub(crate) minconn: bool,
+    /// Seed of the visit order in [`RefineMode::Random`].
+    pub(crate) seed: u64,
+    /// Stop refining once a pass lowers the cut by less than this fraction.
+    pub(crate) min_improvement: f64,
+}
+
+impl Default for PassConfig<'_> {
+    fn default() -> Self {
+        Self {
+            frozen: &[],
+            tpwgts: &[],
+            fixed: &[],
+            mode: RefineMode::default(),
+            objective: Objective::default(),
+            imbalance: MAX_IMBALANCE,
+            ubvec: &[],
+            origin: &[],
+            migration_weight: 0.0,
+            min_weight: 0,
+            minconn: false,
+            seed: 0,
+            min_improvement: 0.0,
+        }
+    }
+}
+
+/// Single FM refinement pass. Returns the number of moves kept, which is 0
+/// if no improvement was made.
+///
+/// Candidate moves are kept in a max-heap keyed by gain, so picking the best
+/// move is O(log n) and only the neighbors of a moved vertex are re-scored.
+/// Part weights also change as vertices move; rather than re-scoring every
+/// vertex after each move, an entry is re-evaluated when it reaches the top
+/// of the heap and pushed back if its best move changed. Ties in gain prefer
+/// the destination that was lighter when the entry was scored, then the
+/// lower vertex ID.
+///
+/// A move never takes the last vertex out of a part, so refinement cannot
+/// empty a part that the initial partition filled, nor takes a part below
+/// `cfg.min_weight`. Together with the upper bounds this can leave a vertex
+/// with no feasible move, in which case it stays put.
+///
+/// With several balance constraints a move is only allowed if it keeps the
+/// destination within its bound on every constraint, and "lighter" compares
+/// the destination's weight summed over all constraints. Each vertex moves
+/// at most once per pass, so conflicting constraints cannot make the pass
+/// oscillate.
+///
+/// With `cfg.minconn` each gain also counts the pairs of parts the move
+/// separates minus the pairs it makes adjacent, weighted by
+/// [`PartAdjacency::penalty`].
+///
+/// Gains are measured in `cfg.objective`. Every move is recorded with its
+/// gain. In [`RefineMode::Rollback`] the
+/// pass continues through non-positive gains and finally undoes the moves
+/// after the highest cumulative gain; in greedy mode it stops at the first
+/// non-positive gain, so nothing is undone.
+///
+/// Only vertices on `refine`'s boundary are scored, since no other vertex
+/// has a move; the boundary is updated as vertices move and matches `part`
+/// on return.
+fn fm_pass(
+    g: &Graph,
+    part: &mut [usize],
+    nparts: usize,
+    cfg: &PassConfig,
+    refine: &mut RefineScratch,
+) -> usize {
+    let mut balance = Balance::new(g, part, nparts, cfg);
+    let mut adjacency = PartAdjacency::new(g, part, nparts, cfg);
+
+    refine.lock_pinned(g.n, cfg);
+    let RefineScratch { boundary, locked } = refine;
+    let mut scratch = GainScratch::new(nparts);
+    let mut heap: BinaryHeap<Move> =
+        initial_moves(g, part, &balance, &adjacency, cfg, boundary, locked)
+            .into_iter()
+            .collect();
 
-            // Check if this is a boundary vertex
-            let is_boundary = ext.iter().any(|&e| e > 0);
-            if !is_boundary {
+    // Applied moves as (vertex, previous part), with the best prefix so far
+    let mut moves: Vec<(usize, usize)> = Vec::new();
+    let mut total_gain = 0i64;
+    let mut best_gain = 0i64;
+    let mut best_len = 0usize;
+
+    while let Some(mv) = heap.pop() {
+        let u = mv.vertex;
+        if locked[u] {
+            continue;
+        }
+
+        // The entry may be stale: re-score and requeue if it changed
+        match scratch.best_move(g, part, &balance, &adjacency, &boundary.degrees, cfg, u) {
+            Some(cur) if cur.gain == mv.gain && cur.to == mv.to => {}
+            Some(cur) => {
+                heap.push(cur);
                 continue;
             }
+            None => continue,
+        }
+        if mv.gain <= 0 && cfg.mode == RefineMode::Greedy {
+            break; // No profitable move found
+        }
+        if moves.len() - best_len >= MAX_UNPRODUCTIVE_MOVES {
+            break;
+        }
+
+        moves.push((u, part[u]));
+        balance.move_vertex(g, u, part[u], mv.to);
+        adjacency.move_vertex(g, part, u, mv.to);
+        boundary.move_vertex(g, part, u, mv.to);
+        locked[u] = true;
+        total_gain += mv.gain;
+        if total_gain > best_gain {
+            best_gain = total_gain;
+            best_len = moves.len();
+        }
+
+        for &v in g.neighbors(u) {
+            if !locked[v] {
+                if let Some(mv) =
+                    scratch.best_move(g, part, &balance, &adjacency, &boundary.degrees, cfg, v)
+                {
+                    heap.push(mv);
+                }
+            }
+        }
+    }
+
+    // Roll back to the best objective value seen during the pass
+    for &(u, from) in moves[best_len..].iter().rev() {
+        balance.move_vertex(g, u, part[u], from);
+        adjacency.move_vertex(g, part, u, from);
+        boundary.move_vertex(g, part, u, from);
+    }
+
+    best_len
+}
+
+/// Single sweep over the boundary vertices, moving each one at once if
+/// its best move pays off. Returns the number of moves made.
+///
+/// With `rng` ([`RefineMode::Random`]) the vertices are visited in an order
+/// shuffled by it and only positive-gain moves are made. Without it
+/// ([`RefineMode::LabelPropagation`]) they are visited in ID order, and a
+/// zero-gain move is also made if it leaves the destination lighter than
+/// the source was, so it can never be undone by the same rule. Best moves
+/// follow the same rules as in [`fm_pass`].
+fn sweep_pass(
+    g: &Graph,
+    part: &mut [usize],
+    nparts: usize,
+    cfg: &PassConfig,
+    boundary: &mut Boundary,
+    rng: Option<&mut Rng>,
+) -> usize {
+    let mut balance = Balance::new(g, part, nparts, cfg);
+    let mut adjacency = PartAdjacency::new(g, part, nparts, cfg);
+    let mut scratch = GainScratch::new(nparts);
+    let mut order = boundary.vertices().to_vec();
+    let label_propagation = rng.is_none();
+    match rng {
+        Some(rng) => rng.shuffle(&mut order),
+        None => order.sort_unstable(),
+    }
+
+    let mut moves = 0usize;
+    for u in order {
+        if !cfg.fixed.is_empty() && cfg.fixed[u].is_some() {
+            continue;
+        }
+        let Some(mv) = scratch.best_move(g, part, &balance, &adjacency, &boundary.degrees, cfg, u)
+        else {
+            continue;
+        };
+        let weight: i64 = (0..g.ncon()).map(|c| g.constraint_weight(u, c)).sum();
+        let evens_out = mv.gain == 0 && balance.load(mv.to) + weight < balance.load(part[u]);
+        if mv.gain < 0 || (mv.gain == 0 && !(label_propagation && evens_out)) {
+            continue;
+        }
+        balance.move_vertex(g, u, part[u], mv.to);
+        adjacency.move_vertex(g, part, u, mv.to);
+        boundary.move_vertex(g, part, u, mv.to);
+        moves += 1;
+    }
+    moves
+}
+
+/// Single pass of [`RefineMode::Colored`]. Returns the number of moves
+/// made.
+///
+/// The unpinned boundary vertices are split into color classes by
+/// [`color_classes`] and the classes processed in turn. Every vertex of a
+/// class is scored against the same partition by [`class_moves`], and the
+/// positive-gain moves of the class then take effect together, without
+/// being re-scored: in decreasing gain order each move reserves its
+/// vertex's weight in the destination part, and a move that no longer fits
+/// or would take its part below the floor is dropped. No two vertices of a
+/// class are adjacent, so the edge-cut gains of the kept moves add up
+/// exactly; volume and connectivity gains are those of the class snapshot
+/// and ignore the two-hop effects of the other moves. Updating the degrees
+/// and boundary for the kept moves is serial. Best moves follow the same
+/// rules as in [`fm_pass`].
+fn colored_pass(
+    g: &Graph,
+    part: &mut [usize],
+    nparts: usize,
+    cfg: &PassConfig,
+    boundary: &mut Boundary,
+) -> usize {
+    let mut balance = Balance::new(g, part, nparts, cfg);
+    let mut adjacency = PartAdjacency::new(g, part, nparts, cfg);
+
+    let mut moves = 0usize;
+    for class in color_classes(g, boundary.vertices(), cfg.fixed) {
+        let mut proposed = class_moves(
+            g,
+            part,
+            &balance,
+            &adjacency,
+            &boundary.degrees,
+            cfg,
+            &class,
+        );
+        proposed.sort_unstable_by(|a, b| b.cmp(a));
+        proposed.retain(|mv| {
+            let (u, from) = (mv.vertex, part[mv.vertex]);
+            let reserved = balance.can_leave(g, u, from) && balance.fits(g, u, mv.to);
+            if reserved {
+                balance.move_vertex(g, u, from, mv.to);
+            }
+            reserved
+        });
+        for mv in &proposed {
+            adjacency.move_vertex(g, part, mv.vertex, mv.to);
+            boundary.move_vertex(g, part, mv.vertex, mv.to);
+        }
+        moves += proposed.len();
+    }
+    moves
+}
+
+/// Greedy coloring of the unpinned `vertices` in ID order, each taking the
+/// lowest color none of its neighbors has. Returns the vertices of each
+/// color, in ID order.
+fn color_classes(g: &Graph, vertices: &[usize], fixed: &[Option<usize>]) -> Vec<Vec<usize>> {
+    let mut order: Vec<usize> = vertices
+        .iter()
+        .copied()
+        .filter(|&u| fixed.is_empty() || fixed[u].is_none())
+        .collect();
+    order.sort_unstable();
+
+    let mut color = vec![usize::MAX; g.n];
+    let mut classes: Vec<Vec<usize>> = Vec::new();
+    // taken[c] marks color c as used by a neighbor of the current vertex
+    let mut taken: Vec<bool> = Vec::new();
+    for u in order {
+        for &v in g.neighbors(u) {
+            if color[v] != usize::MAX {
+                taken[color[v]] = true;
+            }
+        }
+        let c = taken.iter().position(|&t| !t).unwrap_or(classes.len());
+        for &v in g.neighbors(u) {
+            if color[v] != usize::MAX {
+                taken[color[v]] = false;
+            }
+        }
+        if c == classes.len() {
+            classes.push(Vec::new());
+            taken.push(false);
+        }
+        color[u] = c;
+        classes[c].push(u);
+    }
+    classes
+}
+
+/// Positive-gain best moves of the vertices of `class`, in class order.
+///
+/// Scoring only reads the partition, so with the `rayon` feature the
+/// vertices are scored in parallel, as in [`initial_moves`].
+fn class_moves(
+    g: &Graph,
+    part: &[usize],
+    balance: &Balance,
+    adjacency: &PartAdjacency,
+    degrees: &Degrees,
+    cfg: &PassConfig,
+    class: &[usize],
+) -> Vec<Move> {
+    let nparts = balance.nparts();
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        class
+            .par_iter()
+            .map_init(
+                || GainScratch::new(nparts),
+                |scratch, &u| scratch.best_move(g, part, balance, adjacency, degrees, cfg, u),
+            )
+            .flatten()
+            .filter(|mv| mv.gain > 0)
+            .collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        let mut scratch = GainScratch::new(nparts);
+        class
+            .iter()
+            .filter_map(|&u| scratch.best_move(g, part, balance, adjacency, degrees, cfg, u))
+            .filter(|mv| mv.gain > 0)
+            .collect()
+    }
+}
+
+/// Single tabu-search pass ([`RefineMode::Tabu`]). Returns the number of
+/// moves kept, which is 0 if no improvement was made.
+///
+/// Moves are picked best gain first from a lazily re-scored heap, as in
+/// [`fm_pass`], but a moved vertex is only tabu rather than locked: its
+/// entries are dropped until `tenure` further moves have been made, and it
+/// is then re-scored. A tabu move is still allowed if it reaches a better
+/// cut than any seen so far in the pass. After `iterations` moves, or when
+/// no vertex has a feasible move, the moves after the best cut are undone.
+fn tabu_pass(
+    g: &Graph,
+    part: &mut [usize],
+    nparts: usize,
+    cfg: &PassConfig,
+    refine: &mut RefineScratch,
+    tenure: usize,
+    iterations: usize,
+) -> usize {
+    let mut balance = Balance::new(g, part, nparts, cfg);
+    let mut adjacency = PartAdjacency::new(g, part, nparts, cfg);
+    // Only pinned vertices stay locked; the rest are held by `tabu_until`
+    refine.lock_pinned(g.n, cfg);
+    let RefineScratch {
+        boundary,
+        locked: pinned,
+    } = refine;
+    let pinned = &*pinned;
+
+    let mut scratch = GainScratch::new(nparts);
+    let mut heap: BinaryHeap<Move> =
+        initial_moves(g, part, &balance, &adjacency, cfg, boundary, pinned)
+            .into_iter()
+            .collect();
+    // tabu_until[u] is the first iteration at which u may move again;
+    // released holds (iteration, vertex) in increasing iteration order
+    let mut tabu_until = vec![0usize; g.n];
+    let mut released = std::collections::VecDeque::new();
+
+    let mut moves: Vec<(usize, usize)> = Vec::new();
+    let mut total_gain = 0i64;
+    let mut best_gain = 0i64;
+    let mut best_len = 0usize;
+
+    for iter in 0..iterations {
+        while released.front().is_some_and(|&(at, _)| at <= iter) {
+            let (_, u) = released.pop_front().unwrap();
+            if let Some(mv) =
+                scratch.best_move(g, part, &balance, &adjacency, &boundary.degrees, cfg, u)
+            {
+                heap.push(mv);
+            }
+        }
 
-            // Try moving to each neighboring part
-            for to in 0..nparts {
-                if to == from || ext[to] == 0 {
+        let mut chosen = None;
+        while let Some(mv) = heap.pop() {
+            let u = mv.vertex;
+            if pinned[u] {
+                continue;
+            }
+            match scratch.best_move(g, part, &balance, &adjacency, &boundary.degrees, cfg, u) {
+                Some(cur) if cur.gain == mv.gain && cur.to == mv.to => {}
+                Some(cur) => {
+                    heap.push(cur);
                     continue;
                 }
+                None => continue,
+            }
+            if tabu_until[u] > iter && total_gain + mv.gain <= best_gain {
+                continue;
+            }
+            chosen = Some(mv);
+            break;
+        }
+        let Some(mv) = chosen else {
+            break;
+        };
+
+        let u = mv.vertex;
+        moves.push((u, part[u]));
+        balance.move_vertex(g, u, part[u], mv.to);
+        adjacency.move_vertex(g, part, u, mv.to);
+        boundary.move_vertex(g, part, u, mv.to);
+        tabu_until[u] = iter + 1 + tenure;
+        released.push_back((tabu_until[u], u));
+        total_gain += mv.gain;
+        if total_gain > best_gain {
+            best_gain = total_gain;
+            best_len = moves.len();
+        }
+
+        for &v in g.neighbors(u) {
+            if !pinned[v] {
+                if let Some(mv) =
+                    scratch.best_move(g, part, &balance, &adjacency, &boundary.degrees, cfg, v)
+                {
+                    heap.push(mv);
+                }
+            }
+        }
+    }
+
+    for &(u, from) in moves[best_len..].iter().rev() {
+        balance.move_vertex(g, u, part[u], from);
+        adjacency.move_vertex(g, part, u, from);
+        boundary.move_vertex(g, part, u, from);
+    }
+    best_len
+}
+
+/// Best move of every unlocked boundary vertex at the start of a pass.
+///
+/// Scoring only reads the partition, so with the `rayon` feature the
+/// vertices are scored in parallel, each worker with its own
+/// [`GainScratch`]. Every [`Move`] is distinct and totally ordered, so the
+/// heap built from the result pops moves in the same order either way.
+fn initial_moves(
+    g: &Graph,
+    part: &[usize],
+    balance: &Balance,
+    adjacency: &PartAdjacency,
+    cfg: &PassConfig,
+    boundary: &Boundary,
+    locked: &[bool],
+) -> Vec<Move> {
+    let nparts = balance.nparts();
+    let degrees = &boundary.degrees;
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        boundary
+            .vertices()
+            .par_iter()
+            .copied()
+            .filter(|&u| !locked[u])
+            .map_init(
+                || GainScratch::new(nparts),
+                |scratch, u| scratch.best_move(g, part, balance, adjacency, degrees, cfg, u),
+            )
+            .flatten()
+            .collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        let mut scratch = GainScratch::new(nparts);
+        boundary
+            .vertices()
+            .iter()
+            .copied()
+            .filter(|&u| !locked[u])
+            .filter_map(|u| scratch.best_move(g, part, balance, adjacency, degrees, cfg, u))
+            .collect()
+    }
+}
+
+/// Vertices with nonzero edge weight to another part, and the edge cut,
+/// kept up to date as vertices move so that passes never scan the interior.
+#[derive(Debug, Default)]
+struct Boundary {
+    /// The boundary vertices, in no particular order.
+    list: Vec<usize>,
+    /// `pos[u]` is the index of `u` in `list`, or `usize::MAX` if `u` is
+    /// interior.
+    pos: Vec<usize>,
+    /// Edge cut of the partition, wide enough never to overflow.
+    cut: i128,
+    degrees: Degrees,
+}
+
+impl Boundary {
+    /// Recompute everything from scratch for `part`, keeping the memory of
+    /// earlier refinements.
+    fn reset(&mut self, g: &Graph, part: &[usize], nparts: usize) {
+        self.list.clear();
+        self.pos.clear();
+        self.pos.resize(g.n, usize::MAX);
+        self.degrees.reset(g, part, nparts);
+        let mut twice_cut = 0i128;
+        for u in 0..g.n {
+            self.update(u);
+            twice_cut += self
+                .degrees
+                .ext(u)
+                .iter()
+                .map(|e| e.1 as i128)
+                .sum::<i128>();
+        }
+        self.cut = twice_cut / 2;
+    }
+
+    fn vertices(&self) -> &[usize] {
+        &self.list
+    }
+
+    /// Edge cut of the partition, saturating at `i64::MAX` as
+    /// [`Graph::edge_cut`] does.
+    fn cut(&self) -> i64 {
+        i64::try_from(self.cut).unwrap_or(i64::MAX)
+    }
+
+    /// Move `u` to part `to`, updating the cut by the weight of its edges
+    /// to its old part minus those to its new one.
+    fn move_vertex(&mut self, g: &Graph, part: &mut [usize], u: usize, to: usize) {
+        let from = part[u];
+        if from == to {
+            return;
+        }
+        for (k, &v) in g.neighbors(u).iter().enumerate() {
+            if v == u {
+                continue;
+            }
+            if part[v] == from {
+                self.cut += g.edge_weight(u, k) as i128;
+            } else if part[v] == to {
+                self.cut -= g.edge_weight(u, k) as i128;
+            }
+        }
+        self.degrees.move_vertex(g, part, u, to);
+        part[u] = to;
+        // Only `u` and its neighbors can enter or leave the boundary
+        self.update(u);
+        for &v in g.neighbors(u) {
+            self.update(v);
+        }
+    }
+
+    /// Recompute whether `u` is on the boundary.
+    fn update(&mut self, u: usize) {
+        let on = !self.degrees.ext(u).is_empty();
+        let listed = self.pos[u] != usize::MAX;
+        if on && !listed {
+            self.pos[u] = self.list.len();
+            self.list.push(u);
+        } else if !on && listed {
+            let i = self.pos[u];
+            self.list.swap_remove(i);
+            if let Some(&moved) = self.list.get(i) {
+                self.pos[moved] = i;
+            }
+            self.pos[u] = usize::MAX;
+        }
+    }
+}
+
+/// Internal and external degree of every vertex, updated as its neighbors
+/// move so that scoring a move never scans the vertex's edges, as METIS
+/// does with its `ckrinfo` arrays.
+#[derive(Debug, Default)]
+struct Degrees {
+    /// Edge weight from each vertex to its own part, self-loops included.
+    int: Vec<i64>,
+    /// `ext[start[u]..start[u] + len[u]]` holds `(part, weight)` for every
+    /// other part `u` has nonzero edge weight to, in no particular order.
+    ext: Vec<(usize, i64)>,
+    /// Start of each vertex's slots in `ext`, or `usize::MAX` until it
+    /// first touches another part. Slots are handed out from the end of
+    /// `ext`, one per neighbor but no more than the number of other parts,
+    /// and kept for the rest of the refinement.
+    start: Vec<usize>,
+    len: Vec<usize>,
+    nparts: usize,
+}
+
+impl Degrees {
+    /// Recompute the degrees of every vertex under `part`, keeping the
+    /// memory of earlier refinements.
+    fn reset(&mut self, g: &Graph, part: &[usize], nparts: usize) {
+        self.int.clear();
+        self.int.resize(g.n, 0);
+        self.ext.clear();
+        self.start.clear();
+        self.start.resize(g.n, usize::MAX);
+        self.len.clear();
+        self.len.resize(g.n, 0);
+        self.nparts = nparts;
+        for u in 0..g.n {
+            let neighbors = g.neighbors(u);
+            let (int, internal) = weight_to_part(neighbors, g.edge_weights(u), part, part[u]);
+            self.int[u] = int;
+            // Interior vertices, the vast majority, need no second scan
+            if internal == neighbors.len() {
+                continue;
+            }
+            for (k, &v) in neighbors.iter().enumerate() {
+                if part[v] != part[u] {
+                    self.add(g, u, part[v], g.edge_weight(u, k));
+                }
+            }
+        }
+    }
 
-                let vw = g.vertex_weight(u);
+    /// External degrees of `u` as `(part, weight)` pairs.
+    fn ext(&self, u: usize) -> &[(usize, i64)] {
+        match self.start[u] {
+            usize::MAX => &[],
+            start => &self.ext[start..start + self.len[u]],
+        }
+    }
+
+    /// Add `w` to the edge weight from `u` to part `p`, dropping the entry
+    /// once it reaches zero.
+    fn add(&mut self, g: &Graph, u: usize, p: usize, w: i64) {
+        if self.start[u] == usize::MAX {
+            if w == 0 {
+                return;
+            }
+            self.start[u] = self.ext.len();
+            let slots = g.degree(u).min(self.nparts - 1);
+            self.ext.resize(self.ext.len() + slots, (0, 0));
+        }
+        let start = self.start[u];
+        let slots = &mut self.ext[start..start + self.len[u]];
+        match slots.iter().position(|&(q, _)| q == p) {
+            Some(i) => {
+                slots[i].1 += w;
+                if slots[i].1 == 0 {
+                    slots[i] = slots[slots.len() - 1];
+                    self.len[u] -= 1;
+                }
+            }
+            None if w != 0 => {
+                self.ext[start + self.len[u]] = (p, w);
+                self.len[u] += 1;
+            }
+            None => {}
+        }
+    }
 
-                // Balance check: would `to` exceed max?
-                if part_weight[to] + vw > max_part_weight {
+    /// Account for moving `u` from its part in `part` to part `to`.
+    fn move_vertex(&mut self, g: &Graph, part: &[usize], u: usize, to: usize) {
+        let from = part[u];
+        let mut self_loops = 0;
+        for (k, &v) in g.neighbors(u).iter().enumerate() {
+            let w = g.edge_weight(u, k);
+            if v == u {
+                self_loops += w;
+                continue;
+            }
+            if part[v] == from {
+                self.int[v] -= w;
+            } else {
+                self.add(g, v, from, -w);
+            }
+            if part[v] == to {
+                self.int[v] += w;
+            } else {
+                self.add(g, v, to, w);
+            }
+        }
+        let to_weight = self
+            .ext(u)
+            .iter()
+            .find(|&&(p, _)| p == to)
+            .map_or(0, |e| e.1);
+        self.add(g, u, to, -to_weight);
+        self.add(g, u, from, self.int[u] - self_loops);
+        self.int[u] = to_weight + self_loops;
+    }
+}
+
+/// Per-part, per-constraint weights and upper bounds tracked during a pass.
+struct Balance {
+    ncon: usize,
+    /// `weight[p * ncon + c]` is the weight of part `p` on constraint `c`.
+    weight: Vec<i64>,
+    /// Upper bound for each entry of `weight`.
+    max: Vec<i64>,
+    /// Lower bound for every entry of `weight`.
+    min: i64,
+    /// Number of vertices in each part.
+    count: Vec<usize>,
+}
+
+impl Balance {
+    /// Current part weights of `part` and their bounds: at most
+    /// `cfg.imbalance` (or the constraint's `cfg.ubvec` entry) times the
+    /// target under `cfg.tpwgts` (empty for uniform targets), and at least
+    /// `cfg.min_weight`.
+    fn new(g: &Graph, part: &[usize], nparts: usize, cfg: &PassConfig) -> Self {
+        let tpwgts = cfg.tpwgts;
+        let ncon = g.ncon();
+        let mut weight = vec![0i64; nparts * ncon];
+        let mut count = vec![0usize; nparts];
+        for u in 0..g.n {
+            count[part[u]] += 1;
+            for c in 0..ncon {
+                weight[part[u] * ncon + c] += g.constraint_weight(u, c);
+            }
+        }
+
+        let mut max = vec![0i64; nparts * ncon];
+        for c in 0..ncon {
+            let total: i64 = (0..nparts).map(|p| weight[p * ncon + c]).sum();
+            let imbalance = cfg.ubvec.get(c).copied().unwrap_or(cfg.imbalance);
+            for p in 0..nparts {
+                max[p * ncon + c] = if tpwgts.is_empty() {
+                    (total as f64 * imbalance / nparts as f64).ceil() as i64
+                } else {
+                    let tsum: f64 = tpwgts.iter().sum();
+                    (total as f64 * imbalance * tpwgts[p] / tsum).ceil() as i64
+                };
+            }
+        }
+
+        Self {
+            ncon,
+            weight,
+            max,
+            min: cfg.min_weight,
+            count,
+        }
+    }
+
+    /// Whether part `from` can give up `u` without dropping below the floor.
+    fn can_leave(&self, g: &Graph, u: usize, from: usize) -> bool {
+        self.count[from] > 1
+            && (0..self.ncon)
+                .all(|c| self.weight[from * self.ncon + c] - g.constraint_weight(u, c) >= self.min)
+    }
+
+    /// Whether part `p` is above its bound on some constraint.
+    fn overweight(&self, p: usize) -> bool {
+        (0..self.ncon).any(|c| self.weight[p * self.ncon + c] > self.max[p * self.ncon + c])
+    }
+
+    /// Whether part `to` can take `u` without exceeding any bound.
+    fn fits(&self, g: &Graph, u: usize, to: usize) -> bool {
+        (0..self.ncon).all(|c| {
+            let i = to * self.ncon + c;
+            self.weight[i] + g.constraint_weight(u, c) <= self.max[i]
+        })
+    }
+
+    /// Total weight above the bounds, over all parts and constraints.
+    fn excess(&self) -> i64 {
+        self.weight
+            .iter()
+            .zip(&self.max)
+            .map(|(&w, &m)| (w - m).max(0))
+            .sum()
+    }
+
+    /// Number of parts.
+    fn nparts(&self) -> usize {
+        self.count.len()
+    }
+
+    /// Weight of part `p` summed over all constraints.
+    fn load(&self, p: usize) -> i64 {
+        self.weight[p * self.ncon..(p + 1) * self.ncon].iter().sum()
+    }
+
+    /// Account for moving `u` from part `from` to part `to`.
+    fn move_vertex(&mut self, g: &Graph, u: usize, from: usize, to: usize) {
+        self.count[from] -= 1;
+        self.count[to] += 1;
+        for c in 0..self.ncon {
+            let w = g.constraint_weight(u, c);
+            self.weight[from * self.ncon + c] -= w;
+            self.weight[to * self.ncon + c] += w;
+        }
+    }
+}
+
+/// Edge weight between every pair of parts, tracked during a pass when
+/// `cfg.minconn` is set.
+struct PartAdjacency {
+    nparts: usize,
+    /// `weight[p * nparts + q]` is the total weight of edges between parts
+    /// `p != q`; empty unless `cfg.minconn` is set.
+    weight: Vec<i64>,
+    /// Gain charged for each pair of parts a move makes adjacent: the
+    /// average weighted degree, rounded up and at least 1.
+    penalty: i64,
+}
+
+impl PartAdjacency {
+    fn new(g: &Graph, part: &[usize], nparts: usize, cfg: &PassConfig) -> Self {
+        if !cfg.minconn {
+            return Self {
+                nparts,
+                weight: Vec::new(),
+                penalty: 0,
+            };
+        }
+        let mut weight = vec![0i64; nparts * nparts];
+        let mut total = 0i64;
+        for u in 0..g.n {
+            for (v, w) in g.edges(u) {
+                let pv = part[v];
+                total = total.saturating_add(w);
+                if pv != part[u] {
+                    weight[part[u] * nparts + pv] += w;
+                }
+            }
+        }
+        let penalty = (total as f64 / g.n.max(1) as f64).ceil().max(1.0) as i64;
+        Self {
+            nparts,
+            weight,
+            penalty,
+        }
+    }
+
+    /// Number of part pairs that become adjacent minus the number that stop
+    /// being adjacent when `u` moves from `from` to `to`, given `u`'s edge
+    /// weight `int` to `from` and `ext[p]` to each part `p` in `touched`.
+    fn delta(&self, from: usize, to: usize, int: i64, ext: &[i64], touched: &[usize]) -> i64 {
+        let n = self.nparts;
+        let change = |old: i64, new: i64| i64::from(new > 0) - i64::from(old > 0);
+        let mut delta = 0;
+        for &p in touched {
+            // Pair (from, p) loses u's edges to p and, if p is the
+            // destination, gains u's edges to `from`.
+            let old = self.weight[from * n + p];
+            let new = old - ext[p] + if p == to { int } else { 0 };
+            delta += change(old, new);
+            if p != to {
+                let old = self.weight[to * n + p];
+                delta += change(old, old + ext[p]);
+            }
+        }
+        delta
+    }
+
+    /// Account for moving `u` from its part in `part` to part `to`.
+    fn move_vertex(&mut self, g: &Graph, part: &[usize], u: usize, to: usize) {
+        if self.weight.is_empty() {
+            return;
+        }
+        let (n, from) = (self.nparts, part[u]);
+        for (v, w) in g.edges(u) {
+            let pv = part[v];
+            if pv != from {
+                self.weight[from * n + pv] -= w;
+                self.weight[pv * n + from] -= w;
+            }
+            if pv != to {
+                self.weight[to * n + pv] += w;
+                self.weight[pv * n + to] += w;
+            }
+        }
+    }
+}
+
+/// A candidate move of `vertex` to part `to`.
+///
+/// Ordered by gain, then by the lighter destination weight at scoring time,
+/// then by the lower vertex ID.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Move {
+    gain: i64,
+    lighter: Reverse<i64>,
+    lower_id: Reverse<usize>,
+    vertex: usize,
+    to: usize,
+}
+
+/// Reusable per-part accumulator for scoring vertex moves.
+struct GainScratch {
+    /// Edge weight from the current vertex to each part; zero outside `touched`.
+    ext: Vec<i64>,
+    /// Parts with a nonzero entry in `ext`.
+    touched: Vec<usize>,
+    /// Volume gain of moving the current vertex to each part in `touched`.
+    vol_gain: Vec<i64>,
+    /// `seen[p] == stamp` marks part `p` as adjacent to the vertex being
+    /// examined in [`GainScratch::volume_gains`].
+    seen: Vec<usize>,
+    stamp: usize,
+}
+
+impl GainScratch {
+    fn new(nparts: usize) -> Self {
+        Self {
+            ext: vec![0; nparts],
+            touched: Vec::new(),
+            vol_gain: vec![0; nparts],
+            seen: vec![0; nparts],
+            stamp: 0,
+        }
+    }
+
+    /// Best balance-feasible move of boundary vertex `u`, if any.
+    ///
+    /// For [`Objective::EdgeCut`] the gain is the edge weight to the
+    /// destination minus the edge weight internal to `u`'s current part; for
+    /// [`Objective::CommVolume`] it is computed by
+    /// [`GainScratch::volume_gains`]. With `cfg.minconn` set, it is reduced
+    /// by the connectivity penalty of [`PartAdjacency`]. With `cfg.origin`
+    /// set, the gain is scaled by [`MIGRATION_SCALE`] and reduced by
+    /// [`migration_cost`].
+    #[allow(clippy::too_many_arguments)]
+    fn best_move(
+        &mut self,
+        g: &Graph,
+        part: &[usize],
+        balance: &Balance,
+        adjacency: &PartAdjacency,
+        degrees: &Degrees,
+        cfg: &PassConfig,
+        u: usize,
+    ) -> Option<Move> {
+        let frozen = cfg.frozen;
+        let from = part[u];
+        if !frozen.is_empty() && frozen[from] {
+            return None;
+        }
+        // Never empty a part or take it below the floor
+        if !balance.can_leave(g, u, from) {
+            return None;
+        }
+
+        let int = degrees.int[u];
+        for &(p, w) in degrees.ext(u) {
+            self.ext[p] = w;
+            self.touched.push(p);
+        }
+
+        if cfg.objective == Objective::CommVolume {
+            self.volume_gains(g, part, u);
+        }
+
+        let mut best: Option<Move> = None;
+        for &to in &self.touched {
+            if self.ext[to] == 0 || (!frozen.is_empty() && frozen[to]) {
+                continue;
+            }
+            // Balance check: would `to` exceed max?
+            if !balance.fits(g, u, to) {
+                continue;
+            }
+            let gain = match cfg.objective {
+                Objective::EdgeCut => self.ext[to] - int,
+                Objective::CommVolume => self.vol_gain[to],
+            };
+            let gain = if cfg.minconn {
+                gain - adjacency.penalty * adjacency.delta(from, to, int, &self.ext, &self.touched)
+            } else {
+                gain
+            };
+            let gain = if cfg.origin.is_empty() {
+                gain
+            } else {
+                gain * MIGRATION_SCALE - migration_cost(g, cfg, u, from, to)
+            };
+            let load = balance.load(to);
+            let better = match best {
+                None => true,
+                Some(b) => (gain, Reverse(load), Reverse(to)) > (b.gain, b.lighter, Reverse(b.to)),
+            };
+            if better {
+                best = Some(Move {
+                    gain,
+                    lighter: Reverse(load),
+                    lower_id: Reverse(u),
+                    vertex: u,
+                    to,
+                });
+            }
+        }
+
+        for &p in &self.touched {
+            self.ext[p] = 0;
+        }
+        self.touched.clear();
+        best
+    }
+
+    /// Fill `vol_gain[to]` for every `to` in `touched` with the decrease in
+    /// communication volume from moving `u` out of its part `a` into `to`.
+    ///
+    /// `u` itself stops seeing `to` but starts seeing `a` if it keeps a
+    /// neighbor there. A neighbor `v` in part `pv` loses `a` from its view
+    /// unless `a == pv` or another neighbor of `v` is in `a`, and gains `to`
+    /// unless `to == pv` or it already sees `to`. Each change is weighted by
+    /// the size of the vertex whose view changes.
+    fn volume_gains(&mut self, g: &Graph, part: &[usize], u: usize) {
+        let a = part[u];
+        let keeps_a = g.neighbors(u).iter().any(|&v| part[v] == a);
+        let own = g.vertex_size(u) * (1 - i64::from(keeps_a));
+        for &to in &self.touched {
+            self.vol_gain[to] = own;
+        }
+
+        for &v in g.neighbors(u) {
+            let pv = part[v];
+            self.stamp += 1;
+            for &x in g.neighbors(v) {
+                if x != u {
+                    self.seen[part[x]] = self.stamp;
+                }
+            }
+            let wv = g.vertex_size(v);
+            let loses_a = a != pv && self.seen[a] != self.stamp;
+            for &to in &self.touched {
+                let gains_to = to != pv && self.seen[to] != self.stamp;
+                self.vol_gain[to] += wv * (i64::from(loses_a) - i64::from(gains_to));
+            }
+        }
+    }
+}
+
+/// Change in migration penalty, scaled by [`MIGRATION_SCALE`], when `u`
+/// moves from `from` to `to`.
+///
+/// Leaving the original part `cfg.origin[u]` costs `migration_weight` times
+/// the vertex weight, and returning to it refunds the same amount.
+fn migration_cost(g: &Graph, cfg: &PassConfig, u: usize, from: usize, to: usize) -> i64 {
+    let penalty =
+        (cfg.migration_weight * g.vertex_weight(u) as f64 * MIGRATION_SCALE as f64).round() as i64;
+    let home = cfg.origin[u];
+    penalty * (i64::from(to != home) - i64::from(from != home))
+}
+
+/// Refine a bisection (parts `0` and `1`) with classic 2-way FM passes.
+///
+/// A specialization of [`fm_refine`] for two parts. Each vertex has a
+/// single destination, so its gain is one number, the edge weight to the
+/// other side minus the edge weight to its own, and is updated in place as
+/// its neighbors move. Each pass keeps a max-heap of the boundary vertices
+/// of each side and repeatedly moves the best one, negative gains included,
+/// then rolls back to the lowest cut seen, as [`RefineMode::Rollback`]
+/// does. Both sides are held to 5% above half the total weight. A move that
+/// lightens the heavier side is always allowed, and prefixes closer to that
+/// bound are kept over ones with a lower cut, so an unbalanced bisection is
+/// evened out. Passes stop once one keeps no move.
+///
+/// Panics if an entry of `part` is not `0` or `1`.
+pub fn fm_refine_2way(g: &Graph, part: &mut [usize], max_passes: usize) {
+    assert_eq!(part.len(), g.n, "part must have one entry per vertex");
+    assert!(part.iter().all(|&p| p < 2), "part IDs must be 0 or 1");
+    let mut weight = [0i64; 2];
+    for u in 0..g.n {
+        weight[part[u]] += g.vertex_weight(u);
+    }
+    let bound = ((weight[0] + weight[1]) as f64 * MAX_IMBALANCE / 2.0).ceil() as i64;
+    for _ in 0..max_passes {
+        if fm_pass_2way(g, part, &mut weight, bound) == 0 {
+            break;
+        }
+    }
+}
+
+/// Single pass of [`fm_refine_2way`]. Returns the number of moves kept.
+///
+/// `weight` holds the weight of each side and is kept up to date. Heap
+/// entries are `(gain, lower vertex ID first)`; an entry is stale
+/// once its vertex moved or its gain changed, and is then skipped, since
+/// every gain change pushes a fresh entry. Ties between the two sides go
+/// to the move into the lighter side.
+fn fm_pass_2way(g: &Graph, part: &mut [usize], weight: &mut [i64; 2], bound: i64) -> usize {
+    // gain[u] is the edge weight from u to the other side minus that to
+    // its own side, and ext[u] the former; self-loops count for neither
+    let mut gain = vec![0i64; g.n];
+    let mut ext = vec![0i64; g.n];
+    for u in 0..g.n {
+        for (v, w) in g.edges(u) {
+            if v == u {
+                continue;
+            }
+            if part[v] == part[u] {
+                gain[u] -= w;
+            } else {
+                gain[u] += w;
+                ext[u] += w;
+            }
+        }
+    }
+    let mut heaps: [BinaryHeap<(i64, Reverse<usize>)>; 2] = Default::default();
+    for u in (0..g.n).filter(|&u| ext[u] > 0) {
+        heaps[part[u]].push((gain[u], Reverse(u)));
+    }
+
+    let mut locked = vec![false; g.n];
+    let mut moves: Vec<usize> = Vec::new();
+    // Cut change since the start of the pass, and the best prefix so far:
+    // least weight over the bound, then lowest cut, then lightest heavier
+    // side
+    let rank = |delta: i64, weight: &[i64; 2]| {
+        let heavier = weight[0].max(weight[1]);
+        ((heavier - bound).max(0), delta, heavier)
+    };
+    let mut delta = 0i64;
+    let mut best = rank(0, weight);
+    let mut best_len = 0usize;
+
+    loop {
+        // Best current entry of each side whose move balance allows
+        let mut candidates = [None, None];
+        for (from, heap) in heaps.iter_mut().enumerate() {
+            while let Some(&(g_u, Reverse(u))) = heap.peek() {
+                if locked[u] || part[u] != from || gain[u] != g_u {
+                    heap.pop();
                     continue;
                 }
+                let w = g.vertex_weight(u);
+                let to = 1 - from;
+                let heavier = weight[0].max(weight[1]);
+                let after = (weight[from] - w).max(weight[to] + w);
+                if weight[to] + w <= bound || after < heavier {
+                    candidates[from] = Some((g_u, Reverse(weight[to]), Reverse(u)));
+                }
+                break;
+            }
+        }
+        let Some((g_u, _, Reverse(u))) = candidates.into_iter().flatten().max() else {
+            break;
+        };
+        if moves.len() - best_len >= MAX_UNPRODUCTIVE_MOVES {
+            break;
+        }
+
+        let from = part[u];
+        let to = 1 - from;
+        heaps[from].pop();
+        part[u] = to;
+        locked[u] = true;
+        weight[from] -= g.vertex_weight(u);
+        weight[to] += g.vertex_weight(u);
+        delta -= g_u;
+        moves.push(u);
+        gain[u] = -gain[u];
+        for (v, w) in g.edges(u) {
+            if v == u || locked[v] {
+                continue;
+            }
+            if part[v] == from {
+                gain[v] += 2 * w;
+                ext[v] += w;
+            } else {
+                gain[v] -= 2 * w;
+                ext[v] -= w;
+            }
+            if ext[v] > 0 {
+                heaps[part[v]].push((gain[v], Reverse(v)));
+            }
+        }
+
+        let state = rank(delta, weight);
+        if state < best {
+            best = state;
+            best_len = moves.len();
+        }
+    }
+
+    for &u in moves[best_len..].iter().rev() {
+        let from = part[u];
+        part[u] = 1 - from;
+        weight[from] -= g.vertex_weight(u);
+        weight[1 - from] += g.vertex_weight(u);
+    }
+    best_len
+}
+
+/// Make every part connected by moving stray fragments to neighboring parts.
+///
+/// A part split into several connected fragments keeps its heaviest one
+/// (the first by vertex order on ties). Each other fragment is moved whole
+/// to the adjacent part it shares the most edge weight with (the lowest part
+/// ID on ties), which is the move adding the least cut. The lightest stray
+/// fragment is moved first, and the fragments are recomputed after every move
+/// until none is left.
+///
+/// A moved fragment never splits its old part, since it had no edge to the
+/// rest of it, and it always joins a fragment of its new part, so the loop
+/// terminates. Fragments with no neighbor outside their part (a connected
+/// component of `g` on its own) cannot be moved and are left in place, as
+/// is balance: moves ignore the imbalance tolerance.
+pub fn enforce_contiguity(g: &Graph, part: &mut [usize], nparts: usize) {
+    // conn[q] is the edge weight from the current fragment to part q, or
+    // None if they are not adjacent
+    let mut conn: Vec<Option<i64>> = vec![None; nparts];
+    loop {
+        let (fragment, count) = part_fragments(g, part);
+        let mut weight = vec![0i64; count];
+        let mut main = vec![usize::MAX; nparts];
+        // members[start[f]..start[f + 1]] are the vertices of fragment f
+        let mut start = vec![0usize; count + 1];
+        for u in 0..g.n {
+            weight[fragment[u]] += g.vertex_weight(u);
+            start[fragment[u] + 1] += 1;
+        }
+        for f in 0..count {
+            start[f + 1] += start[f];
+        }
+        let mut members = vec![0usize; g.n];
+        let mut next = start.clone();
+        for u in 0..g.n {
+            let (p, f) = (part[u], fragment[u]);
+            if main[p] == usize::MAX || weight[f] > weight[main[p]] {
+                main[p] = f;
+            }
+            members[next[f]] = u;
+            next[f] += 1;
+        }
+
+        // Lightest stray fragment that touches another part, with the part
+        // it is most strongly connected to
+        let mut best: Option<(i64, usize, usize)> = None;
+        for f in 0..count {
+            let home = part[members[start[f]]];
+            if main[home] == f || best.is_some_and(|(bw, _, _)| weight[f] >= bw) {
+                continue;
+            }
+            conn.fill(None);
+            for &u in &members[start[f]..start[f + 1]] {
+                for (v, w) in g.edges(u) {
+                    let q = part[v];
+                    if q != home {
+                        *conn[q].get_or_insert(0) += w;
+                    }
+                }
+            }
+            let to = (0..nparts)
+                .filter(|&q| conn[q].is_some())
+                .min_by_key(|&q| Reverse(conn[q]));
+            if let Some(to) = to {
+                best = Some((weight[f], f, to));
+            }
+        }
 
-                // Gain = external edges to `to` - internal edges in `from`
-                let gain = ext[to] - int;
+        let Some((_, f, to)) = best else {
+            break;
+        };
+        for &u in &members[start[f]..start[f + 1]] {
+            part[u] = to;
+        }
+    }
+}
+
+/// Improve a k-way partition with minimum s-t cuts between adjacent parts,
+/// keeping every part within `imbalance` times the average part weight.
+///
+/// For each pair of adjacent parts `a` and `b`, a corridor is grown
+/// breadth first from their shared boundary into each side, taking only as
+/// much of `a` as `b` could absorb within its bound, and vice versa. The
+/// rest of `a` becomes the source and the rest of `b` the sink, and a
+/// minimum cut of the corridor replaces the current split whenever it cuts
+/// less edge weight between `a` and `b`. Every split of the corridor keeps
+/// both parts within bounds, so the flow may reach a better cut that FM
+/// cannot, one move at a time. Pairs are revisited until a round improves
+/// nothing.
+///
+/// The cut never increases: edges from `a` or `b` to a third part are cut
+/// whichever of the two their endpoint ends up in.
+pub fn flow_refine(g: &Graph, part: &mut [usize], nparts: usize, imbalance: f64) {
+    assert!(imbalance >= 1.0, "imbalance must be at least 1.0");
+    let cfg = PassConfig {
+        imbalance,
+        ..PassConfig::default()
+    };
+    flow_passes(g, part, nparts, &cfg);
+}
 
-                if gain > best_gain {
-                    best_gain = gain;
-                    best_u = Some(u);
-                    best_to = to;
+/// [`flow_refine`] under `cfg`: bounds come from `cfg` as in FM passes,
+/// pinned vertices stay out of the corridors and frozen parts are skipped.
+pub(crate) fn flow_passes(g: &Graph, part: &mut [usize], nparts: usize, cfg: &PassConfig) {
+    if g.n == 0 || nparts <= 1 {
+        return;
+    }
+    let frozen = |p: usize| !cfg.frozen.is_empty() && cfg.frozen[p];
+    // local[u] is u's node in the current corridor network, if any
+    let mut local = vec![usize::MAX; g.n];
+    loop {
+        let mut adjacent = vec![false; nparts * nparts];
+        for u in 0..g.n {
+            for &v in g.neighbors(u) {
+                if part[u] < part[v] {
+                    adjacent[part[u] * nparts + part[v]] = true;
+                }
+            }
+        }
+        let mut improved = false;
+        for a in 0..nparts {
+            for b in a + 1..nparts {
+                if adjacent[a * nparts + b] && !frozen(a) && !frozen(b) {
+                    improved |= flow_pair(g, part, nparts, cfg, (a, b), &mut local);
                 }
             }
         }
+        if !improved {
+            break;
+        }
+    }
+}
+
+/// One flow step between parts `a` and `b`; returns whether it lowered
+/// the cut. `local` must be all `usize::MAX` and is left that way.
+fn flow_pair(
+    g: &Graph,
+    part: &mut [usize],
+    nparts: usize,
+    cfg: &PassConfig,
+    (a, b): (usize, usize),
+    local: &mut [usize],
+) -> bool {
+    let balance = Balance::new(g, part, nparts, cfg);
+    let mut region = corridor(g, part, cfg, &balance, a, b, local);
+    region.extend(corridor(g, part, cfg, &balance, b, a, local));
+    if region.is_empty() {
+        return false;
+    }
+    for (i, &u) in region.iter().enumerate() {
+        local[u] = i;
+    }
 
-        match best_u {
-            Some(u) if best_gain > 0 => {
-                let from = part[u];
-                let vw = g.vertex_weight(u);
-                part_weight[from] -= vw;
-                part_weight[best_to] += vw;
-                part[u] = best_to;
-                locked[u] = true;
-                improved = true;
+    let (s, t) = (region.len(), region.len() + 1);
+    let mut net = FlowNetwork::new(region.len() + 2);
+    for (i, &u) in region.iter().enumerate() {
+        for (v, w) in g.edges(u) {
+            if local[v] != usize::MAX {
+                if i < local[v] {
+                    net.add_edge(i, local[v], w);
+                }
+            } else if part[v] == a {
+                net.add_edge(s, i, w);
+            } else if part[v] == b {
+                net.add_edge(i, t, w);
+            }
+        }
+    }
+    // Current cut between the two parts, and the part of it that no split
+    // of the corridor can avoid
+    let (mut current, mut fixed_cut) = (0i64, 0i64);
+    for u in (0..g.n).filter(|&u| part[u] == a) {
+        for (v, w) in g.edges(u) {
+            if part[v] == b {
+                current += w;
+                if local[u] == usize::MAX && local[v] == usize::MAX {
+                    fixed_cut += w;
+                }
             }
-            _ => break, // No profitable move found
         }
     }
 
+    let flow = net.max_flow(s, t);
+    let improved = flow + fixed_cut < current;
+    if improved {
+        let source_side = net.reachable(s);
+        for (i, &u) in region.iter().enumerate() {
+            part[u] = if source_side[i] { a } else { b };
+        }
+    }
+    for &u in &region {
+        local[u] = usize::MAX;
+    }
     improved
 }
+
+/// Vertices of part `from` that could all move to part `to` within bounds,
+/// grown breadth first from the vertices of `from` adjacent to `to`.
+///
+/// Pinned vertices are never taken, and `from` keeps at least one vertex
+/// and its floor. `local` marks visited vertices while growing and is
+/// restored to `usize::MAX` on return.
+fn corridor(
+    g: &Graph,
+    part: &[usize],
+    cfg: &PassConfig,
+    balance: &Balance,
+    from: usize,
+    to: usize,
+    local: &mut [usize],
+) -> Vec<usize> {
+    let ncon = balance.ncon;
+    let mut budget: Vec<i64> = (0..ncon)
+        .map(|c| {
+            let room = balance.max[to * ncon + c] - balance.weight[to * ncon + c];
+            let spare = balance.weight[from * ncon + c] - balance.min;
+            room.min(spare)
+        })
+        .collect();
+    let pinned = |u: usize| !cfg.fixed.is_empty() && cfg.fixed[u].is_some();
+
+    let mut queue = std::collections::VecDeque::new();
+    let mut visited = Vec::new();
+    for u in 0..g.n {
+        if part[u] == from && !pinned(u) && g.neighbors(u).iter().any(|&v| part[v] == to) {
+            local[u] = 0;
+            visited.push(u);
+            queue.push_back(u);
+        }
+    }
+    let mut taken = Vec::new();
+    while let Some(u) = queue.pop_front() {
+        if taken.len() + 1 >= balance.count[from]
+            || (0..ncon).any(|c| g.constraint_weight(u, c) > budget[c])
+        {
+            continue;
+        }
+        for (c, left) in budget.iter_mut().enumerate() {
+            *left -= g.constraint_weight(u, c);
+        }
+        taken.push(u);
+        for &v in g.neighbors(u) {
+            if part[v] == from && local[v] == usize::MAX && !pinned(v) {
+                local[v] = 0;
+                visited.push(v);
+                queue.push_back(v);
+            }
+        }
+    }
+    for u in visited {
+        local[u] = usize::MAX;
+    }
+    taken
+}
+
+/// Residual network for [`flow_refine`], solved with Dinic's algorithm.
+///
+/// Arcs are stored in pairs: arc `e ^ 1` is the reverse of arc `e`.
+struct FlowNetwork {
+    /// First arc out of each node, or `usize::MAX`.
+    head: Vec<usize>,
+    /// Next arc out of the same node, or `usize::MAX`.
+    next: Vec<usize>,
+    to: Vec<usize>,
+    cap: Vec<i64>,
+}
+
+impl FlowNetwork {
+    fn new(n: usize) -> Self {
+        Self {
+            head: vec![usize::MAX; n],
+            next: Vec::new(),
+            to: Vec::new(),
+            cap: Vec::new(),
+        }
+    }
+
+    /// Add an undirected edge of capacity `w` between `u` and `v`.
+    fn add_edge(&mut self, u: usize, v: usize, w: i64) {
+        for (x, y) in [(u, v), (v, u)] {
+            self.to.push(y);
+            self.cap.push(w);
+            self.next.push(self.head[x]);
+            self.head[x] = self.to.len() - 1;
+        }
+    }
+
+    /// BFS distance from `s` over arcs with residual capacity.
+    fn levels(&self, s: usize) -> Vec<usize> {
+        let mut level = vec![usize::MAX; self.head.len()];
+        let mut queue = std::collections::VecDeque::from([s]);
+        level[s] = 0;
+        while let Some(u) = queue.pop_front() {
+            let mut e = self.head[u];
+            while e != usize::MAX {
+                let v = self.to[e];
+                if self.cap[e] > 0 && level[v] == usize::MAX {
+                    level[v] = level[u] + 1;
+                    queue.push_back(v);
+                }
+                e = self.next[e];
+            }
+        }
+        level
+    }
+
+    /// Push a maximum flow from `s` to `t` and return its value.
+    fn max_flow(&mut self, s: usize, t: usize) -> i64 {
+        let mut total = 0i64;
+        loop {
+            let mut level = self.levels(s);
+            if level[t] == usize::MAX {
+                return total;
+            }
+            // Blocking flow by iterative DFS along level-increasing arcs
+            let mut iter = self.head.clone();
+            let mut path: Vec<usize> = Vec::new();
+            let mut u = s;
+            loop {
+                if u == t {
+                    let f = path.iter().map(|&e| self.cap[e]).min().unwrap_or(0);
+                    for &e in &path {
+                        self.cap[e] -= f;
+                        self.cap[e ^ 1] += f;
+                    }
+                    total = total.saturating_add(f);
+                    path.clear();
+                    u = s;
+                    continue;
+                }
+                while iter[u] != usize::MAX {
+                    let e = iter[u];
+                    if self.cap[e] > 0 && level[self.to[e]] == level[u] + 1 {
+                        break;
+                    }
+                    iter[u] = self.next[e];
+                }
+                if iter[u] != usize::MAX {
+                    let e = iter[u];
+                    path.push(e);
+                    u = self.to[e];
+                } else {
+                    // Dead end: prune `u` and retreat one arc
+                    level[u] = usize::MAX;
+                    let Some(e) = path.pop() else {
+                        break;
+                    };
+                    u = self.to[e ^ 1];
+                    iter[u] = self.next[e];
+                }
+            }
+        }
+    }
+
+    /// Nodes reachable from `s` in the residual network.
+    fn reachable(&self, s: usize) -> Vec<bool> {
+        self.levels(s)
+            .into_iter()
+            .map(|l| l != usize::MAX)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `rows x cols` grid with edge weights 1..=3 and a self-loop on every
+    /// fifth vertex.
+    fn weighted_grid(rows: usize, cols: usize) -> Graph {
+        let mut edges = Vec::new();
+        for u in 0..rows * cols {
+            if u % cols + 1 < cols {
+                edges.push((u, u + 1, 1 + (u % 3) as i64));
+            }
+            if u + cols < rows * cols {
+                edges.push((u, u + cols, 1 + (u % 2) as i64));
+            }
+            if u % 5 == 0 {
+                edges.push((u, u, 4));
+            }
+        }
+        Graph::from_edges(rows * cols, &edges)
+    }
+
+    #[test]
+    fn boundary_tracks_moves_incrementally() {
+        let g = weighted_grid(12, 12);
+        let nparts = 4;
+        let mut part: Vec<usize> = (0..g.n).map(|u| (u % 12) / 3).collect();
+        let mut boundary = Boundary::default();
+        boundary.reset(&g, &part, nparts);
+
+        let mut rng = Rng::new(7);
+        for _ in 0..500 {
+            let u = rng.below(g.n);
+            let to = rng.below(nparts);
+            boundary.move_vertex(&g, &mut part, u, to);
+
+            let mut listed = boundary.vertices().to_vec();
+            listed.sort_unstable();
+            let expected: Vec<usize> = (0..g.n)
+                .filter(|&u| g.neighbors(u).iter().any(|&v| part[v] != part[u]))
+                .collect();
+            assert_eq!(listed, expected);
+            assert_eq!(boundary.cut(), g.edge_cut(&part));
+        }
+    }
+}
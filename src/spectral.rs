@@ -0,0 +1,188 @@
+//! Fiedler vector estimation for spectral bisection.
+//!
+//! A short Lanczos run on the weighted graph Laplacian `L = D - A`, kept
+//! orthogonal to the constant vector (its null space on a connected graph),
+//! followed by an implicit QL eigensolve of the small tridiagonal matrix.
+//! The Ritz vector of the smallest eigenvalue approximates the Fiedler
+//! vector, whose sorted entries order the vertices along the graph.
+
+use crate::graph::Graph;
+use crate::rng::Rng;
+
+/// Maximum number of Lanczos steps; the coarsest graphs this runs on are
+/// small, so a few dozen steps resolve the low end of the spectrum well.
+const LANCZOS_STEPS: usize = 80;
+
+/// QL iterations allowed per eigenvalue before giving up on it.
+const QL_ITERATIONS: usize = 60;
+
+/// Approximate Fiedler vector of `g`, one entry per vertex.
+///
+/// The Lanczos start vector is drawn from `seed`, so the estimate is
+/// deterministic for a given graph and seed. Self-loops are ignored.
+/// Returns all zeros for graphs with fewer than two vertices.
+pub(crate) fn fiedler_vector(g: &Graph, seed: u64) -> Vec<f64> {
+    let n = g.n;
+    if n < 2 {
+        return vec![0.0; n];
+    }
+    let steps = (n - 1).min(LANCZOS_STEPS);
+
+    let mut rng = Rng::new(seed);
+    let mut q: Vec<f64> = (0..n)
+        .map(|_| rng.next_u64() as f64 / u64::MAX as f64)
+        .collect();
+    remove_mean(&mut q);
+    if normalize(&mut q) == 0.0 {
+        return vec![0.0; n];
+    }
+
+    let mut basis: Vec<Vec<f64>> = Vec::with_capacity(steps);
+    let mut alpha = Vec::with_capacity(steps);
+    let mut beta = Vec::with_capacity(steps);
+    loop {
+        let mut w = laplacian_times(g, &q);
+        let a = dot(&w, &q);
+        alpha.push(a);
+        basis.push(q);
+        if basis.len() == steps {
+            break;
+        }
+        // Full reorthogonalization, done twice for stability; it subsumes
+        // the three-term recurrence.
+        for _ in 0..2 {
+            remove_mean(&mut w);
+            for b in &basis {
+                let c = dot(&w, b);
+                w.iter_mut().zip(b).for_each(|(x, y)| *x -= c * y);
+            }
+        }
+        let norm = normalize(&mut w);
+        if norm <= 1e-12 * a.abs().max(1.0) {
+            // Invariant subspace found: T is exact
+            break;
+        }
+        beta.push(norm);
+        q = w;
+    }
+
+    let m = alpha.len();
+    let mut d = alpha;
+    let mut e = beta;
+    e.resize(m, 0.0);
+    let mut z: Vec<Vec<f64>> = (0..m)
+        .map(|i| (0..m).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+        .collect();
+    tridiagonal_eigen(&mut d, &mut e, &mut z);
+    let smallest = (0..m).min_by(|&i, &j| d[i].total_cmp(&d[j])).unwrap();
+
+    let mut x = vec![0.0; n];
+    for (j, b) in basis.iter().enumerate() {
+        let c = z[j][smallest];
+        x.iter_mut().zip(b).for_each(|(x, y)| *x += c * y);
+    }
+    x
+}
+
+/// `L x` for the weighted Laplacian of `g`.
+fn laplacian_times(g: &Graph, x: &[f64]) -> Vec<f64> {
+    (0..g.n)
+        .map(|u| {
+            let mut y = 0.0;
+            for (v, w) in g.edges(u) {
+                if v != u {
+                    y += w as f64 * (x[u] - x[v]);
+                }
+            }
+            y
+        })
+        .collect()
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Project out the constant vector.
+fn remove_mean(x: &mut [f64]) {
+    let mean = x.iter().sum::<f64>() / x.len() as f64;
+    x.iter_mut().for_each(|v| *v -= mean);
+}
+
+/// Scale `x` to unit length, returning its previous length. A zero vector
+/// is left as is.
+fn normalize(x: &mut [f64]) -> f64 {
+    let norm = dot(x, x).sqrt();
+    if norm > 0.0 {
+        x.iter_mut().for_each(|v| *v /= norm);
+    }
+    norm
+}
+
+/// Eigen-decompose a symmetric tridiagonal matrix by implicit QL.
+///
+/// On entry `d` holds the diagonal and `e[i]` the entry coupling rows `i`
+/// and `i + 1` (the last entry is ignored), and `z` is the identity. On
+/// return `d` holds the eigenvalues and column `i` of `z` the eigenvector
+/// of `d[i]`.
+fn tridiagonal_eigen(d: &mut [f64], e: &mut [f64], z: &mut [Vec<f64>]) {
+    let n = d.len();
+    if n == 0 {
+        return;
+    }
+    e[n - 1] = 0.0;
+    for l in 0..n {
+        let mut iter = 0;
+        loop {
+            // Find the first negligible off-diagonal entry at or after l
+            let mut m = l;
+            while m + 1 < n {
+                let dd = d[m].abs() + d[m + 1].abs();
+                if e[m].abs() <= f64::EPSILON * dd {
+                    break;
+                }
+                m += 1;
+            }
+            if m == l || iter == QL_ITERATIONS {
+                break;
+            }
+            iter += 1;
+
+            let mut g = (d[l + 1] - d[l]) / (2.0 * e[l]);
+            let mut r = g.hypot(1.0);
+            g = d[m] - d[l] + e[l] / (g + r.copysign(g));
+            let (mut s, mut c, mut p) = (1.0, 1.0, 0.0);
+            let mut underflow = false;
+            for i in (l..m).rev() {
+                let f = s * e[i];
+                let b = c * e[i];
+                r = f.hypot(g);
+                e[i + 1] = r;
+                if r == 0.0 {
+                    d[i + 1] -= p;
+                    e[m] = 0.0;
+                    underflow = true;
+                    break;
+                }
+                s = f / r;
+                c = g / r;
+                g = d[i + 1] - p;
+                r = (d[i] - g) * s + 2.0 * c * b;
+                p = s * r;
+                d[i + 1] = g + p;
+                g = c * r - b;
+                for row in z.iter_mut() {
+                    let f = row[i + 1];
+                    row[i + 1] = s * row[i] + c * f;
+                    row[i] = c * row[i] - s * f;
+                }
+            }
+            if underflow {
+                continue;
+            }
+            d[l] -= p;
+            e[l] = g;
+            e[m] = 0.0;
+        }
+    }
+}
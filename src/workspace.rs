@@ -0,0 +1,36 @@
+//! Reusable working memory for repeated partitioning.
+//!
+//! Every partitioning call allocates per-vertex arrays on each level of the
+//! multilevel pipeline. A [`Workspace`] keeps them between calls, which
+//! matters when the same kind of graph is partitioned over and over, e.g.
+//! once per time step of a simulation.
+
+use crate::coarsen::CoarsenScratch;
+use crate::refine::RefineScratch;
+
+/// Working memory of the multilevel pipeline, kept between calls.
+///
+/// Pass the same workspace to [`crate::PartitionConfig::partition_with_workspace`]
+/// or [`crate::partition_with_workspace`] on every call. The matching,
+/// contraction, projection and refinement arrays then keep their capacity,
+/// so a call on a graph no larger than earlier ones only allocates the
+/// coarse graphs and the partition it returns. Results never depend on the
+/// workspace or on what it was used for before.
+///
+/// With the `rayon` feature, seeded trials (see
+/// [`crate::PartitionConfig::ntrials`]) run in parallel, each with a
+/// workspace of its own.
+#[derive(Debug, Default)]
+pub struct Workspace {
+    pub(crate) coarsen: CoarsenScratch,
+    pub(crate) refine: RefineScratch,
+    /// Spare partition buffer for projecting between levels.
+    pub(crate) part: Vec<usize>,
+}
+
+impl Workspace {
+    /// An empty workspace; it grows to fit the graphs it is used on.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
@@ -0,0 +1,65 @@
+//! Graph generators shared by the integration tests.
+
+#![allow(dead_code)]
+
+use metis_rs::Graph;
+
+/// Path `0 - 1 - ... - (n-1)`.
+pub fn path(n: usize) -> Graph {
+    let edges: Vec<(usize, usize, i64)> = (1..n).map(|u| (u - 1, u, 1)).collect();
+    Graph::from_edges(n, &edges)
+}
+
+/// `rows x cols` grid; vertex `(r, c)` is `r * cols + c`.
+pub fn grid(rows: usize, cols: usize) -> Graph {
+    let mut edges = Vec::new();
+    for r in 0..rows {
+        for c in 0..cols {
+            let u = r * cols + c;
+            if c + 1 < cols {
+                edges.push((u, u + 1, 1));
+            }
+            if r + 1 < rows {
+                edges.push((u, u + cols, 1));
+            }
+        }
+    }
+    Graph::from_edges(rows * cols, &edges)
+}
+
+/// Xorshift generator of reproducible pseudo-random test data.
+pub fn xorshift(seed: u64) -> impl FnMut() -> u64 {
+    let mut x = seed;
+    move || {
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        x
+    }
+}
+
+/// [`grid`] with edge weights in `1..=max_weight`, drawn in edge order from
+/// [`xorshift`] seeded with `seed`.
+pub fn weighted_grid(rows: usize, cols: usize, max_weight: i64, seed: u64) -> Graph {
+    let mut next = xorshift(seed);
+    let mut weight = || (next() % max_weight as u64) as i64 + 1;
+    let mut edges = Vec::new();
+    for u in 0..rows * cols {
+        if u % cols + 1 < cols {
+            edges.push((u, u + 1, weight()));
+        }
+        if u + cols < rows * cols {
+            edges.push((u, u + cols, weight()));
+        }
+    }
+    Graph::from_edges(rows * cols, &edges)
+}
+
+/// Total vertex weight of each part.
+pub fn part_weights(g: &Graph, part: &[usize], nparts: usize) -> Vec<i64> {
+    let mut weights = vec![0i64; nparts];
+    for u in 0..g.n {
+        weights[part[u]] += g.vertex_weight(u);
+    }
+    weights
+}
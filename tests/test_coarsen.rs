@@ -0,0 +1,326 @@
+mod common;
+
+use common::path;
+use metis_rs::coarsen::{
+    build_hierarchy, coarse_fixed, coarsen_once, coarsen_once_bounded, coarsen_once_capped,
+    coarsen_once_clustered, coarsen_once_fixed, coarsen_once_global_path, coarsen_once_sorted,
+    coarsen_to_size, multilevel_coarsen, multilevel_coarsen_seeded, multilevel_coarsen_sorted,
+    multilevel_coarsen_to_sizes,
+};
+use metis_rs::refine::refine_kway;
+use metis_rs::{Graph, PartitionConfig};
+
+#[test]
+fn coarsening_follows_requested_sizes() {
+    let g = path(32);
+    let levels = multilevel_coarsen_to_sizes(&g, &[20, 10, 5]);
+    let sizes: Vec<usize> = levels.iter().map(|l| l.nc).collect();
+    assert_eq!(sizes, vec![20, 10, 5]);
+    for level in &levels {
+        assert_eq!(level.graph.n, level.nc);
+        let total: i64 = (0..level.nc).map(|u| level.graph.vertex_weight(u)).sum();
+        assert_eq!(total, 32);
+    }
+}
+
+#[test]
+fn coarsen_to_size_absorbs_unmatched_leaves() {
+    // Star: center 0 with leaves 1..=8. Matching alone only merges one leaf.
+    let mut adjncy: Vec<usize> = (1..=8).collect();
+    adjncy.extend([0; 8]);
+    let xadj = (0..=9).map(|u| if u == 0 { 0 } else { 7 + u }).collect();
+    let g = Graph::new(9, xadj, adjncy);
+
+    let level = coarsen_to_size(&g, 3);
+    assert_eq!(level.nc, 3);
+    assert!(level.cmap.iter().all(|&c| c < 3));
+}
+
+#[test]
+fn seeded_coarsening_is_reproducible() {
+    let g = common::grid(12, 12);
+    let a = multilevel_coarsen_seeded(&g, 10, 3);
+    let b = multilevel_coarsen_seeded(&g, 10, 3);
+    assert_eq!(a.len(), b.len());
+    for (la, lb) in a.iter().zip(&b) {
+        assert_eq!(la.cmap, lb.cmap);
+    }
+}
+
+#[test]
+fn coarsening_keeps_differently_pinned_vertices_apart() {
+    // Without pins, heavy-edge matching merges 0 with 1 and 2 with 3.
+    let g = path(4);
+    let fixed = vec![Some(0), Some(1), None, Some(1)];
+    let level = coarsen_once_fixed(&g, &fixed);
+    assert_ne!(level.cmap[0], level.cmap[1]);
+    let cfixed = coarse_fixed(&fixed, &level);
+    for u in 0..g.n {
+        if let Some(k) = fixed[u] {
+            assert_eq!(cfixed[level.cmap[u]], Some(k));
+        }
+    }
+}
+
+#[test]
+fn bounded_coarsening_keeps_enough_vertices() {
+    let g = path(10);
+    let level = coarsen_once_bounded(&g, 7);
+    assert_eq!(level.nc, 7);
+    assert_eq!(coarsen_once_bounded(&g, 0).nc, 5);
+    assert_eq!(coarsen_once_bounded(&g, 20).nc, 10);
+}
+
+/// Hub 0 joined to `arms` sub-hubs, each with `leaves` leaves of its own.
+fn star_of_stars(arms: usize, leaves: usize) -> Graph {
+    let mut edges = Vec::new();
+    for a in 0..arms {
+        let hub = 1 + a * (leaves + 1);
+        edges.push((0, hub, 1));
+        for l in 1..=leaves {
+            edges.push((hub, hub + l, 1));
+        }
+    }
+    Graph::from_edges(1 + arms * (leaves + 1), &edges)
+}
+
+#[test]
+fn two_hop_matching_shrinks_star_of_stars() {
+    // Heavy-edge matching alone merges each hub with one neighbor and leaves
+    // the remaining 45 leaves as singletons (51 coarse vertices).
+    let g = star_of_stars(5, 10);
+    let level = coarsen_once(&g);
+    assert!(level.nc <= 31, "only shrank {} -> {}", g.n, level.nc);
+    assert_eq!(level.graph.n, level.nc);
+    let total: i64 = (0..level.nc).map(|u| level.graph.vertex_weight(u)).sum();
+    assert_eq!(total, g.n as i64);
+    // Still a matching: no coarse vertex holds more than two fine vertices
+    assert!((0..level.nc).all(|c| level.cmap.iter().filter(|&&x| x == c).count() <= 2));
+
+    let levels = multilevel_coarsen(&g, 10);
+    assert!(levels.last().unwrap().nc <= 10);
+}
+
+/// Path 0..spine with a leaf hanging off every path vertex.
+fn comb(spine: usize) -> Graph {
+    let mut edges: Vec<(usize, usize, i64)> = (1..spine).map(|u| (u - 1, u, 1)).collect();
+    edges.extend((0..spine).map(|u| (u, spine + u, 1)));
+    Graph::from_edges(2 * spine, &edges)
+}
+
+#[test]
+fn sorted_matching_pairs_every_leaf_of_a_comb() {
+    // In natural order the path vertices match each other and strand the
+    // leaves; visiting low-degree vertices first matches each leaf instead.
+    let g = comb(20);
+    assert!(coarsen_once(&g).nc > 20);
+    let level = coarsen_once_sorted(&g, 7);
+    assert_eq!(level.nc, 20);
+    for u in 0..20 {
+        assert_eq!(level.cmap[u], level.cmap[20 + u]);
+    }
+}
+
+#[test]
+fn sorted_matching_is_reproducible() {
+    let g = common::grid(12, 12);
+    let a = multilevel_coarsen_sorted(&g, 10, 3);
+    let b = multilevel_coarsen_sorted(&g, 10, 3);
+    assert_eq!(a.len(), b.len());
+    for (la, lb) in a.iter().zip(&b) {
+        assert_eq!(la.cmap, lb.cmap);
+    }
+    // Random tie-breaking differs from the natural-order matching.
+    assert_ne!(coarsen_once_sorted(&g, 3).cmap, coarsen_once(&g).cmap);
+}
+
+#[test]
+fn cluster_coarsening_contracts_stars_in_one_level() {
+    // Matching leaves 20 coarse vertices; each arm fits in one cluster.
+    let g = star_of_stars(5, 6);
+    assert_eq!(coarsen_once(&g).nc, 20);
+    let level = coarsen_once_clustered(&g);
+    assert!(level.nc <= 6, "only shrank to {}", level.nc);
+    assert_eq!(level.graph.n, level.nc);
+    let weights: Vec<i64> = (0..level.nc)
+        .map(|u| level.graph.vertex_weight(u))
+        .collect();
+    assert_eq!(weights.iter().sum::<i64>(), g.n as i64);
+    // No cluster exceeds eight times the average vertex weight.
+    assert!(weights.iter().all(|&w| w <= 8), "{:?}", weights);
+}
+
+#[test]
+fn cluster_coarsening_caps_every_constraint() {
+    // A 12-clique whose first two vertices carry all of the second
+    // constraint. The cap on each constraint is eight times its average
+    // vertex weight, 8: eight unit vertices fit in a cluster, but only one
+    // heavy one.
+    let mut edges = Vec::new();
+    for u in 0..12 {
+        for v in u + 1..12 {
+            edges.push((u, v, 1));
+        }
+    }
+    let g = Graph::from_edges(12, &edges).with_vwgt_multi(
+        (0..12)
+            .map(|u| vec![1, if u < 2 { 6 } else { 0 }])
+            .collect(),
+    );
+    let level = coarsen_once_clustered(&g);
+    assert!(level.nc < g.n);
+    assert_ne!(level.cmap[0], level.cmap[1]);
+    for c in 0..level.nc {
+        let weights: Vec<i64> = (0..2)
+            .map(|k| level.graph.constraint_weight(c, k))
+            .collect();
+        assert!(weights.iter().all(|&w| w <= 8), "{:?}", weights);
+    }
+}
+
+#[test]
+fn capped_coarsening_never_exceeds_the_weight_cap() {
+    let g = path(16).with_vwgt((0..16).map(|u| if u == 8 { 3 } else { 1 }).collect());
+    let mut current = g.clone();
+    for _ in 0..4 {
+        let level = coarsen_once_capped(&current, 4);
+        let weights: Vec<i64> = (0..level.nc)
+            .map(|u| level.graph.vertex_weight(u))
+            .collect();
+        assert!(weights.iter().all(|&w| w <= 4), "{:?}", weights);
+        assert_eq!(weights.iter().sum::<i64>(), 18);
+        current = level.graph;
+    }
+    // The heavy vertex may only take one unit-weight neighbor.
+    let level = coarsen_once_capped(&g, 4);
+    let heavy = level.cmap[8];
+    assert_eq!(level.cmap.iter().filter(|&&c| c == heavy).count(), 2);
+    assert!(current.n < g.n);
+    // Uncapped, repeated matching merges everything into heavier vertices.
+    let mut uncapped = g;
+    for _ in 0..4 {
+        uncapped = coarsen_once(&uncapped).graph;
+    }
+    assert!((0..uncapped.n).any(|u| uncapped.vertex_weight(u) > 4));
+}
+
+#[test]
+fn coarse_weight_cap_applies_to_every_constraint() {
+    // The second constraint sits on a 3x3 corner block of a 12x12 grid, so
+    // its cap for four parts is ceil(90 / 4) = 23 while the first
+    // constraint's is 36.
+    let g = common::grid(12, 12).with_vwgt_multi(
+        (0..144)
+            .map(|u| vec![1, if u % 12 < 3 && u / 12 < 3 { 10 } else { 0 }])
+            .collect(),
+    );
+    let hierarchy = build_hierarchy(&g, &PartitionConfig::default().nparts(4));
+    assert!(hierarchy.depth() > 0);
+    for level in 1..=hierarchy.depth() {
+        let coarse = hierarchy.graph(level);
+        for u in 0..coarse.n {
+            assert!(coarse.constraint_weight(u, 0) <= 36);
+            assert!(coarse.constraint_weight(u, 1) <= 23, "level {}", level);
+        }
+    }
+
+    let capped = coarsen_once_capped(&g, 10);
+    for u in 0..capped.nc {
+        assert!(capped.graph.constraint_weight(u, 1) <= 10);
+    }
+}
+
+#[test]
+fn hierarchy_drives_a_custom_partitioner() {
+    let g = common::grid(20, 20);
+    let opts = PartitionConfig::default().nparts(4).seed(1);
+    let hierarchy = build_hierarchy(&g, &opts);
+    let sizes = hierarchy.sizes();
+    assert_eq!(sizes.len(), hierarchy.depth() + 1);
+    assert_eq!(sizes[0], 400);
+    assert!(sizes.windows(2).all(|w| w[1] < w[0]), "{:?}", sizes);
+    // Four parts coarsen down to at most 10 vertices per part.
+    assert!(*sizes.last().unwrap() <= 40);
+    assert_eq!(hierarchy.coarsest().n, *sizes.last().unwrap());
+    for (level, coarse) in hierarchy.levels().iter().enumerate() {
+        assert_eq!(hierarchy.cmap(level).len(), sizes[level]);
+        assert_eq!(coarse.nc, sizes[level + 1]);
+    }
+
+    // Round-robin on the coarsest graph, then project and refine each level.
+    let depth = hierarchy.depth();
+    let mut part: Vec<usize> = (0..sizes[depth]).map(|u| u % 4).collect();
+    for level in (0..depth).rev() {
+        part = hierarchy.project(level, &part);
+        refine_kway(hierarchy.graph(level), &mut part, 4, &opts);
+    }
+    assert_eq!(part.len(), 400);
+    let counts = (0..4).map(|p| part.iter().filter(|&&q| q == p).count());
+    assert!(counts.into_iter().all(|c| c <= 105));
+
+    // The same seed rebuilds the same hierarchy.
+    let again = build_hierarchy(&g, &opts);
+    assert_eq!(again.sizes(), sizes);
+}
+
+#[test]
+fn coarsest_size_scales_with_nparts() {
+    let g = common::grid(40, 40);
+    let scaled = build_hierarchy(&g, &PartitionConfig::default().nparts(64));
+    let coarsest = scaled.coarsest().n;
+    assert!((640 / 2..=640).contains(&coarsest), "coarsest {}", coarsest);
+
+    let fixed = PartitionConfig::default().nparts(64).coarsen_per_part(0);
+    assert!(build_hierarchy(&g, &fixed).coarsest().n < 320);
+}
+
+#[test]
+fn coarsening_stops_at_dense_graphs() {
+    // Complete graph on 30 vertices: every pair is adjacent.
+    let edges: Vec<(usize, usize, i64)> = (0..30)
+        .flat_map(|u| (u + 1..30).map(move |v| (u, v, 1)))
+        .collect();
+    let g = Graph::from_edges(30, &edges);
+    assert!(multilevel_coarsen(&g, 2).is_empty());
+    // A sparse graph of the same size still coarsens.
+    assert!(!multilevel_coarsen(&path(30), 2).is_empty());
+}
+
+/// Edge weight left in the coarse graph, i.e. not contracted.
+fn exposed_weight(level: &metis_rs::coarsen::CoarsenLevel) -> i64 {
+    level.graph.adjwgt.iter().sum::<i64>() / 2
+}
+
+#[test]
+fn global_path_matching_contracts_heavier_edges() {
+    // Path 0-1-2-3 with weights 1, 3, 1: greedy matching from vertex 0
+    // takes both light edges, while the optimum takes the middle one.
+    let g = Graph::from_edges(4, &[(0, 1, 1), (1, 2, 3), (2, 3, 1)]);
+    assert_eq!(exposed_weight(&coarsen_once(&g)), 3);
+    let level = coarsen_once_global_path(&g);
+    assert_eq!(level.cmap[1], level.cmap[2]);
+    assert_eq!(exposed_weight(&level), 2);
+
+    let g = common::weighted_grid(20, 20, 13, 12345);
+    let greedy = coarsen_once(&g);
+    let gpa = coarsen_once_global_path(&g);
+    assert!(exposed_weight(&gpa) < exposed_weight(&greedy));
+    assert!((0..gpa.nc).all(|c| gpa.cmap.iter().filter(|&&x| x == c).count() <= 2));
+}
+
+#[test]
+fn global_path_matching_breaks_cycles_into_paths() {
+    // 8-cycle alternating weights 1 and 5. Path growing drops one edge to
+    // open the cycle, then matches the resulting path optimally: every
+    // weight-5 edge, leaving only the four weight-1 edges exposed.
+    let edges: Vec<(usize, usize, i64)> = (0..8)
+        .map(|u| (u, (u + 1) % 8, if u % 2 == 1 { 5 } else { 1 }))
+        .collect();
+    let g = Graph::from_edges(8, &edges);
+    let level = coarsen_once_global_path(&g);
+    assert_eq!(level.nc, 4);
+    assert_eq!(exposed_weight(&level), 4);
+    for u in (1..8).step_by(2) {
+        assert_eq!(level.cmap[u], level.cmap[(u + 1) % 8]);
+    }
+}
@@ -0,0 +1,491 @@
+use metis_rs::{Graph, GraphBuilder, GraphCheck, GraphError, NormalizeReport, Symmetrize};
+
+/// Two triangles {0,1,2} and {3,4,5} joined by the bridge 2-3.
+fn two_cliques() -> Graph {
+    Graph::new(
+        6,
+        vec![0, 2, 4, 7, 10, 12, 14],
+        vec![1, 2, 0, 2, 0, 1, 3, 2, 4, 5, 3, 5, 3, 4],
+    )
+}
+
+#[test]
+fn ghost_weights_match_full_graph_boundary() {
+    let g = two_cliques().with_adjwgt(vec![1, 1, 1, 1, 1, 1, 7, 7, 1, 1, 1, 1, 1, 1]);
+    let part = vec![0, 0, 1, 2, 2, 2];
+    let verts = [0, 1, 2];
+
+    let (sub, ghosts) = g.induced_subgraph_with_ghosts(&verts, &part);
+    assert_eq!(sub.n, 3);
+    assert_eq!(sub.adjncy.len(), 6);
+    assert_eq!(ghosts.boundary, vec![2]);
+    assert_eq!(ghosts.external, vec![vec![(2, 7)]]);
+
+    // Subgraph degree plus ghost weight recovers the full weighted degree.
+    for (i, &u) in verts.iter().enumerate() {
+        let ghost: i64 = ghosts
+            .boundary
+            .iter()
+            .position(|&b| b == i)
+            .map_or(0, |j| ghosts.external[j].iter().map(|&(_, w)| w).sum());
+        assert_eq!(sub.weighted_degree(i) + ghost, g.weighted_degree(u));
+    }
+}
+
+#[test]
+fn induced_subgraph_keeps_inner_edges_and_maps_back() {
+    let g = two_cliques().with_vwgt(vec![1, 2, 3, 4, 5, 6]);
+    let (sub, map) = g.induced_subgraph(&[3, 2, 1]);
+    assert_eq!(map, vec![3, 2, 1]);
+    assert_eq!(sub.n, 3);
+    // 3 - 2 (the bridge) and 2 - 1 survive; 3's clique and 0 are gone
+    assert_eq!(sub.xadj, vec![0, 1, 3, 4]);
+    assert_eq!(sub.adjncy, vec![1, 2, 0, 1]);
+    assert_eq!(sub.vwgt, vec![4, 3, 2]);
+    assert!(sub.check().is_ok());
+    for (i, &u) in map.iter().enumerate() {
+        assert_eq!(sub.vertex_weight(i), g.vertex_weight(u));
+    }
+}
+
+#[test]
+fn permuted_relabels_vertices_and_weights() {
+    let edges = [
+        (0, 1, 1),
+        (0, 2, 2),
+        (1, 2, 3),
+        (2, 3, 4),
+        (3, 4, 5),
+        (3, 5, 6),
+        (4, 5, 7),
+    ];
+    let g = Graph::from_edges(6, &edges).with_vwgt(vec![10, 11, 12, 13, 14, 15]);
+    let perm = [3, 4, 5, 0, 1, 2];
+    let p = g.permuted(&perm);
+    assert!(p.check().is_ok());
+    assert_eq!(p.vwgt, vec![13, 14, 15, 10, 11, 12]);
+    for (i, &u) in perm.iter().enumerate() {
+        let mut old: Vec<(usize, i64)> = (0..g.degree(u))
+            .map(|k| (g.neighbors(u)[k], g.edge_weight(u, k)))
+            .collect();
+        let mut new: Vec<(usize, i64)> = (0..p.degree(i))
+            .map(|k| (perm[p.neighbors(i)[k]], p.edge_weight(i, k)))
+            .collect();
+        old.sort_unstable();
+        new.sort_unstable();
+        assert_eq!(old, new);
+    }
+    let part = [0, 0, 0, 1, 1, 1];
+    assert_eq!(p.edge_cut(&part), g.edge_cut(&part));
+}
+
+#[test]
+#[should_panic(expected = "not a permutation")]
+fn permuted_rejects_repeated_vertices() {
+    two_cliques().permuted(&[0, 1, 2, 3, 4, 4]);
+}
+
+#[test]
+fn builder_merges_duplicates_and_grows_vertex_count() {
+    let mut b = GraphBuilder::with_vertices(5);
+    b.add_edge(3, 1, 2)
+        .add_edge(1, 3, 4)
+        .add_edge(0, 1, 1)
+        .add_edge(2, 2, 9)
+        .set_vertex_weight(6, 3);
+    assert_eq!(b.num_vertices(), 7);
+    let g = b.finalize();
+    assert!(g.check().is_ok());
+    assert_eq!(g.n, 7);
+    assert_eq!(g.xadj, vec![0, 1, 3, 3, 4, 4, 4, 4]);
+    assert_eq!(g.adjncy, vec![1, 0, 3, 1]);
+    assert_eq!(g.adjwgt, vec![1, 1, 6, 6]);
+    assert_eq!(g.vwgt, vec![1, 1, 1, 1, 1, 1, 3]);
+
+    let mut unit = GraphBuilder::new();
+    unit.add_edge(0, 1, 1).add_edge(1, 2, 1);
+    let path = unit.finalize();
+    assert!(path.adjwgt.is_empty() && path.vwgt.is_empty());
+}
+
+#[test]
+fn edges_pairs_neighbors_with_weights() {
+    let g = two_cliques();
+    assert_eq!(g.edges(3).collect::<Vec<_>>(), vec![(2, 1), (4, 1), (5, 1)]);
+    let g = g.with_adjwgt((1..=14).collect());
+    assert_eq!(g.edges(0).collect::<Vec<_>>(), vec![(1, 1), (2, 2)]);
+    for u in 0..g.n {
+        assert_eq!(
+            g.edges(u).map(|(_, w)| w).sum::<i64>(),
+            g.weighted_degree(u)
+        );
+    }
+}
+
+#[test]
+#[should_panic(expected = "subgraph vertex 2 is out of range or repeated")]
+fn induced_subgraph_rejects_repeated_vertices() {
+    two_cliques().induced_subgraph(&[1, 2, 2]);
+}
+
+#[test]
+fn from_edges_saturates_repeated_pairs() {
+    let g = Graph::from_edges(2, &[(0, 1, i64::MAX), (1, 0, 1)]);
+    assert_eq!(g.adjwgt, vec![i64::MAX, i64::MAX]);
+}
+
+#[test]
+#[should_panic(expected = "edge (0, 1) has negative weight -2")]
+fn from_edges_rejects_negative_weights() {
+    Graph::from_edges(2, &[(0, 1, -2)]);
+}
+
+#[test]
+fn try_new_rejects_malformed_csr() {
+    assert_eq!(
+        Graph::try_new(3, vec![0, 1, 2], vec![1, 0]).unwrap_err(),
+        GraphError::XadjLength {
+            expected: 4,
+            actual: 3
+        }
+    );
+    assert_eq!(
+        Graph::try_new(2, vec![0, 2, 1], vec![1]).unwrap_err(),
+        GraphError::XadjNotMonotonic { index: 1 }
+    );
+    assert_eq!(
+        Graph::try_new(2, vec![0, 1, 3], vec![1, 0]).unwrap_err(),
+        GraphError::XadjEnd {
+            expected: 2,
+            actual: 3
+        }
+    );
+    assert_eq!(
+        Graph::try_new(2, vec![0, 1, 2], vec![1, 5]).unwrap_err(),
+        GraphError::NeighborOutOfRange {
+            vertex: 1,
+            neighbor: 5
+        }
+    );
+}
+
+#[test]
+fn try_with_weights_checks_lengths() {
+    let g = Graph::try_new(2, vec![0, 1, 2], vec![1, 0]).unwrap();
+    assert_eq!(
+        g.clone().try_with_adjwgt(vec![1]).unwrap_err(),
+        GraphError::AdjwgtLength {
+            expected: 2,
+            actual: 1
+        }
+    );
+    assert_eq!(
+        g.clone().try_with_vwgt(vec![1, 2, 3]).unwrap_err(),
+        GraphError::VwgtLength {
+            expected: 2,
+            actual: 3
+        }
+    );
+    let g = g.try_with_adjwgt(vec![4, 4]).unwrap();
+    assert_eq!(g.edge_weight(0, 0), 4);
+}
+
+#[test]
+fn negative_weights_are_rejected() {
+    let g = Graph::try_new(2, vec![0, 1, 2], vec![1, 0]).unwrap();
+    assert_eq!(
+        g.clone().try_with_adjwgt(vec![3, -3]).unwrap_err(),
+        GraphError::NegativeEdgeWeight { index: 1 }
+    );
+    assert_eq!(
+        g.clone().try_with_vwgt(vec![-1, 0]).unwrap_err(),
+        GraphError::NegativeVertexWeight { vertex: 0 }
+    );
+    assert_eq!(
+        g.clone()
+            .try_with_vwgt_multi(vec![vec![1, 2], vec![0, -2]])
+            .unwrap_err(),
+        GraphError::NegativeVertexWeight { vertex: 1 }
+    );
+    assert_eq!(
+        g.clone().try_with_vsize(vec![1, -1]).unwrap_err(),
+        GraphError::NegativeVertexSize { vertex: 1 }
+    );
+
+    // Fields set by hand are caught by validate
+    let mut bad = g.clone();
+    bad.adjwgt = vec![2, -2];
+    assert_eq!(
+        bad.validate(),
+        Err(GraphError::NegativeEdgeWeight { index: 1 })
+    );
+    assert!(g.with_vwgt(vec![0, 0]).validate().is_ok());
+}
+
+#[test]
+fn check_lists_every_problem() {
+    assert!(two_cliques().check().is_ok());
+
+    // 0: [1, 1, 0, 2], 1: [0, 7], 2: [3], 3: [2]
+    let mut g = Graph::new(4, vec![0, 4, 6, 7, 8], vec![1, 1, 0, 2, 0, 2, 3, 2]);
+    g.adjncy[5] = 7;
+    g.adjwgt = vec![1, 1, 5, 1, 3, 9, 4, 5];
+    g.vwgt = vec![1];
+    assert_eq!(
+        g.check(),
+        GraphCheck {
+            layout: Some(GraphError::VwgtLength {
+                expected: 4,
+                actual: 1
+            }),
+            out_of_range: vec![(1, 7)],
+            self_loops: vec![0],
+            duplicates: vec![(0, 1)],
+            asymmetric: vec![(0, 2)],
+            // 0 -> 1 weighs 1 + 1, 1 -> 0 weighs 3
+            weight_mismatches: vec![(0, 1), (2, 3)],
+        }
+    );
+
+    g.xadj[2] = 9;
+    let report = g.check();
+    assert!(!report.is_ok());
+    assert_eq!(
+        report.layout,
+        Some(GraphError::XadjNotMonotonic { index: 2 })
+    );
+    assert!(report.out_of_range.is_empty());
+}
+
+#[test]
+fn normalize_strips_self_loops_and_merges_parallel_edges() {
+    // 0: [1, 0, 1], 1: [0, 0, 1], 2: []
+    let g = Graph::new(3, vec![0, 3, 6, 6], vec![1, 0, 1, 0, 0, 1])
+        .with_adjwgt(vec![2, 7, 3, 4, 1, 7])
+        .with_vwgt(vec![1, 2, 3]);
+    let (clean, report) = g.normalize();
+    assert_eq!(
+        report,
+        NormalizeReport {
+            self_loops: 2,
+            merged: 2
+        }
+    );
+    assert_eq!(clean.xadj, vec![0, 1, 2, 2]);
+    assert_eq!(clean.adjncy, vec![1, 0]);
+    assert_eq!(clean.adjwgt, vec![5, 5]);
+    assert_eq!(clean.vwgt, g.vwgt);
+    assert!(clean.check().is_ok());
+
+    let (again, report) = clean.normalize();
+    assert!(report.is_clean());
+    assert_eq!(again.adjncy, clean.adjncy);
+
+    let (plain, report) = two_cliques().normalize();
+    assert!(report.is_clean());
+    assert!(plain.adjwgt.is_empty());
+}
+
+#[test]
+fn normalize_saturates_merged_weights() {
+    // 0: [1, 1], 1: [0, 0]
+    let max = i64::MAX;
+    let g = Graph::new(2, vec![0, 2, 4], vec![1, 1, 0, 0]).with_adjwgt(vec![max, 1, max, max]);
+    let (clean, report) = g.normalize();
+    assert_eq!(report.merged, 2);
+    assert_eq!(clean.adjwgt, vec![i64::MAX, i64::MAX]);
+}
+
+#[test]
+#[should_panic(expected = "out-of-range neighbor")]
+fn new_panics_on_out_of_range_neighbor() {
+    Graph::new(2, vec![0, 1, 2], vec![1, 2]);
+}
+
+#[test]
+fn from_edges_builds_symmetric_csr() {
+    // Triangle given in mixed orientation, with a duplicate and a self-loop.
+    let g = Graph::from_edges(4, &[(1, 0, 2), (1, 2, 1), (2, 0, 5), (0, 1, 3), (3, 3, 9)]);
+    assert_eq!(g.xadj, vec![0, 2, 4, 6, 6]);
+    assert_eq!(g.adjncy, vec![1, 2, 0, 2, 0, 1]);
+    assert_eq!(g.adjwgt, vec![5, 5, 5, 1, 5, 1]);
+    assert_eq!(g.degree(3), 0);
+    assert_eq!(g.edge_cut(&[0, 1, 1, 0]), 10);
+}
+
+#[test]
+fn unweighted_edges_keep_unit_weights_implicit() {
+    // A triangle, each edge given once in one orientation.
+    let g = Graph::from_unweighted_edges(3, &[(0, 1), (1, 2), (2, 0)]);
+    assert_eq!(g.adjncy, vec![1, 2, 0, 2, 0, 1]);
+    assert!(g.adjwgt.is_empty());
+    assert!(g.is_symmetric());
+
+    // 0-1 given twice, plus a self-loop that is dropped.
+    let g = Graph::from_unweighted_edges(3, &[(0, 1), (1, 0), (1, 2), (2, 2)]);
+    assert_eq!(g.xadj, vec![0, 1, 3, 4]);
+    assert_eq!(g.adjwgt, vec![2, 2, 1, 1]);
+}
+
+#[test]
+fn from_edges_matches_hand_built_csr() {
+    let built = Graph::from_edges(4, &[(0, 1, 1), (1, 2, 1), (2, 3, 1)]);
+    let manual = Graph::new(4, vec![0, 1, 3, 5, 6], vec![1, 0, 2, 1, 3, 2]);
+    assert_eq!(built.xadj, manual.xadj);
+    assert_eq!(built.adjncy, manual.adjncy);
+}
+
+#[test]
+fn connected_components_labels_in_vertex_order() {
+    // Components {0, 2}, {1, 3, 4}, {5}.
+    let g = Graph::from_edges(6, &[(0, 2, 1), (1, 3, 1), (3, 4, 1)]);
+    assert_eq!(g.connected_components(), vec![0, 1, 0, 1, 1, 2]);
+    assert_eq!(
+        g.connected_component_sizes(),
+        (vec![0, 1, 0, 1, 1, 2], vec![2, 3, 1])
+    );
+    assert!(Graph::new(0, vec![0], vec![])
+        .connected_components()
+        .is_empty());
+}
+
+#[test]
+fn symmetry_check_sees_weights_and_missing_edges() {
+    assert!(two_cliques().is_symmetric());
+
+    // 0 -> 1 without 1 -> 0
+    let g = Graph::new(2, vec![0, 1, 1], vec![1]);
+    assert!(!g.is_symmetric());
+
+    // Both directions present but with different weights
+    let g = Graph::new(2, vec![0, 1, 2], vec![1, 0]).with_adjwgt(vec![3, 5]);
+    assert!(!g.is_symmetric());
+}
+
+#[test]
+fn canonicalize_merges_duplicates_and_symmetrizes() {
+    // 0: [2, 1, 1, 0]   (duplicate 1, self-loop)
+    // 1: [0]            (reverse of the duplicated edge)
+    // 2: []             (0 -> 2 has no reverse)
+    let mut g =
+        Graph::new(3, vec![0, 4, 5, 5], vec![2, 1, 1, 0, 1]).with_adjwgt(vec![3, 2, 2, 9, 1]);
+    assert!(!g.is_symmetric());
+    g.canonicalize();
+    assert!(g.is_symmetric());
+    assert_eq!(g.xadj, vec![0, 2, 3, 4]);
+    assert_eq!(g.adjncy, vec![1, 2, 0, 0]);
+    // 0 -> 1 sums to 4 and beats 1 -> 0 (1); 0 -> 2 is mirrored.
+    assert_eq!(g.adjwgt, vec![4, 3, 4, 3]);
+    assert_eq!(g.edge_cut(&[0, 1, 1]), 7);
+}
+
+#[test]
+fn canonicalize_keeps_unit_weights_implicit() {
+    let mut g = Graph::new(3, vec![0, 2, 2, 3], vec![2, 1, 0]);
+    g.canonicalize();
+    assert!(g.adjwgt.is_empty());
+    assert_eq!(g.adjncy, vec![1, 2, 0, 0]);
+    assert_eq!(g.xadj, vec![0, 2, 3, 4]);
+}
+
+#[test]
+fn symmetrized_applies_reciprocal_edge_policy() {
+    // 0 -> 1 (2), 1 -> 0 (5), 1 -> 2 (3), 2 -> 2 (9)
+    let g = Graph::new(3, vec![0, 1, 3, 4], vec![1, 0, 2, 2])
+        .with_adjwgt(vec![2, 5, 3, 9])
+        .with_vwgt(vec![1, 2, 3]);
+
+    let union = g.symmetrized(Symmetrize::Union);
+    assert!(union.is_symmetric());
+    assert_eq!(union.adjncy, vec![1, 0, 2, 1]);
+    assert_eq!(union.adjwgt, vec![5, 5, 3, 3]);
+    assert_eq!(union.vwgt, g.vwgt);
+    let mut canonical = g.clone();
+    canonical.canonicalize();
+    assert_eq!(canonical.adjwgt, union.adjwgt);
+
+    let both = g.symmetrized(Symmetrize::Intersection);
+    assert_eq!(both.xadj, vec![0, 1, 2, 2]);
+    assert_eq!(both.adjwgt, vec![2, 2]);
+
+    let sum = g.symmetrized(Symmetrize::Sum);
+    assert_eq!(sum.adjncy, union.adjncy);
+    assert_eq!(sum.adjwgt, vec![7, 7, 3, 3]);
+
+    // Unit weights stay implicit unless reciprocal pairs add up
+    let path = Graph::new(3, vec![0, 1, 2, 3], vec![1, 0, 1]);
+    assert!(path.symmetrized(Symmetrize::Union).adjwgt.is_empty());
+    assert_eq!(path.symmetrized(Symmetrize::Sum).adjwgt, vec![2, 2, 1, 1]);
+
+    // Repeated and reciprocal entries saturate instead of wrapping
+    let max = i64::MAX;
+    let huge = Graph::new(2, vec![0, 2, 3], vec![1, 1, 0]).with_adjwgt(vec![max, max, 1]);
+    for mode in [Symmetrize::Union, Symmetrize::Sum] {
+        assert_eq!(huge.symmetrized(mode).adjwgt, vec![max, max]);
+    }
+}
+
+#[test]
+fn edge_cut_does_not_wrap_on_huge_weights() {
+    let triangle = |w: i64| Graph::from_edges(3, &[(0, 1, w), (1, 2, w), (2, 0, w)]);
+    let part = [0, 1, 2];
+
+    // Counting every edge twice would overflow, but the cut itself fits.
+    let w = i64::MAX / 3;
+    let g = triangle(w);
+    assert_eq!(g.try_edge_cut(&part), Ok(3 * w));
+    assert_eq!(g.edge_cut(&part), 3 * w);
+    assert_eq!(g.weighted_degree(0), 2 * w);
+
+    // A cut above i64::MAX is reported, or saturates.
+    let g = triangle(i64::MAX / 2);
+    assert_eq!(g.try_edge_cut(&part), Err(GraphError::WeightOverflow));
+    assert_eq!(g.edge_cut(&part), i64::MAX);
+    assert_eq!(g.weighted_degree(0), i64::MAX - 1);
+}
+
+#[test]
+fn coarsening_saturates_merged_weights() {
+    // Triangle where 0 and 1 merge along the heaviest edge and their edges
+    // to 2 add up.
+    let w = i64::MAX / 2 + 1;
+    let g =
+        Graph::from_edges(3, &[(0, 1, i64::MAX), (0, 2, w), (1, 2, w)]).with_vwgt(vec![w, w, 1]);
+    let level = metis_rs::coarsen::coarsen_once(&g);
+    assert_eq!(level.cmap[0], level.cmap[1]);
+    assert_eq!(level.graph.adjwgt, vec![i64::MAX, i64::MAX]);
+    assert_eq!(level.graph.vertex_weight(level.cmap[0]), i64::MAX);
+}
+
+#[test]
+fn compact_graph_round_trips_and_halves_memory() {
+    use metis_rs::CompactGraph;
+
+    let g = two_cliques()
+        .with_adjwgt((1..=14).collect())
+        .with_vwgt_multi((0..6).map(|u| vec![u, 2 * u]).collect())
+        .with_vsize(vec![3; 6]);
+    let compact = CompactGraph::try_from(&g).unwrap();
+    assert_eq!(compact.n(), 6);
+    assert_eq!(compact.neighbors(2), &[0, 1, 3]);
+    assert_eq!(compact.edge_weight(2, 2), 7);
+    let back = Graph::from(&compact);
+    assert_eq!(back.xadj, g.xadj);
+    assert_eq!(back.adjncy, g.adjncy);
+    assert_eq!(back.adjwgt, g.adjwgt);
+    assert_eq!(back.vwgt_multi, g.vwgt_multi);
+    assert_eq!(back.vsize, g.vsize);
+    let wide = 8 * (g.xadj.len() + g.adjncy.len() + g.adjwgt.len() + 6 * 2 + 6);
+    assert_eq!(2 * compact.memory_bytes(), wide);
+
+    // Unit weights stay implicit.
+    let plain = CompactGraph::try_from(&two_cliques()).unwrap();
+    assert_eq!(plain.vertex_weight(0), 1);
+    assert!(Graph::from(&plain).adjwgt.is_empty());
+
+    let heavy = two_cliques().with_vwgt(vec![1, 1, 1, 1, 1, 1 << 40]);
+    assert_eq!(
+        CompactGraph::try_from(&heavy),
+        Err(GraphError::CompactOverflow)
+    );
+}
@@ -0,0 +1,211 @@
+use metis_rs::io::{read_partition, write_partition};
+use metis_rs::{Graph, ParseError};
+
+/// Path 0-1-2.
+fn path3() -> Graph {
+    Graph::new(3, vec![0, 1, 3, 4], vec![1, 0, 2, 1])
+}
+
+#[test]
+fn unweighted_metis_string() {
+    assert_eq!(path3().to_metis_string(), "3 2\n2\n1 3\n2\n");
+}
+
+#[test]
+fn weighted_metis_string() {
+    let g = path3().with_adjwgt(vec![4, 4, 9, 9]);
+    assert_eq!(g.to_metis_string(), "3 2 1\n2 4\n1 4 3 9\n2 9\n");
+
+    let g = g.with_vwgt(vec![5, 6, 7]);
+    assert_eq!(g.to_metis_string(), "3 2 11\n5 2 4\n6 1 4 3 9\n7 2 9\n");
+
+    let g = path3().with_vwgt(vec![5, 6, 7]);
+    assert_eq!(g.to_metis_string(), "3 2 10\n5 2\n6 1 3\n7 2\n");
+}
+
+#[test]
+fn isolated_vertex_gets_empty_line() {
+    let g = Graph::new(2, vec![0, 0, 0], vec![]);
+    assert_eq!(g.to_metis_string(), "2 0\n\n\n");
+}
+
+#[test]
+fn reads_one_based_neighbors() {
+    let g = Graph::from_metis_str("% a path\n3 2\n2\n1 3\n% middle done\n2\n").unwrap();
+    assert_eq!(g.n, 3);
+    assert_eq!(g.xadj, vec![0, 1, 3, 4]);
+    assert_eq!(g.adjncy, vec![1, 0, 2, 1]);
+    assert!(g.adjwgt.is_empty() && g.vwgt.is_empty());
+}
+
+#[test]
+fn metis_round_trip_keeps_weights() {
+    let g = path3()
+        .with_adjwgt(vec![4, 4, 9, 9])
+        .with_vwgt(vec![5, 6, 7]);
+    let text = g.to_metis_string();
+    let h = Graph::from_metis_reader(text.as_bytes()).unwrap();
+    assert_eq!(h.adjncy, g.adjncy);
+    assert_eq!(h.adjwgt, g.adjwgt);
+    assert_eq!(h.vwgt, g.vwgt);
+    assert_eq!(h.to_metis_string(), text);
+
+    let g = path3().with_vwgt_multi(vec![vec![1, 2], vec![3, 4], vec![5, 6]]);
+    let text = g.to_metis_string();
+    assert_eq!(text, "3 2 10 2\n1 2 2\n3 4 1 3\n5 6 2\n");
+    assert_eq!(
+        Graph::from_metis_str(&text).unwrap().vwgt_multi,
+        g.vwgt_multi
+    );
+}
+
+#[test]
+fn reads_isolated_vertices_and_vertex_sizes() {
+    let g = Graph::from_metis_str("3 1 110\n4 7 2\n9 8 1\n1 1\n").unwrap();
+    assert_eq!(g.xadj, vec![0, 1, 2, 2]);
+    assert_eq!(g.vwgt, vec![7, 8, 1]);
+    assert_eq!(g.vsize, vec![4, 9, 1]);
+    assert_eq!(g.to_metis_string(), "3 1 110\n4 7 2\n9 8 1\n1 1\n");
+}
+
+#[test]
+fn rejects_edge_count_mismatch() {
+    let err = Graph::from_metis_str("3 3\n2\n1 3\n2\n").unwrap_err();
+    assert!(matches!(
+        err,
+        ParseError::EdgeCount {
+            expected: 3,
+            actual: 2
+        }
+    ));
+}
+
+#[test]
+fn rejects_zero_based_neighbor() {
+    let err = Graph::from_metis_str("2 1\n0\n1\n").unwrap_err();
+    assert!(matches!(
+        err,
+        ParseError::NeighborOutOfRange {
+            line: 2,
+            neighbor: 0
+        }
+    ));
+}
+
+#[test]
+fn rejects_truncated_files() {
+    assert!(matches!(
+        Graph::from_metis_str("% only a comment\n"),
+        Err(ParseError::MissingHeader)
+    ));
+    assert!(matches!(
+        Graph::from_metis_str("3 1\n2\n1\n"),
+        Err(ParseError::VertexCount {
+            expected: 3,
+            actual: 2
+        })
+    ));
+    assert!(matches!(
+        Graph::from_metis_str("2 1 1\n2\n1 1\n"),
+        Err(ParseError::MissingField { line: 2 })
+    ));
+}
+
+#[test]
+fn huge_header_counts_are_errors() {
+    assert!(matches!(
+        Graph::from_metis_str("99999999999999999 1\n2\n1\n"),
+        Err(ParseError::VertexCount { actual: 2, .. })
+    ));
+    assert!(matches!(
+        Graph::from_metis_str("2 99999999999999999\n2\n1\n"),
+        Err(ParseError::EdgeCount { actual: 1, .. })
+    ));
+    let header = format!("{} {}\n", usize::MAX, usize::MAX / 2 + 1);
+    assert!(matches!(
+        Graph::from_metis_str(&header),
+        Err(ParseError::InvalidHeader { line: 1 })
+    ));
+}
+
+#[test]
+fn partition_file_round_trip() {
+    let part = vec![1, 0, 3, 3, 2];
+    let mut buf = Vec::new();
+    write_partition(&part, &mut buf).unwrap();
+    assert_eq!(String::from_utf8(buf.clone()).unwrap(), "1\n0\n3\n3\n2\n");
+    assert_eq!(read_partition(&buf[..]).unwrap(), part);
+
+    let read = read_partition("% parts\n0\n\n 2 \n".as_bytes()).unwrap();
+    assert_eq!(read, vec![0, 2]);
+    assert!(matches!(
+        read_partition("0\n1 2\n".as_bytes()),
+        Err(ParseError::InvalidNumber { line: 2, .. })
+    ));
+}
+
+#[test]
+fn matrix_market_is_symmetrized_without_diagonal() {
+    let mtx = "%%MatrixMarket matrix coordinate real general
+% 3x3, with a diagonal and one entry stored both ways
+3 3 5
+1 1 4.0
+2 1 -1.5
+1 2 -1.5
+3 2 2.6
+3 3 4.0
+";
+    let g = Graph::from_matrix_market(mtx.as_bytes(), None).unwrap();
+    assert!(g.check().is_ok());
+    assert_eq!(g.xadj, vec![0, 1, 3, 4]);
+    assert_eq!(g.adjncy, vec![1, 0, 2, 1]);
+    assert!(g.adjwgt.is_empty());
+
+    let g = Graph::from_matrix_market(mtx.as_bytes(), Some(2.0)).unwrap();
+    assert_eq!(g.adjwgt, vec![3, 3, 5, 5]);
+
+    let lower = "%%MatrixMarket matrix coordinate pattern symmetric\n3 3 2\n2 1\n3 1\n";
+    let g = Graph::from_matrix_market(lower.as_bytes(), Some(10.0)).unwrap();
+    assert_eq!(g.adjncy, vec![1, 2, 0, 0]);
+    assert_eq!(g.adjwgt, vec![1, 1, 1, 1]);
+}
+
+#[test]
+fn matrix_market_rejects_unsupported_or_malformed_input() {
+    let read = |s: &str| Graph::from_matrix_market(s.as_bytes(), None);
+    assert!(matches!(
+        read("%%MatrixMarket matrix coordinate complex general\n1 1 0\n"),
+        Err(ParseError::InvalidHeader { line: 1 })
+    ));
+    assert!(matches!(
+        read("%%MatrixMarket matrix coordinate real general\n2 3 0\n"),
+        Err(ParseError::InvalidHeader { line: 2 })
+    ));
+    assert!(matches!(
+        read("%%MatrixMarket matrix coordinate real general\n2 2 1\n1 3 1.0\n"),
+        Err(ParseError::NeighborOutOfRange {
+            line: 3,
+            neighbor: 3
+        })
+    ));
+    assert!(matches!(
+        read("%%MatrixMarket matrix coordinate real general\n2 2 2\n1 2 1.0\n"),
+        Err(ParseError::EdgeCount {
+            expected: 2,
+            actual: 1
+        })
+    ));
+    // A huge declared entry count is an error, not an allocation
+    assert!(matches!(
+        read("%%MatrixMarket matrix coordinate real general\n3 3 99999999999999999\n1 2 1\n"),
+        Err(ParseError::EdgeCount { actual: 1, .. })
+    ));
+    let header = format!("{} {} 0", usize::MAX, usize::MAX);
+    assert!(matches!(
+        read(&format!(
+            "%%MatrixMarket matrix coordinate real general\n{}\n",
+            header
+        )),
+        Err(ParseError::InvalidHeader { line: 2 })
+    ));
+}
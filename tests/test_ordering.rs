@@ -0,0 +1,121 @@
+mod common;
+
+use common::{grid, path};
+use metis_rs::ordering::{
+    nested_dissection, nested_dissection_tree, vertex_separator, SeparatorTree,
+};
+use metis_rs::Graph;
+
+/// All vertices in the subtree rooted at `node`, in elimination order.
+fn subtree_vertices(tree: &SeparatorTree, node: usize) -> Vec<usize> {
+    let n = &tree.nodes[node];
+    let mut out = Vec::new();
+    for child in [n.left, n.right].into_iter().flatten() {
+        out.extend(subtree_vertices(tree, child));
+    }
+    out.extend_from_slice(&n.vertices);
+    out
+}
+
+#[test]
+fn path_separator_tree_is_binary() {
+    let g = path(15);
+    let (perm, tree) = nested_dissection_tree(&g);
+
+    let mut sorted = perm.clone();
+    sorted.sort_unstable();
+    assert_eq!(sorted, (0..15).collect::<Vec<_>>());
+
+    // The permutation is the post-order traversal of the tree.
+    let root = tree.root.unwrap();
+    assert_eq!(subtree_vertices(&tree, root), perm);
+
+    for node in &tree.nodes {
+        if node.is_leaf() {
+            continue;
+        }
+        // On a path every separator is a single vertex with two subdomains.
+        assert_eq!(node.vertices.len(), 1);
+        let left = subtree_vertices(&tree, node.left.unwrap());
+        let right = subtree_vertices(&tree, node.right.unwrap());
+        for &u in &left {
+            for &v in g.neighbors(u) {
+                assert!(!right.contains(&v), "edge {}-{} crosses separator", u, v);
+            }
+        }
+    }
+    assert!(tree.nodes.iter().filter(|n| n.is_leaf()).count() >= 4);
+}
+
+#[test]
+fn grid_ordering_is_a_permutation_with_separators_last() {
+    let g = grid(12, 12);
+    let perm = nested_dissection(&g);
+    let mut sorted = perm.clone();
+    sorted.sort_unstable();
+    assert_eq!(sorted, (0..g.n).collect::<Vec<_>>());
+
+    // The top-level separator is numbered last and splits the rest.
+    let (tree_perm, tree) = nested_dissection_tree(&g);
+    assert_eq!(tree_perm, perm);
+    let root = &tree.nodes[tree.root.unwrap()];
+    assert!(!root.is_leaf());
+    assert!(perm.ends_with(&root.vertices));
+    assert!(
+        root.vertices.len() <= 24,
+        "separator too large: {}",
+        root.vertices.len()
+    );
+    let left = subtree_vertices(&tree, root.left.unwrap());
+    let right = subtree_vertices(&tree, root.right.unwrap());
+    for &u in &left {
+        for &v in g.neighbors(u) {
+            assert!(!right.contains(&v), "edge {}-{} crosses separator", u, v);
+        }
+    }
+}
+
+/// Check that `(left, right, sep)` covers every vertex once and that no edge
+/// joins `left` to `right`.
+fn assert_valid_separator(g: &Graph, left: &[usize], right: &[usize], sep: &[usize]) {
+    let mut side = vec![None; g.n];
+    for (s, set) in [left, right, sep].into_iter().enumerate() {
+        for &u in set {
+            assert!(side[u].is_none(), "vertex {} in two sets", u);
+            side[u] = Some(s);
+        }
+    }
+    assert!(side.iter().all(Option::is_some), "vertex missing");
+    for &u in left {
+        for &v in g.neighbors(u) {
+            assert_ne!(side[v], Some(1), "edge {}-{} joins left and right", u, v);
+        }
+    }
+}
+
+#[test]
+fn star_center_is_the_separator() {
+    let edges: Vec<(usize, usize, i64)> = (1..=8).map(|v| (0, v, 1)).collect();
+    let g = Graph::from_edges(9, &edges);
+    let (left, right, sep) = vertex_separator(&g);
+    assert_valid_separator(&g, &left, &right, &sep);
+    assert_eq!(sep, vec![0]);
+    assert!(!left.is_empty() && !right.is_empty());
+}
+
+#[test]
+fn grid_separator_is_valid_and_small() {
+    for (rows, cols) in [(8, 8), (10, 15), (1, 9)] {
+        let g = grid(rows, cols);
+        let (left, right, sep) = vertex_separator(&g);
+        assert_valid_separator(&g, &left, &right, &sep);
+        assert!(
+            sep.len() <= rows.min(cols) + 1,
+            "{}x{} separator has {} vertices",
+            rows,
+            cols,
+            sep.len()
+        );
+        assert!(!left.is_empty() && !right.is_empty());
+    }
+}
@@ -0,0 +1,127 @@
+//! The `rayon` feature must not change results. With it, every result here
+//! is computed on one thread and on four and the two must match; in both
+//! builds the results are checked against balance and cut bounds.
+
+mod common;
+
+use std::fmt::Debug;
+
+use metis_rs::coarsen::coarsen_once;
+use metis_rs::partition::initial_partition;
+use metis_rs::{partition, partition_best_of, Graph, PartitionConfig, RefineMode};
+
+/// 40x40 grid with pseudo-random edge weights in 1..=5.
+fn weighted_grid() -> Graph {
+    common::weighted_grid(40, 40, 5, 12345)
+}
+
+/// Run `f` on a pool of `threads` threads, or directly without `rayon`.
+fn on_threads<T: Send>(threads: usize, f: impl FnOnce() -> T + Send) -> T {
+    #[cfg(feature = "rayon")]
+    {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap()
+            .install(f)
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        let _ = threads;
+        f()
+    }
+}
+
+/// Result of `f` on one thread, checked to be the same on four.
+fn same_on_any_threads<T: Send + PartialEq + Debug>(f: impl Fn() -> T + Sync) -> T {
+    let serial = on_threads(1, &f);
+    assert_eq!(on_threads(4, &f), serial);
+    serial
+}
+
+/// Horizontal strips of a `rows x cols` grid, one per part: a balanced
+/// partition that the partitioner should never cut worse than.
+fn strips(rows: usize, cols: usize, nparts: usize) -> Vec<usize> {
+    (0..rows * cols).map(|u| u / cols * nparts / rows).collect()
+}
+
+/// Check that `part` is an `nparts`-way partition of `g` within 5% of
+/// balance, cutting no more than the strips of a `rows x cols` grid.
+fn assert_balanced_below_strips(g: &Graph, part: &[usize], nparts: usize, rows: usize) {
+    assert_eq!(part.len(), g.n);
+    assert!(part.iter().all(|&p| p < nparts));
+    let bound = (g.n as f64 * 1.05 / nparts as f64).ceil() as i64;
+    let weights = common::part_weights(g, part, nparts);
+    assert!(weights.iter().all(|&w| w <= bound), "{:?}", weights);
+    let strip_cut = g.edge_cut(&strips(rows, g.n / rows, nparts));
+    assert!(
+        g.edge_cut(part) <= strip_cut,
+        "cut {} vs {}",
+        g.edge_cut(part),
+        strip_cut
+    );
+}
+
+#[test]
+fn partition_is_independent_of_rayon_feature() {
+    for (rows, cols, nparts) in [(30, 30, 4), (17, 23, 7)] {
+        let g = common::grid(rows, cols);
+        let (cut, part) = same_on_any_threads(|| partition(&g, nparts));
+        assert_eq!(cut, g.edge_cut(&part));
+        assert_balanced_below_strips(&g, &part, nparts, rows);
+    }
+}
+
+#[test]
+fn matching_is_independent_of_rayon_feature() {
+    let g = weighted_grid();
+    let cmap = same_on_any_threads(|| coarsen_once(&g).cmap);
+    let level = coarsen_once(&g);
+    assert_eq!(level.cmap, cmap);
+    assert_eq!(level.graph.n, level.nc);
+    // Heavy-edge matching pairs up most grid vertices and keeps weight.
+    assert!(level.nc <= g.n * 3 / 5, "{} coarse vertices", level.nc);
+    let coarse_weight: i64 = (0..level.nc).map(|u| level.graph.vertex_weight(u)).sum();
+    assert_eq!(coarse_weight, g.n as i64);
+    let fine_edges: i64 = g.adjwgt.iter().sum();
+    assert!(level.graph.adjwgt.iter().sum::<i64>() < fine_edges);
+}
+
+#[test]
+fn colored_refinement_is_independent_of_rayon_feature() {
+    let g = weighted_grid();
+    let config = PartitionConfig::default()
+        .nparts(8)
+        .refine_mode(RefineMode::Colored);
+    let result = same_on_any_threads(|| config.partition(&g));
+    assert_balanced_below_strips(&g, &result.part, 8, 40);
+}
+
+#[test]
+fn recursive_bisection_is_independent_of_rayon_feature() {
+    let g = weighted_grid();
+    let part = same_on_any_threads(|| initial_partition(&g, 8));
+    assert_balanced_below_strips(&g, &part, 8, 40);
+}
+
+#[test]
+fn best_of_is_independent_of_rayon_feature() {
+    let g = weighted_grid();
+    let (cut, part, trial) = same_on_any_threads(|| partition_best_of(&g, 4, 6, 0));
+    assert!(trial < 6);
+    assert_eq!(cut, g.edge_cut(&part));
+    assert_balanced_below_strips(&g, &part, 4, 40);
+    // The best trial is no worse than the first, which uses seed 0.
+    let (first, _) = metis_rs::partition_seeded(&g, 4, 0);
+    assert!(cut <= first);
+}
+
+#[test]
+fn edge_cut_is_independent_of_rayon_feature() {
+    // 10000 vertices span several blocks of the parallel sum; every grid
+    // edge joins vertices in different residues mod 7.
+    let g = common::grid(100, 100);
+    let part: Vec<usize> = (0..g.n).map(|u| u % 7).collect();
+    assert_eq!(same_on_any_threads(|| g.edge_cut(&part)), 2 * 99 * 100);
+    assert_eq!(g.edge_cut(&vec![0; g.n]), 0);
+}
@@ -1,4 +1,6 @@
-use metis_rs::{Graph, partition};
+mod common;
+
+use metis_rs::{partition, Graph};
 
 /// Helper: verify that partition is valid (every vertex assigned to 0..nparts).
 fn assert_valid_partition(part: &[usize], n: usize, nparts: usize) {
@@ -9,11 +11,7 @@ fn assert_valid_partition(part: &[usize], n: usize, nparts: usize) {
     // Each part should have at least one vertex (when n >= nparts)
     if n >= nparts {
         for k in 0..nparts {
-            assert!(
-                part.iter().any(|&p| p == k),
-                "part {} is empty",
-                k
-            );
+            assert!(part.contains(&k), "part {} is empty", k);
         }
     }
 }
@@ -113,19 +111,23 @@ fn two_cliques_connected_by_bridge() {
     // Bridge: 2-3
     let xadj = vec![0, 2, 4, 7, 10, 12, 14];
     let adjncy = vec![
-        1, 2,    // vertex 0: -> 1, 2
-        0, 2,    // vertex 1: -> 0, 2
+        1, 2, // vertex 0: -> 1, 2
+        0, 2, // vertex 1: -> 0, 2
         0, 1, 3, // vertex 2: -> 0, 1, 3
         2, 4, 5, // vertex 3: -> 2, 4, 5
-        3, 5,    // vertex 4: -> 3, 5
-        3, 4,    // vertex 5: -> 3, 4
+        3, 5, // vertex 4: -> 3, 5
+        3, 4, // vertex 5: -> 3, 4
     ];
     let g = Graph::new(6, xadj, adjncy);
 
     let (cut, part) = partition(&g, 2);
     assert_valid_partition(&part, 6, 2);
     // Optimal: cut the bridge edge -> cut = 1
-    assert_eq!(cut, 1, "two cliques with bridge should have cut = 1, got {}", cut);
+    assert_eq!(
+        cut, 1,
+        "two cliques with bridge should have cut = 1, got {}",
+        cut
+    );
     // Vertices 0,1,2 should be in one part, 3,4,5 in another
     assert_eq!(part[0], part[1]);
     assert_eq!(part[1], part[2]);
@@ -170,13 +172,13 @@ fn grid_4x4_into_4_parts() {
     assert_valid_partition(&part, n, 4);
 
     // Check balance: each part should have ~4 vertices
-    let mut counts = vec![0usize; 4];
+    let mut counts = [0usize; 4];
     for &p in &part {
         counts[p] += 1;
     }
     for (k, &c) in counts.iter().enumerate() {
         assert!(
-            c >= 2 && c <= 6,
+            (2..=6).contains(&c),
             "part {} has {} vertices, expected ~4",
             k,
             c
@@ -205,11 +207,7 @@ fn weighted_vertices() {
 fn weighted_edges() {
     // Triangle: 0-1 (weight 100), 1-2 (weight 1), 0-2 (weight 1)
     // Optimal 2-way: cut the two weak edges, keep the heavy edge intact
-    let mut g = Graph::new(
-        3,
-        vec![0, 2, 4, 6],
-        vec![1, 2, 0, 2, 0, 1],
-    );
+    let mut g = Graph::new(3, vec![0, 2, 4, 6], vec![1, 2, 0, 2, 0, 1]);
     g.adjwgt = vec![100, 1, 100, 1, 1, 1];
 
     let (cut, part) = partition(&g, 2);
@@ -227,7 +225,7 @@ fn nparts_equals_n() {
     let (_cut, part) = partition(&g, 3);
     assert_eq!(part.len(), 3);
     // Each vertex should be in a unique part
-    let mut seen = vec![false; 3];
+    let mut seen = [false; 3];
     for &p in &part {
         assert!(p < 3);
         seen[p] = true;
@@ -246,15 +244,15 @@ fn nparts_one() {
 #[test]
 fn disconnected_graph() {
     // Two disconnected edges: 0-1, 2-3
-    let g = Graph::new(
-        4,
-        vec![0, 1, 2, 3, 4],
-        vec![1, 0, 3, 2],
-    );
+    let g = Graph::new(4, vec![0, 1, 2, 3, 4], vec![1, 0, 3, 2]);
     let (cut, part) = partition(&g, 2);
     assert_valid_partition(&part, 4, 2);
     // Optimal: put each connected component in a different part -> cut = 0
-    assert_eq!(cut, 0, "disconnected components should give cut = 0, got {}", cut);
+    assert_eq!(
+        cut, 0,
+        "disconnected components should give cut = 0, got {}",
+        cut
+    );
 }
 
 #[test]
@@ -264,11 +262,11 @@ fn star_graph() {
     let xadj = vec![0, 5, 6, 7, 8, 9, 10];
     let adjncy = vec![
         1, 2, 3, 4, 5, // vertex 0
-        0,             // vertex 1
-        0,             // vertex 2
-        0,             // vertex 3
-        0,             // vertex 4
-        0,             // vertex 5
+        0, // vertex 1
+        0, // vertex 2
+        0, // vertex 3
+        0, // vertex 4
+        0, // vertex 5
     ];
     let g = Graph::new(n, xadj, adjncy);
     let (cut, part) = partition(&g, 2);
@@ -276,3 +274,1054 @@ fn star_graph() {
     // Center must be in one part, so at least some leaves cross
     assert!(cut >= 1);
 }
+
+#[test]
+fn timed_partition_matches_untimed() {
+    let g = Graph::new(
+        6,
+        vec![0, 2, 4, 7, 10, 12, 14],
+        vec![1, 2, 0, 2, 0, 1, 3, 2, 4, 5, 3, 5, 3, 4],
+    );
+    let (cut, part) = partition(&g, 2);
+    let (timed_cut, timed_part, timings) = metis_rs::part_kway_timed(&g, 2);
+    assert_eq!(cut, timed_cut);
+    assert_eq!(part, timed_part);
+    assert!(timings.coarsen + timings.initial_partition + timings.refine <= timings.total);
+}
+
+#[test]
+fn target_part_weights_are_honored() {
+    let g = common::grid(12, 12);
+    let tpwgts = [0.5, 0.25, 0.25];
+    let (cut, part) = metis_rs::part_kway_tpwgts(&g, 3, &tpwgts);
+    assert_valid_partition(&part, g.n, 3);
+    assert_eq!(cut, g.edge_cut(&part));
+
+    let weights = common::part_weights(&g, &part, 3);
+    for (k, &w) in weights.iter().enumerate() {
+        let target = tpwgts[k] * g.n as f64;
+        assert!(
+            (w as f64 - target).abs() <= 0.2 * target,
+            "part {} has weight {}, target {}",
+            k,
+            w,
+            target
+        );
+    }
+}
+
+#[test]
+#[should_panic(expected = "tpwgts must have nparts entries")]
+fn target_part_weights_length_is_checked() {
+    let g = common::grid(4, 4);
+    metis_rs::part_kway_tpwgts(&g, 3, &[0.5, 0.5]);
+}
+
+#[test]
+fn seeded_partition_is_reproducible() {
+    let g = common::grid(16, 16);
+    let a = metis_rs::partition_seeded(&g, 4, 7);
+    let b = metis_rs::partition_seeded(&g, 4, 7);
+    assert_eq!(a, b);
+    assert_valid_partition(&a.1, g.n, 4);
+
+    // Different seeds explore different matchings.
+    let distinct = (0..8u64)
+        .map(|seed| metis_rs::partition_seeded(&g, 4, seed).1)
+        .filter(|part| *part != a.1)
+        .count();
+    assert!(distinct > 0, "all seeds produced the same partition");
+}
+
+#[test]
+fn best_of_keeps_lowest_cut() {
+    let g = common::grid(16, 16);
+
+    let (cut, part, trial) = metis_rs::partition_best_of(&g, 4, 1, 0);
+    assert_eq!(trial, 0);
+    assert_eq!((cut, part), metis_rs::partition_seeded(&g, 4, 0));
+
+    let (cut, part, trial) = metis_rs::partition_best_of(&g, 4, 6, 0);
+    assert!(trial < 6);
+    assert_valid_partition(&part, g.n, 4);
+    assert_eq!((cut, part), metis_rs::partition_seeded(&g, 4, trial as u64));
+    for seed in 0..6 {
+        assert!(cut <= metis_rs::partition_seeded(&g, 4, seed).0);
+    }
+}
+
+#[test]
+fn best_of_seed_selects_the_trials() {
+    let g = common::grid(16, 16);
+
+    let (cut, part, trial) = metis_rs::partition_best_of(&g, 4, 3, 10);
+    assert!(trial < 3);
+    assert_eq!(
+        (cut, part.clone()),
+        metis_rs::partition_seeded(&g, 4, 10 + trial as u64)
+    );
+    assert_eq!(
+        metis_rs::partition_best_of(&g, 4, 3, 10),
+        (cut, part, trial)
+    );
+
+    // Single trials with different seeds differ for some seed
+    let single = |seed| metis_rs::partition_best_of(&g, 4, 1, seed).1;
+    assert!((1..8).any(|seed| single(seed) != single(0)));
+}
+
+#[test]
+fn components_are_packed_whole() {
+    // Eight disjoint triangles.
+    let mut edges = Vec::new();
+    for t in 0..8 {
+        let b = 3 * t;
+        edges.extend([(b, b + 1, 1), (b + 1, b + 2, 1), (b, b + 2, 1)]);
+    }
+    let g = Graph::from_edges(24, &edges);
+    let (cut, part) = metis_rs::part_kway_components(&g, 4);
+    assert_valid_partition(&part, 24, 4);
+    assert_eq!(cut, 0);
+    assert_eq!(common::part_weights(&g, &part, 4), vec![6, 6, 6, 6]);
+}
+
+#[test]
+fn large_component_is_split_and_small_ones_packed() {
+    // A 6x6 grid plus four isolated edges.
+    let grid = common::grid(6, 6);
+    let mut edges = Vec::new();
+    for u in 0..grid.n {
+        for &v in grid.neighbors(u) {
+            if u < v {
+                edges.push((u, v, 1));
+            }
+        }
+    }
+    for e in 0..4 {
+        edges.push((36 + 2 * e, 37 + 2 * e, 1));
+    }
+    let g = Graph::from_edges(44, &edges);
+    let (cut, part) = metis_rs::part_kway_components(&g, 4);
+    assert_valid_partition(&part, 44, 4);
+    assert_eq!(cut, g.edge_cut(&part));
+    for e in 0..4 {
+        assert_eq!(part[36 + 2 * e], part[37 + 2 * e]);
+    }
+}
+
+#[test]
+fn single_component_matches_part_kway() {
+    let g = common::grid(10, 10);
+    assert_eq!(metis_rs::part_kway_components(&g, 4), partition(&g, 4));
+}
+
+#[test]
+fn multi_constraint_balances_every_constraint() {
+    // Constraint 0 is uniform; constraint 1 is concentrated in the top rows.
+    let g = common::grid(12, 12);
+    let vwgt_multi: Vec<Vec<i64>> = (0..g.n)
+        .map(|u| vec![1, if u < 48 { 4 } else { 1 }])
+        .collect();
+    let g = g.with_vwgt_multi(vwgt_multi);
+    assert_eq!(g.ncon(), 2);
+
+    let (cut, part) = partition(&g, 2);
+    assert_valid_partition(&part, g.n, 2);
+    assert_eq!(cut, g.edge_cut(&part));
+    for c in 0..2 {
+        let mut weights = [0i64; 2];
+        for u in 0..g.n {
+            weights[part[u]] += g.constraint_weight(u, c);
+        }
+        let total = (weights[0] + weights[1]) as f64;
+        for w in weights {
+            assert!(
+                (w as f64) <= 0.6 * total,
+                "constraint {} unbalanced: {:?}",
+                c,
+                weights
+            );
+        }
+    }
+}
+
+fn two_cliques() -> Graph {
+    let xadj = vec![0, 2, 4, 7, 10, 12, 14];
+    let adjncy = vec![1, 2, 0, 2, 0, 1, 3, 2, 4, 5, 3, 5, 3, 4];
+    Graph::new(6, xadj, adjncy)
+}
+
+#[test]
+fn fixed_vertices_stay_put() {
+    let g = two_cliques();
+    let mut fixed = vec![None; 6];
+    fixed[0] = Some(0);
+    fixed[5] = Some(1);
+    let (cut, part) = metis_rs::part_kway_fixed(&g, 2, &fixed);
+    assert_valid_partition(&part, 6, 2);
+    assert_eq!((part[0], part[5]), (0, 1));
+    assert_eq!(cut, 1);
+
+    // Pins against the natural labelling are honored too
+    fixed[0] = Some(1);
+    fixed[5] = Some(0);
+    let (cut, part) = metis_rs::part_kway_fixed(&g, 2, &fixed);
+    assert_eq!((part[0], part[5]), (1, 0));
+    assert_eq!(cut, 1);
+}
+
+#[test]
+fn fixed_vertices_survive_coarsening() {
+    let g = common::grid(16, 16);
+    let mut fixed = vec![None; g.n];
+    for u in (0..g.n).step_by(37) {
+        fixed[u] = Some(u % 4);
+    }
+    let (cut, part) = metis_rs::part_kway_fixed(&g, 4, &fixed);
+    assert_valid_partition(&part, g.n, 4);
+    assert_eq!(cut, g.edge_cut(&part));
+    for u in 0..g.n {
+        if let Some(k) = fixed[u] {
+            assert_eq!(part[u], k, "vertex {} left its pinned part", u);
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "fixed part out of range")]
+fn fixed_part_is_checked() {
+    let g = two_cliques();
+    let mut fixed = vec![None; 6];
+    fixed[0] = Some(2);
+    metis_rs::part_kway_fixed(&g, 2, &fixed);
+}
+
+#[test]
+fn volume_objective_lowers_comm_volume() {
+    let g = common::grid(20, 20);
+    let (_, by_cut) = metis_rs::part_kway_objective(&g, 4, metis_rs::Objective::EdgeCut);
+    assert_eq!(by_cut, partition(&g, 4).1);
+    let (cut, by_volume) = metis_rs::part_kway_objective(&g, 4, metis_rs::Objective::CommVolume);
+    assert_valid_partition(&by_volume, g.n, 4);
+    assert_eq!(cut, g.edge_cut(&by_volume));
+    assert!(
+        g.partition_stats(&by_volume, 4).comm_volume <= g.partition_stats(&by_cut, 4).comm_volume
+    );
+}
+
+#[test]
+fn default_config_matches_partition() {
+    let g = common::grid(12, 12);
+    let result = metis_rs::PartitionConfig::default().nparts(4).partition(&g);
+    assert_eq!((result.edge_cut, result.part.clone()), partition(&g, 4));
+    assert_eq!(result.stats, g.partition_stats(&result.part, 4));
+}
+
+#[test]
+fn config_knobs_reach_the_pipeline() {
+    use metis_rs::{PartitionConfig, RefineMode};
+
+    let g = common::grid(16, 16);
+    let config = PartitionConfig::default().nparts(4).ntrials(3);
+    assert_eq!(
+        config.partition(&g).part,
+        metis_rs::part_kway_best_of(&g, 4, 3, 0).1
+    );
+    assert_eq!(
+        PartitionConfig::default()
+            .nparts(4)
+            .seed(7)
+            .partition(&g)
+            .part,
+        metis_rs::part_kway_seeded(&g, 4, 7).1
+    );
+
+    // A looser imbalance can never be violated by more than it allows.
+    let loose = PartitionConfig::default()
+        .nparts(4)
+        .imbalance(1.3)
+        .partition(&g);
+    assert!(
+        loose.stats.imbalance <= 1.3,
+        "imbalance {}",
+        loose.stats.imbalance
+    );
+
+    // No refinement at all is a different (and no better) partition.
+    let unrefined = PartitionConfig::default()
+        .nparts(4)
+        .coarsen_threshold(64)
+        .refine_passes(0)
+        .partition(&g);
+    let refined = PartitionConfig::default()
+        .nparts(4)
+        .coarsen_threshold(64)
+        .refine_mode(RefineMode::Rollback)
+        .partition(&g);
+    assert_valid_partition(&unrefined.part, g.n, 4);
+    assert!(refined.edge_cut <= unrefined.edge_cut);
+}
+
+#[test]
+#[should_panic(expected = "imbalance must be at least 1.0")]
+fn config_rejects_imbalance_below_one() {
+    metis_rs::PartitionConfig::default()
+        .imbalance(0.9)
+        .partition(&common::grid(4, 4));
+}
+
+fn complete(n: usize) -> Graph {
+    let mut edges = Vec::new();
+    for u in 0..n {
+        for v in u + 1..n {
+            edges.push((u, v, 1));
+        }
+    }
+    Graph::from_edges(n, &edges)
+}
+
+#[test]
+fn clique_parts_are_never_empty() {
+    let (_, part) = partition(&complete(8), 4);
+    assert_valid_partition(&part, 8, 4);
+
+    for (n, nparts) in [(5, 4), (9, 7), (30, 5), (30, 7)] {
+        let (_, part) = partition(&complete(n), nparts);
+        assert_valid_partition(&part, n, nparts);
+    }
+}
+
+/// Columns of a 12x12 grid split into 4 stripes, with a few vertices of
+/// each stripe boundary pushed into the wrong stripe. Those in the bottom
+/// half weigh 3, the rest 1.
+fn perturbed_stripes() -> (Graph, Vec<usize>) {
+    let mut vwgt = vec![1; 144];
+    let mut part: Vec<usize> = (0..144).map(|u| (u % 12) / 3).collect();
+    for r in [1, 4, 7, 10] {
+        part[r * 12 + 3] = 0;
+        part[r * 12 + 8] = 3;
+        if r > 5 {
+            vwgt[r * 12 + 3] = 3;
+            vwgt[r * 12 + 8] = 3;
+        }
+    }
+    (common::grid(12, 12).with_vwgt(vwgt), part)
+}
+
+#[test]
+fn repartition_without_penalty_is_plain_refinement() {
+    let (g, initial) = perturbed_stripes();
+    let mut expected = initial.clone();
+    metis_rs::refine::fm_refine(&g, &mut expected, 4, 10);
+
+    let (cut, part) = metis_rs::repartition(&g, 4, &initial, 0.0);
+    assert_eq!(part, expected);
+    assert_eq!(cut, g.edge_cut(&part));
+    assert!(cut < g.edge_cut(&initial));
+}
+
+#[test]
+fn repartition_penalty_limits_migration() {
+    let (g, initial) = perturbed_stripes();
+    let migrated = |part: &[usize]| (0..g.n).filter(|&u| part[u] != initial[u]).count();
+
+    let (_, free) = metis_rs::repartition(&g, 4, &initial, 0.0);
+    // Moving a stray back saves 2 cut edges: worth a penalty of 1 for the
+    // light strays but not 3 for the heavy ones.
+    let (_, cautious) = metis_rs::repartition(&g, 4, &initial, 1.0);
+    let (_, frozen) = metis_rs::repartition(&g, 4, &initial, 100.0);
+    assert!(migrated(&cautious) < migrated(&free));
+    assert!(g.edge_cut(&free) < g.edge_cut(&cautious));
+    assert!(g.edge_cut(&cautious) < g.edge_cut(&initial));
+    assert_eq!(frozen, initial);
+}
+
+#[test]
+fn greedy_kway_initial_partition_on_grid() {
+    use metis_rs::partition::{initial_partition, initial_partition_greedy};
+    use metis_rs::{InitialMethod, PartitionConfig};
+
+    let g = common::grid(4, 4);
+    let direct = initial_partition_greedy(&g, 4);
+    let recursive = initial_partition(&g, 4);
+    assert_valid_partition(&direct, g.n, 4);
+    assert!(g.edge_cut(&direct) <= g.edge_cut(&recursive));
+    // Seeds land in the four corners and grow into 2x2 quadrants.
+    assert_eq!(g.edge_cut(&direct), 8);
+    assert_eq!(common::part_weights(&g, &direct, 4), vec![4, 4, 4, 4]);
+
+    let config = PartitionConfig::default().nparts(4);
+    let greedy = config.clone().initial_method(InitialMethod::GreedyKway);
+    let (a, b) = (greedy.partition(&g), config.partition(&g));
+    assert_valid_partition(&a.part, g.n, 4);
+    assert!(a.edge_cut <= b.edge_cut);
+}
+
+#[test]
+fn greedy_kway_places_unreachable_vertices() {
+    use metis_rs::partition::initial_partition_greedy;
+
+    // Two disjoint triangles and an isolated vertex, into 2 parts.
+    let g = Graph::from_edges(
+        7,
+        &[
+            (0, 1, 1),
+            (1, 2, 1),
+            (2, 0, 1),
+            (3, 4, 1),
+            (4, 5, 1),
+            (5, 3, 1),
+        ],
+    );
+    let part = initial_partition_greedy(&g, 2);
+    assert_valid_partition(&part, g.n, 2);
+    assert_eq!(g.edge_cut(&part), 0);
+}
+
+#[test]
+fn min_part_weight_keeps_every_part_populated() {
+    use metis_rs::{PartitionConfig, RefineMode};
+
+    let g = common::path(20);
+    let result = PartitionConfig::default()
+        .nparts(4)
+        .min_part_weight(3)
+        .partition(&g);
+    assert_valid_partition(&result.part, g.n, 4);
+    assert!(result.stats.part_weights.iter().all(|&w| w >= 3));
+
+    // K10 with a two-vertex tail 9-10-11: with a loose imbalance the
+    // cheapest bisection cuts off the tail alone.
+    let mut edges: Vec<(usize, usize, i64)> = Vec::new();
+    for u in 0..10 {
+        for v in u + 1..10 {
+            edges.push((u, v, 1));
+        }
+    }
+    edges.extend([(9, 10, 1), (10, 11, 1)]);
+    let g = Graph::from_edges(12, &edges);
+    let config = PartitionConfig::default()
+        .imbalance(1.9)
+        .refine_mode(RefineMode::Rollback);
+    let loose = config.clone().partition(&g);
+    let floored = config.min_part_weight(4).partition(&g);
+    assert_eq!(loose.stats.part_weights, vec![10, 2]);
+    assert!(floored.stats.part_weights.iter().all(|&w| w >= 4));
+}
+
+#[test]
+#[should_panic(expected = "min_part_weight exceeds the average part weight")]
+fn min_part_weight_must_be_feasible() {
+    let g = common::path(10);
+    metis_rs::PartitionConfig::default()
+        .nparts(4)
+        .min_part_weight(3)
+        .partition(&g);
+}
+
+#[test]
+fn observed_kway_reports_levels_down_to_the_input() {
+    let g = common::grid(20, 20);
+    let mut reports: Vec<(usize, usize, i64, usize)> = Vec::new();
+    let (cut, part) = metis_rs::part_kway_observed(&g, 4, &mut |level, pass, cut, moves| {
+        reports.push((level, pass, cut, moves))
+    });
+    assert_eq!((cut, part), metis_rs::part_kway(&g, 4));
+
+    // Levels count down from the coarsest to the input graph.
+    assert!(reports[0].0 > 0);
+    assert!(reports.windows(2).all(|w| w[1].0 <= w[0].0));
+    let last = reports.last().unwrap();
+    assert_eq!(last.0, 0);
+    assert_eq!(last.2, cut);
+}
+
+#[test]
+fn partition_with_options_applies_every_knob() {
+    use metis_rs::{partition_with_options, PartitionOptions};
+
+    let g = common::grid(16, 16);
+    let defaults = partition_with_options(&g, &PartitionOptions::default().nparts(4));
+    assert_eq!(defaults, partition(&g, 4));
+
+    let opts = PartitionOptions::default()
+        .nparts(4)
+        .imbalance(1.02)
+        .refine_passes(3)
+        .coarsen_threshold(40)
+        .ntrials(3)
+        .seed(5);
+    let (cut, part) = partition_with_options(&g, &opts);
+    assert_valid_partition(&part, g.n, 4);
+    assert_eq!(cut, g.edge_cut(&part));
+    assert_eq!(part, opts.partition(&g).part);
+}
+
+#[test]
+fn detailed_result_matches_tuple_api() {
+    let g = common::grid(10, 10);
+    let result = metis_rs::partition_detailed(&g, 4);
+    assert_eq!(result.stats.part_weights.iter().sum::<i64>(), 100);
+    assert!(result.stats.imbalance >= 1.0);
+    assert_eq!(
+        result.stats.boundary_vertices,
+        g.boundary_vertices(&result.part).len()
+    );
+    let tuple: (i64, Vec<usize>) = result.into();
+    assert_eq!(tuple, partition(&g, 4));
+}
+
+#[test]
+fn try_partition_reports_malformed_input() {
+    use metis_rs::{try_partition, GraphError, PartitionError};
+
+    let g = common::path(6);
+    assert_eq!(try_partition(&g, 2).unwrap(), partition(&g, 2));
+    assert_eq!(try_partition(&g, 0), Err(PartitionError::ZeroParts));
+
+    let mut bad = g.clone();
+    bad.adjncy[0] = 9;
+    assert_eq!(
+        try_partition(&bad, 2),
+        Err(PartitionError::Graph(GraphError::NeighborOutOfRange {
+            vertex: 0,
+            neighbor: 9
+        }))
+    );
+
+    let mut bad = g.clone();
+    bad.adjwgt = vec![1; 3];
+    assert!(matches!(
+        try_partition(&bad, 2),
+        Err(PartitionError::Graph(GraphError::AdjwgtLength { .. }))
+    ));
+
+    let mut bad = g.clone();
+    bad.xadj.swap(1, 2);
+    assert!(matches!(
+        try_partition(&bad, 2),
+        Err(PartitionError::Graph(GraphError::XadjNotMonotonic {
+            index: 1
+        }))
+    ));
+
+    // 0 -> 1 without 1 -> 0
+    let one_way = Graph::new(2, vec![0, 1, 1], vec![1]);
+    assert_eq!(try_partition(&one_way, 2), Err(PartitionError::Asymmetric));
+}
+
+#[test]
+fn recursive_bisection_does_not_compound_imbalance() {
+    let g = common::grid(30, 30);
+    for nparts in [3, 5, 7, 13] {
+        let (_, part) = partition(&g, nparts);
+        assert_valid_partition(&part, g.n, nparts);
+        // Parts may reach ceil(1.05 * average) vertices.
+        let bound = (g.n as f64 * 1.05 / nparts as f64).ceil() as i64;
+        let weights = common::part_weights(&g, &part, nparts);
+        assert!(
+            weights.iter().all(|&w| w <= bound),
+            "{} parts: {:?}",
+            nparts,
+            weights
+        );
+    }
+}
+
+#[test]
+fn odd_part_counts_split_in_proportion() {
+    use metis_rs::partition::initial_partition;
+
+    // Three parts split 1:2 at the top level, not 1:1 with the second half
+    // split again into quarters.
+    let g = common::grid(30, 30);
+    for nparts in [3, 5, 6, 7, 11] {
+        let part = initial_partition(&g, nparts);
+        let bound = (g.n as f64 * 1.05 / nparts as f64).ceil() as i64;
+        let weights = common::part_weights(&g, &part, nparts);
+        assert!(
+            weights.iter().all(|&w| w <= bound),
+            "{} parts: {:?}",
+            nparts,
+            weights
+        );
+    }
+}
+
+#[test]
+fn uneven_targets_respect_the_imbalance_bound() {
+    use metis_rs::{InitialMethod, PartitionConfig};
+
+    let g = common::grid(20, 20);
+    let tpwgts = [0.5, 0.3, 0.2];
+    for method in [
+        InitialMethod::RecursiveBisection,
+        InitialMethod::GreedyKway,
+        InitialMethod::Spectral,
+        InitialMethod::Bubble,
+    ] {
+        let result = PartitionConfig::default()
+            .nparts(3)
+            .tpwgts(&tpwgts)
+            .initial_method(method)
+            .partition(&g);
+        for (k, &w) in result.stats.part_weights.iter().enumerate() {
+            let bound = (tpwgts[k] * g.n as f64 * 1.05).ceil() as i64;
+            assert!(w <= bound, "{:?}: part {} has weight {}", method, k, w);
+        }
+    }
+}
+
+/// 12x12 grid whose second constraint is concentrated in the left columns,
+/// so balancing it pulls against a clean split of the first.
+fn skewed_two_constraint_grid() -> Graph {
+    let g = common::grid(12, 12);
+    let vwgt_multi: Vec<Vec<i64>> = (0..g.n)
+        .map(|u| vec![1, if u % 12 < 3 { 6 } else { 1 }])
+        .collect();
+    g.with_vwgt_multi(vwgt_multi)
+}
+
+fn constraint_weights(g: &Graph, part: &[usize], nparts: usize, c: usize) -> Vec<i64> {
+    let mut weights = vec![0i64; nparts];
+    for u in 0..g.n {
+        weights[part[u]] += g.constraint_weight(u, c);
+    }
+    weights
+}
+
+#[test]
+fn ubvec_bounds_each_constraint_separately() {
+    let g = skewed_two_constraint_grid();
+    let ubvec = [1.05, 1.5];
+    let tight = metis_rs::PartitionConfig::default().nparts(4).partition(&g);
+    let loose = metis_rs::PartitionConfig::default()
+        .nparts(4)
+        .ubvec(&ubvec)
+        .partition(&g);
+    assert_valid_partition(&loose.part, g.n, 4);
+    for (c, ub) in ubvec.iter().enumerate() {
+        let weights = constraint_weights(&g, &loose.part, 4, c);
+        let total: i64 = weights.iter().sum();
+        let bound = (total as f64 * ub / 4.0).ceil() as i64;
+        assert!(
+            weights.iter().all(|&w| w <= bound),
+            "constraint {} over {}: {:?}",
+            c,
+            bound,
+            weights
+        );
+    }
+    // Room on the second constraint is spent on a smaller cut.
+    assert!(loose.edge_cut < tight.edge_cut);
+}
+
+#[test]
+#[should_panic(expected = "ubvec must have one entry per constraint")]
+fn ubvec_must_match_constraints() {
+    let g = skewed_two_constraint_grid();
+    metis_rs::PartitionConfig::default()
+        .ubvec(&[1.05])
+        .partition(&g);
+}
+
+#[test]
+fn contiguous_option_repairs_split_parts() {
+    use metis_rs::refine::refine_kway;
+
+    // Strips of a 12x12 grid with part 0 split across the top and the
+    // bottom, balanced so that refinement alone has no reason to merge it.
+    let g = common::grid(12, 12);
+    let mut part: Vec<usize> = (0..g.n).map(|u| [0, 1, 1, 2, 2, 0][u / 12 / 2]).collect();
+    assert!(!g.part_is_contiguous(&part, 3));
+    let config = metis_rs::PartitionConfig::default()
+        .nparts(3)
+        .contiguous(true);
+    let cut = refine_kway(&g, &mut part, 3, &config);
+    assert_valid_partition(&part, g.n, 3);
+    assert!(g.part_is_contiguous(&part, 3));
+    assert_eq!(cut, g.edge_cut(&part));
+
+    // The pipeline result is connected as well.
+    let h = common::grid(7, 15);
+    let result = config.partition(&h);
+    assert_valid_partition(&result.part, h.n, 3);
+    assert!(h.part_is_contiguous(&result.part, 3));
+}
+
+#[test]
+#[should_panic(expected = "contiguous partitions cannot have pinned vertices")]
+fn contiguous_option_rejects_pinned_vertices() {
+    let g = two_cliques();
+    metis_rs::PartitionConfig::default()
+        .contiguous(true)
+        .fixed(&[Some(0), None, None, None, None, Some(1)])
+        .partition(&g);
+}
+
+#[test]
+fn random_refinement_is_seeded() {
+    let g = common::grid(40, 40);
+    let config = metis_rs::PartitionConfig::default()
+        .nparts(8)
+        .refine_mode(metis_rs::RefineMode::Random);
+    let a = config.clone().seed(3).partition(&g);
+    assert_valid_partition(&a.part, g.n, 8);
+    assert_eq!(a, config.clone().seed(3).partition(&g));
+    let best_first = metis_rs::PartitionConfig::default()
+        .nparts(8)
+        .seed(3)
+        .partition(&g);
+    assert!(a.edge_cut <= best_first.edge_cut * 11 / 10);
+    assert!(a.stats.imbalance <= 1.05 + 1e-9);
+}
+
+/// 40x40 grid with edge weights 1 to 5, which gives refinement work to do
+/// at every level.
+fn uneven_grid() -> Graph {
+    let mut edges = Vec::new();
+    for u in 0..1600 {
+        let w = ((u * 7919) % 5) as i64 + 1;
+        if u % 40 + 1 < 40 {
+            edges.push((u, u + 1, w));
+        }
+        if u + 40 < 1600 {
+            edges.push((u, u + 40, w));
+        }
+    }
+    Graph::from_edges(1600, &edges)
+}
+
+#[test]
+fn refine_levels_skip_intermediate_refinement() {
+    use metis_rs::RefineLevels;
+    let g = uneven_grid();
+    let config = metis_rs::PartitionConfig::default().nparts(8);
+    let refined = |levels| {
+        let mut seen = Vec::new();
+        let result =
+            config
+                .clone()
+                .refine_levels(levels)
+                .partition_observed(&g, &mut |level, _, _, _| {
+                    if seen.last() != Some(&level) {
+                        seen.push(level);
+                    }
+                });
+        (result, seen)
+    };
+
+    let (all, seen) = refined(RefineLevels::All);
+    assert_eq!(all, config.partition(&g));
+    let coarsest = seen[0];
+    assert!(coarsest >= 4, "{} levels", coarsest);
+    assert_eq!(seen, (0..=coarsest).rev().collect::<Vec<_>>());
+
+    for (levels, expected) in [
+        (
+            RefineLevels::EveryOther,
+            (0..=coarsest)
+                .rev()
+                .filter(|&l| l == 0 || (coarsest - l) % 2 == 0)
+                .collect::<Vec<_>>(),
+        ),
+        (RefineLevels::FinestOnly, vec![0]),
+        (
+            RefineLevels::CoarsestAndFinest(2),
+            vec![coarsest, coarsest - 1, 0],
+        ),
+    ] {
+        let (result, seen) = refined(levels);
+        assert_eq!(seen, expected, "{:?}", levels);
+        assert_valid_partition(&result.part, g.n, 8);
+        assert!(result.stats.imbalance <= 1.05 + 1e-9, "{:?}", levels);
+    }
+}
+
+#[test]
+fn flow_refinement_never_raises_the_cut() {
+    use metis_rs::refine::flow_refine;
+
+    for (g, nparts) in [
+        (uneven_grid(), 8),
+        (uneven_grid(), 3),
+        (common::grid(24, 24), 4),
+    ] {
+        let config = metis_rs::PartitionConfig::default().nparts(nparts);
+        let fm = config.clone().partition(&g);
+        let mut part = fm.part.clone();
+        flow_refine(&g, &mut part, nparts, 1.05);
+        assert_valid_partition(&part, g.n, nparts);
+        assert!(g.edge_cut(&part) <= fm.edge_cut, "{} parts", nparts);
+        assert!(g.partition_stats(&part, nparts).imbalance <= 1.05 + 1e-9);
+
+        let flow = config.flow_refinement(true).partition(&g);
+        assert_valid_partition(&flow.part, g.n, nparts);
+        assert!(flow.stats.imbalance <= 1.05 + 1e-9, "{} parts", nparts);
+    }
+}
+
+#[test]
+fn coarsening_schemes_partition_within_balance() {
+    use metis_rs::{CoarsenScheme, PartitionConfig};
+
+    // Column 0 of the 20x20 grid holds over a third of the total weight.
+    let vwgt = (0..400).map(|u| if u % 20 == 0 { 10 } else { 1 }).collect();
+    let heavy_column = common::grid(20, 20).with_vwgt(vwgt);
+    let uneven = uneven_grid();
+    let schemes = |nparts| PartitionConfig::default().nparts(nparts);
+    let cases = [
+        (
+            &uneven,
+            8,
+            schemes(8)
+                .coarsen_scheme(CoarsenScheme::SortedHeavyEdge)
+                .seed(5),
+        ),
+        (
+            &uneven,
+            8,
+            schemes(8).coarsen_scheme(CoarsenScheme::Cluster),
+        ),
+        (
+            &uneven,
+            8,
+            schemes(8).coarsen_scheme(CoarsenScheme::GlobalPath),
+        ),
+        (&heavy_column, 4, schemes(4).max_coarse_weight(0.25)),
+    ];
+    for (g, nparts, config) in cases {
+        let result = config.partition(g);
+        assert_valid_partition(&result.part, g.n, nparts);
+        assert!(result.stats.imbalance <= 1.05 + 1e-9, "{:?}", config);
+        assert_eq!(result, config.partition(g), "{:?}", config);
+        let plain = PartitionConfig::default().nparts(nparts).partition(g);
+        assert!(
+            result.edge_cut <= plain.edge_cut * 11 / 10,
+            "{:?}: cut {} vs {}",
+            config,
+            result.edge_cut,
+            plain.edge_cut
+        );
+    }
+}
+
+#[test]
+fn initial_bisection_is_refined_before_selection() {
+    let g = common::weighted_grid(8, 8, 9, 99);
+
+    let part = metis_rs::partition::initial_bisection(&g);
+    assert_valid_partition(&part, g.n, 2);
+    let size0 = part.iter().filter(|&&p| p == 0).count();
+    assert!((31..=33).contains(&size0), "part 0 has {} vertices", size0);
+    // The chosen candidate already went through FM, which finds nothing
+    // left to improve.
+    let mut refined = part.clone();
+    metis_rs::refine::fm_refine_2way(&g, &mut refined, 10);
+    assert_eq!(g.edge_cut(&refined), g.edge_cut(&part));
+}
+
+#[test]
+fn spectral_bisection_cuts_a_long_grid_across() {
+    use metis_rs::partition::spectral_bisection;
+
+    // The Fiedler vector of a 40x10 grid runs along its long side, so the
+    // weighted median split is the optimal 10-edge cut.
+    let g = common::grid(40, 10);
+    let part = spectral_bisection(&g);
+    assert_valid_partition(&part, g.n, 2);
+    assert_eq!(g.edge_cut(&part), 10);
+    assert_eq!(common::part_weights(&g, &part, 2), vec![200, 200]);
+
+    // Coarse graphs carry vertex and edge weights; the split stays balanced.
+    let mut edges = Vec::new();
+    for u in 0..60 {
+        if u % 10 < 9 {
+            edges.push((u, u + 1, 1 + (u % 3) as i64));
+        }
+        if u + 10 < 60 {
+            edges.push((u, u + 10, 2));
+        }
+    }
+    let vwgt = (0..60).map(|u| 1 + (u % 4) as i64).collect();
+    let g = Graph::from_edges(60, &edges).with_vwgt(vwgt);
+    let part = spectral_bisection(&g);
+    let weights = common::part_weights(&g, &part, 2);
+    let bound = (weights.iter().sum::<i64>() as f64 * 0.5 * 1.05).ceil() as i64;
+    assert!(weights.iter().all(|&w| w <= bound), "{:?}", weights);
+}
+
+#[test]
+fn ncuts_keeps_the_best_initial_partition() {
+    use metis_rs::{InitialMethod, PartitionConfig};
+
+    let g = common::grid(16, 24);
+    let config = PartitionConfig::default().nparts(8);
+    let single = config.clone().partition(&g);
+    assert_eq!(config.clone().ncuts(1).partition(&g).part, single.part);
+    let several = config.clone().ncuts(8).partition(&g);
+    assert_valid_partition(&several.part, g.n, 8);
+    assert!(several.edge_cut < single.edge_cut);
+    assert_eq!(config.clone().ncuts(8).partition(&g).part, several.part);
+
+    let g = common::grid(12, 12);
+    let greedy = PartitionConfig::default().initial_method(InitialMethod::GreedyKway);
+    let single = greedy.clone().partition(&g);
+    let several = greedy.ncuts(4).partition(&g);
+    assert!(several.edge_cut < single.edge_cut);
+    assert!(several.stats.imbalance <= 1.05);
+}
+
+#[test]
+fn tiny_bisections_are_optimal() {
+    use metis_rs::partition::initial_bisection;
+
+    let mut next = common::xorshift(99);
+    for _ in 0..6 {
+        // Random 12-vertex graph, each pair joined with probability 1/3
+        let mut edges = Vec::new();
+        for u in 0..12 {
+            for v in u + 1..12 {
+                if next().is_multiple_of(3) {
+                    edges.push((u, v, (next() % 5) as i64 + 1));
+                }
+            }
+        }
+        let g = Graph::from_edges(12, &edges);
+        // Each side may hold ceil(1.05 * 6) = 7 vertices.
+        let best = (1u32..(1 << 12) - 1)
+            .filter(|m| (5..=7).contains(&m.count_ones()))
+            .map(|m| {
+                let part: Vec<usize> = (0..12).map(|u| (m >> u & 1) as usize).collect();
+                g.edge_cut(&part)
+            })
+            .min()
+            .unwrap();
+        let part = initial_bisection(&g);
+        assert_eq!(g.edge_cut(&part), best);
+        assert!(common::part_weights(&g, &part, 2).iter().all(|&w| w <= 7));
+    }
+}
+
+#[test]
+fn bubble_initial_partition_grows_compact_parts() {
+    use metis_rs::partition::{initial_partition_bubble, initial_partition_greedy};
+
+    let g = common::grid(20, 20);
+    let part = initial_partition_bubble(&g, 4);
+    assert_valid_partition(&part, g.n, 4);
+    assert!(g.part_is_contiguous(&part, 4));
+    let weights = common::part_weights(&g, &part, 4);
+    assert!(
+        weights.iter().all(|&w| (90..=110).contains(&w)),
+        "{:?}",
+        weights
+    );
+    assert!(g.edge_cut(&part) < g.edge_cut(&initial_partition_greedy(&g, 4)));
+
+    // Vertices no center reaches still get a part.
+    let g = Graph::from_edges(
+        7,
+        &[
+            (0, 1, 1),
+            (1, 2, 1),
+            (2, 0, 1),
+            (3, 4, 1),
+            (4, 5, 1),
+            (5, 3, 1),
+        ],
+    );
+    let part = initial_partition_bubble(&g, 2);
+    assert_valid_partition(&part, g.n, 2);
+    assert_eq!(g.edge_cut(&part), 0);
+}
+
+#[test]
+fn pseudo_peripheral_seeds_split_a_strip_across() {
+    use metis_rs::{PartitionConfig, SeedStrategy};
+
+    // 4x150 strip with scrambled vertex IDs, so vertex 0 sits mid-strip.
+    let (rows, cols) = (4, 150);
+    let n = rows * cols;
+    let id = |u: usize| (u * 7919 + 13) % n;
+    let mut edges = Vec::new();
+    for u in 0..n {
+        if u % cols + 1 < cols {
+            edges.push((id(u), id(u + 1), 1));
+        }
+        if u + cols < n {
+            edges.push((id(u), id(u + cols), 1));
+        }
+    }
+    let g = Graph::from_edges(n, &edges);
+    // Bisect the whole graph from a single seed with no refinement.
+    let config = |strategy| {
+        PartitionConfig::default()
+            .nparts(2)
+            .coarsen_threshold(n)
+            .coarsen_per_part(n)
+            .refine_passes(0)
+            .bisection_seeds(1)
+            .seed_strategy(strategy)
+    };
+    let peripheral = config(SeedStrategy::PseudoPeripheral).partition(&g);
+    assert_eq!(peripheral.edge_cut, rows as i64);
+    assert!(config(SeedStrategy::Spread).partition(&g).edge_cut > peripheral.edge_cut);
+
+    // Any seed count works with every strategy.
+    for strategy in [
+        SeedStrategy::Spread,
+        SeedStrategy::Random,
+        SeedStrategy::PseudoPeripheral,
+    ] {
+        for count in [1, 3, 12] {
+            let result = PartitionConfig::default()
+                .nparts(4)
+                .bisection_seeds(count)
+                .seed_strategy(strategy)
+                .partition(&g);
+            assert_valid_partition(&result.part, n, 4);
+        }
+    }
+    // The defaults are seven spread seeds.
+    let explicit = PartitionConfig::default()
+        .nparts(4)
+        .bisection_seeds(7)
+        .seed_strategy(SeedStrategy::Spread)
+        .partition(&g);
+    assert_eq!(
+        explicit.part,
+        PartitionConfig::default().nparts(4).partition(&g).part
+    );
+}
+
+#[test]
+fn reused_workspace_gives_the_same_partitions() {
+    use metis_rs::{
+        partition_with_workspace, CoarsenScheme, PartitionConfig, RefineMode, Workspace,
+    };
+
+    let mut workspace = Workspace::new();
+    let configs = [
+        PartitionConfig::default().nparts(8),
+        PartitionConfig::default().nparts(3).seed(5).ntrials(3),
+        PartitionConfig::default()
+            .nparts(6)
+            .coarsen_scheme(CoarsenScheme::SortedHeavyEdge)
+            .refine_mode(RefineMode::Rollback)
+            .ncuts(2),
+    ];
+    // Shrinking and growing graphs, so the arrays are reused at both sizes.
+    for g in [
+        common::grid(30, 30),
+        common::grid(8, 12),
+        common::grid(40, 25),
+    ] {
+        for config in &configs {
+            let reused = config.partition_with_workspace(&g, &mut workspace);
+            assert_eq!(reused, config.partition(&g));
+        }
+        let (cut, part) = partition_with_workspace(&g, 4, &mut workspace);
+        assert_eq!((cut, part), partition(&g, 4));
+    }
+}
@@ -0,0 +1,82 @@
+use metis_rs::quality::balance_summary;
+use metis_rs::Graph;
+
+#[test]
+fn balance_summary_reports_count_and_weight() {
+    // Path 0-1-2-3 with one heavy vertex.
+    let g = Graph::new(4, vec![0, 1, 3, 5, 6], vec![1, 0, 2, 1, 3, 2]).with_vwgt(vec![5, 1, 1, 1]);
+    let summary = balance_summary(&g, &[0, 1, 1, 1], 2);
+    assert_eq!(summary.max_count, 3);
+    assert_eq!(summary.min_count, 1);
+    assert_eq!(summary.max_weight, 5);
+    assert_eq!(summary.min_weight, 3);
+    assert!((summary.count_imbalance - 1.5).abs() < 1e-12);
+    assert!((summary.weight_imbalance - 1.25).abs() < 1e-12);
+}
+
+#[test]
+fn balance_summary_includes_empty_parts() {
+    let g = Graph::new(2, vec![0, 1, 2], vec![1, 0]);
+    let summary = balance_summary(&g, &[0, 0], 3);
+    assert_eq!(summary.min_count, 0);
+    assert_eq!(summary.min_weight, 0);
+    assert!((summary.count_imbalance - 3.0).abs() < 1e-12);
+}
+
+#[test]
+fn comm_volume_counts_each_neighbor_part_once() {
+    // Star: center 0 with leaves 1..=4, plus isolated vertex 5.
+    let g = Graph::new(6, vec![0, 4, 5, 6, 7, 8, 8], vec![1, 2, 3, 4, 0, 0, 0, 0]);
+    let part = [0, 1, 1, 2, 0, 2];
+    let stats = g.partition_stats(&part, 4);
+    assert_eq!(stats.edge_cut, 3);
+    // Center sees parts {1, 2}; leaves 1, 2, 3 each see part 0.
+    assert_eq!(stats.comm_volume, 5);
+    assert_eq!(stats.part_weights, vec![2, 2, 2, 0]);
+    assert!((stats.imbalance - 4.0 / 3.0).abs() < 1e-12);
+}
+
+#[test]
+fn partition_stats_on_empty_graph() {
+    let g = Graph::new(0, vec![0], vec![]);
+    let stats = g.partition_stats(&[], 2);
+    assert_eq!(stats.edge_cut, 0);
+    assert_eq!(stats.comm_volume, 0);
+    assert_eq!(stats.imbalance, 1.0);
+}
+
+#[test]
+fn contiguity_ignores_empty_parts_and_sees_split_ones() {
+    // Path 0-1-2-3
+    let g = Graph::new(4, vec![0, 1, 3, 5, 6], vec![1, 0, 2, 1, 3, 2]);
+    assert!(g.part_is_contiguous(&[0, 0, 1, 1], 3));
+    assert!(!g.part_is_contiguous(&[0, 1, 1, 0], 2));
+}
+
+#[test]
+fn boundary_vertices_and_cut_contributions() {
+    // Weighted path 0-1-2-3 plus isolated vertex 4.
+    let g = Graph::new(5, vec![0, 1, 3, 5, 6, 6], vec![1, 0, 2, 1, 3, 2])
+        .with_adjwgt(vec![2, 2, 7, 7, 3, 3]);
+    let part = [0, 0, 1, 1, 0];
+    assert_eq!(g.boundary_vertices(&part), vec![1, 2]);
+    assert_eq!(
+        g.iter_boundary(&part).collect::<Vec<_>>(),
+        vec![(1, 7), (2, 7)]
+    );
+
+    // Interior and isolated vertices contribute nothing.
+    assert_eq!(g.vertex_cut_contribution(0, &part), 0);
+    assert_eq!(g.vertex_cut_contribution(3, &part), 0);
+    assert_eq!(g.vertex_cut_contribution(4, &part), 0);
+    let total: i64 = (0..g.n).map(|u| g.vertex_cut_contribution(u, &part)).sum();
+    assert_eq!(total, 2 * g.edge_cut(&part));
+}
+
+#[test]
+fn partition_stats_counts_boundary_vertices() {
+    // Path 0-1-2-3 plus isolated vertex 4.
+    let g = Graph::new(5, vec![0, 1, 3, 5, 6, 6], vec![1, 0, 2, 1, 3, 2]);
+    assert_eq!(g.partition_stats(&[0, 0, 1, 1, 1], 2).boundary_vertices, 2);
+    assert_eq!(g.partition_stats(&[0, 1, 0, 1, 0], 2).boundary_vertices, 4);
+}
@@ -0,0 +1,591 @@
+mod common;
+
+use common::grid;
+use metis_rs::partition::initial_partition_frozen;
+use metis_rs::refine::{
+    enforce_balance, enforce_contiguity, flow_refine, fm_refine, fm_refine_2way, fm_refine_fixed,
+    fm_refine_frozen, fm_refine_minconn, fm_refine_mode, fm_refine_objective,
+    fm_refine_with_importance, fm_refine_with_observer, refine_kway, RefineMode,
+};
+use metis_rs::{Graph, Objective, PartitionConfig};
+
+/// Cycle 0-1-...-7-0 plus an isolated heavy vertex 8.
+///
+/// Vertices 3 and 7 sit in part 2 between a part-0 and a part-1 neighbor,
+/// so moving either one has the same gain toward both parts.
+fn contested_cycle() -> Graph {
+    let mut xadj = vec![0usize];
+    let mut adjncy = Vec::new();
+    for u in 0..8 {
+        adjncy.push((u + 7) % 8);
+        adjncy.push((u + 1) % 8);
+        xadj.push(adjncy.len());
+    }
+    xadj.push(adjncy.len());
+    Graph::new(9, xadj, adjncy).with_vwgt(vec![1, 1, 1, 1, 1, 1, 1, 1, 4])
+}
+
+fn part_counts(part: &[usize], nparts: usize) -> Vec<usize> {
+    let mut counts = vec![0usize; nparts];
+    for &p in part {
+        counts[p] += 1;
+    }
+    counts
+}
+
+#[test]
+fn tied_gains_do_not_favor_low_part_ids() {
+    let g = contested_cycle();
+    let mut part = vec![0, 0, 0, 2, 1, 1, 1, 2, 2];
+    fm_refine(&g, &mut part, 3, 10);
+    let counts = part_counts(&part, 3);
+    assert_eq!(
+        counts[0], counts[1],
+        "parts 0/1 should split evenly: {:?}",
+        counts
+    );
+
+    // Swapping the labels of parts 0 and 1 must give the same sizes.
+    let mut swapped = vec![1, 1, 1, 2, 0, 0, 0, 2, 2];
+    fm_refine(&g, &mut swapped, 3, 10);
+    assert_eq!(part_counts(&swapped, 3), counts);
+}
+
+#[test]
+fn important_edge_is_kept_internal() {
+    // Path 0-1-2-3 with the middle edge (1, 2) marked as important.
+    let g = Graph::new(4, vec![0, 1, 3, 5, 6], vec![1, 0, 2, 1, 3, 2]);
+    let importance = [1.0, 1.0, 10.0, 10.0, 1.0, 1.0];
+
+    let mut plain = vec![0, 0, 1, 1];
+    fm_refine_with_importance(&g, &mut plain, 2, 10, None);
+    assert_ne!(plain[1], plain[2]);
+
+    let mut part = vec![0, 0, 1, 1];
+    fm_refine_with_importance(&g, &mut part, 2, 10, Some(&importance));
+    assert_eq!(part[1], part[2], "important edge was cut: {:?}", part);
+}
+
+#[test]
+#[should_panic(expected = "edge_importance entries must be finite and non-negative")]
+fn negative_importance_is_rejected() {
+    let g = Graph::new(2, vec![0, 1, 2], vec![1, 0]);
+    let mut part = vec![0, 1];
+    fm_refine_with_importance(&g, &mut part, 2, 10, Some(&[1.0, -1.0]));
+}
+
+/// Three triangles {0,1,2}, {3,4,5}, {6,7,8} chained by edges 2-3 and 5-6.
+fn triangle_chain() -> Graph {
+    Graph::new(
+        9,
+        vec![0, 2, 4, 7, 10, 12, 15, 18, 20, 22],
+        vec![
+            1, 2, 0, 2, 0, 1, 3, 2, 4, 5, 3, 5, 3, 4, 6, 5, 7, 8, 6, 8, 6, 7,
+        ],
+    )
+}
+
+#[test]
+fn frozen_part_is_left_untouched() {
+    let g = triangle_chain();
+    // Part 0 holds a poor mix of vertices that refinement would like to fix.
+    let initial = vec![0, 1, 1, 0, 2, 2, 2, 0, 1];
+    let mut part = initial.clone();
+    fm_refine_frozen(&g, &mut part, 3, 10, &[0]);
+    for u in 0..g.n {
+        assert_eq!(part[u] == 0, initial[u] == 0, "vertex {} crossed part 0", u);
+    }
+
+    let mut free = initial.clone();
+    fm_refine(&g, &mut free, 3, 10);
+    assert_ne!(
+        free, part,
+        "unfrozen refinement should move part-0 vertices"
+    );
+}
+
+#[test]
+fn pinned_vertices_are_never_moved() {
+    let g = triangle_chain();
+    // Vertex 3 belongs with {4, 5} and vertex 6 with {7, 8}, but both are
+    // pinned to the wrong side.
+    let mut fixed = vec![None; g.n];
+    fixed[3] = Some(0);
+    fixed[6] = Some(1);
+    let mut part = vec![0, 0, 0, 0, 1, 1, 1, 2, 2];
+    fm_refine_fixed(&g, &mut part, 3, 10, &fixed);
+    assert_eq!((part[3], part[6]), (0, 1));
+
+    let mut free = vec![0, 0, 0, 0, 1, 1, 1, 2, 2];
+    fm_refine(&g, &mut free, 3, 10);
+    assert!(
+        free[3] != 0 || free[6] != 1,
+        "refinement had nothing to fix"
+    );
+}
+
+#[test]
+fn initial_partition_keeps_frozen_assignments() {
+    let g = triangle_chain();
+    let current = vec![1, 1, 1, 0, 0, 0, 2, 2, 2];
+    let part = initial_partition_frozen(&g, 3, &current, &[0]);
+    for u in 0..g.n {
+        assert_eq!(part[u] == 0, current[u] == 0);
+    }
+    assert!(part.iter().all(|&p| p < 3));
+}
+
+#[test]
+#[should_panic(expected = "frozen part 3 out of range for 3 parts")]
+fn frozen_part_ids_are_checked() {
+    let g = triangle_chain();
+    let mut part = vec![0, 0, 0, 1, 1, 1, 2, 2, 2];
+    fm_refine_frozen(&g, &mut part, 3, 10, &[3]);
+}
+
+#[test]
+fn refinement_scales_to_large_grid() {
+    let g = grid(100, 100);
+    let initial: Vec<usize> = (0..g.n).map(|u| ((u * 7919) % 13) % 4).collect();
+    let mut part = initial.clone();
+    fm_refine(&g, &mut part, 4, 10);
+    assert!(g.edge_cut(&part) < g.edge_cut(&initial));
+}
+
+/// Checkerboard of 2x3 blocks on a grid with `cols` columns. On an 8x8 grid
+/// no single move reduces the cut.
+fn blocky_bisection(cols: usize, n: usize) -> Vec<usize> {
+    (0..n)
+        .map(|u| ((u / cols) / 2 + (u % cols) / 3) % 2)
+        .collect()
+}
+
+#[test]
+fn rollback_escapes_local_minimum() {
+    let g = grid(8, 8);
+    let start = blocky_bisection(8, g.n);
+
+    let mut greedy = start.clone();
+    fm_refine_mode(&g, &mut greedy, 2, 10, RefineMode::Greedy);
+    assert_eq!(g.edge_cut(&greedy), g.edge_cut(&start));
+
+    let mut rollback = start.clone();
+    fm_refine_mode(&g, &mut rollback, 2, 10, RefineMode::Rollback);
+    assert!(
+        g.edge_cut(&rollback) < g.edge_cut(&greedy),
+        "rollback cut {} should beat greedy cut {}",
+        g.edge_cut(&rollback),
+        g.edge_cut(&greedy)
+    );
+    let counts = part_counts(&rollback, 2);
+    assert!(counts.iter().all(|&c| c <= 34), "unbalanced: {:?}", counts);
+}
+
+#[test]
+fn two_way_refiner_escapes_local_minimum() {
+    let g = grid(8, 8);
+    let start = blocky_bisection(8, g.n);
+    let mut part = start.clone();
+    fm_refine_2way(&g, &mut part, 10);
+    assert!(g.edge_cut(&part) < g.edge_cut(&start));
+    let counts = part_counts(&part, 2);
+    assert!(counts.iter().all(|&c| c <= 34), "unbalanced: {:?}", counts);
+}
+
+#[test]
+fn two_way_refiner_never_raises_the_cut() {
+    for (rows, cols) in [(10, 10), (7, 13), (20, 5)] {
+        let g = grid(rows, cols);
+        let start: Vec<usize> = (0..g.n).map(|u| (u / 3 + u / 7) % 2).collect();
+        let mut part = start.clone();
+        fm_refine_2way(&g, &mut part, 10);
+        assert!(g.edge_cut(&part) <= g.edge_cut(&start));
+        let bound = (g.n as f64 * 1.05 / 2.0).ceil() as usize;
+        let counts = part_counts(&part, 2);
+        assert!(
+            counts.iter().all(|&c| c <= bound),
+            "unbalanced: {:?}",
+            counts
+        );
+    }
+}
+
+#[test]
+fn two_way_refiner_evens_out_an_unbalanced_bisection() {
+    let g = grid(10, 10);
+    // Part 0 holds the top seven rows.
+    let mut part: Vec<usize> = (0..g.n).map(|u| usize::from(u >= 70)).collect();
+    fm_refine_2way(&g, &mut part, 10);
+    let counts = part_counts(&part, 2);
+    assert!(counts.iter().all(|&c| c <= 53), "unbalanced: {:?}", counts);
+}
+
+#[test]
+fn tabu_search_escapes_local_minimum() {
+    let g = grid(8, 8);
+    let start = blocky_bisection(8, g.n);
+    let tabu = RefineMode::Tabu {
+        tenure: 8,
+        iterations: 200,
+    };
+
+    let mut part = start.clone();
+    fm_refine_mode(&g, &mut part, 2, 10, tabu);
+    assert!(g.edge_cut(&part) < g.edge_cut(&start));
+    let counts = part_counts(&part, 2);
+    assert!(counts.iter().all(|&c| c <= 34), "unbalanced: {:?}", counts);
+
+    let mut again = start.clone();
+    fm_refine_mode(&g, &mut again, 2, 10, tabu);
+    assert_eq!(again, part);
+}
+
+#[test]
+fn greedy_mode_matches_fm_refine() {
+    let g = grid(10, 10);
+    let start: Vec<usize> = (0..g.n).map(|u| ((u * 7919) % 13) % 2).collect();
+    let mut a = start.clone();
+    fm_refine(&g, &mut a, 2, 10);
+    let mut b = start;
+    fm_refine_mode(&g, &mut b, 2, 10, RefineMode::Greedy);
+    assert_eq!(a, b);
+}
+
+#[test]
+fn sweep_modes_improve_cut_within_balance() {
+    let g = grid(30, 30);
+    let start: Vec<usize> = (0..g.n).map(|u| ((u * 7919) % 13) % 4).collect();
+    for mode in [
+        RefineMode::Random,
+        RefineMode::LabelPropagation,
+        RefineMode::Colored,
+    ] {
+        let mut part = start.clone();
+        fm_refine_mode(&g, &mut part, 4, 10, mode);
+        assert!(g.edge_cut(&part) < g.edge_cut(&start) / 2, "{:?}", mode);
+        let counts = part_counts(&part, 4);
+        assert!(
+            counts.iter().all(|&c| c <= 237),
+            "{:?} unbalanced: {:?}",
+            mode,
+            counts
+        );
+
+        let mut again = start.clone();
+        fm_refine_mode(&g, &mut again, 4, 10, mode);
+        assert_eq!(again, part, "{:?}", mode);
+    }
+}
+
+#[test]
+fn colored_mode_keeps_pinned_vertices() {
+    let g = grid(30, 30);
+    let start: Vec<usize> = (0..g.n).map(|u| ((u * 7919) % 13) % 4).collect();
+    let fixed: Vec<Option<usize>> = start
+        .iter()
+        .enumerate()
+        .map(|(u, &p)| (u % 10 == 0).then_some(p))
+        .collect();
+    let mut pinned = start.clone();
+    let config = PartitionConfig::default()
+        .nparts(4)
+        .refine_mode(RefineMode::Colored)
+        .fixed(&fixed);
+    refine_kway(&g, &mut pinned, 4, &config);
+    for u in (0..g.n).step_by(10) {
+        assert_eq!(pinned[u], start[u]);
+    }
+}
+
+#[test]
+fn label_propagation_evens_out_ties() {
+    // Vertex 3 is tied between its parts; only label propagation hands it
+    // to the lighter part 1.
+    let g = common::path(6);
+    let start = vec![0, 0, 0, 0, 1, 1];
+    let mut greedy = start.clone();
+    fm_refine(&g, &mut greedy, 2, 10);
+    assert_eq!(greedy, start);
+
+    let mut part = start.clone();
+    fm_refine_mode(&g, &mut part, 2, 10, RefineMode::LabelPropagation);
+    assert_eq!(part, vec![0, 0, 0, 1, 1, 1]);
+}
+
+#[test]
+fn volume_objective_prefers_lone_leaf_of_star() {
+    // Hub 0 with leaves 1..=9, plus isolated vertex 10 keeping part 2
+    // nonempty. Part 0 has room for one more vertex, and every leaf outside
+    // it gains 1 in cut by joining. Leaf 9 is the hub's only neighbor in
+    // part 2, so moving it also stops the hub from talking to part 2.
+    let edges: Vec<(usize, usize, i64)> = (1..=9).map(|v| (0, v, 1)).collect();
+    let g = Graph::from_edges(11, &edges);
+    let start = vec![0, 0, 0, 1, 1, 1, 1, 1, 1, 2, 2];
+
+    let mut by_cut = start.clone();
+    fm_refine_objective(&g, &mut by_cut, 3, 10, Objective::EdgeCut);
+    assert_eq!(by_cut[3], 0);
+
+    let mut by_volume = start.clone();
+    fm_refine_objective(&g, &mut by_volume, 3, 10, Objective::CommVolume);
+    assert_eq!(by_volume[9], 0);
+    assert_eq!(g.edge_cut(&by_volume), g.edge_cut(&by_cut));
+    assert_eq!(g.partition_stats(&by_cut, 3).comm_volume, 8);
+    assert_eq!(g.partition_stats(&by_volume, 3).comm_volume, 7);
+}
+
+#[test]
+fn volume_objective_weighs_vertex_sizes() {
+    // The star above with a free hub and a large leaf 3: moving leaf 3 now
+    // saves more volume than cutting the hub off from part 2.
+    let edges: Vec<(usize, usize, i64)> = (1..=9).map(|v| (0, v, 1)).collect();
+    let mut vsize = vec![1; 11];
+    vsize[0] = 0;
+    vsize[3] = 5;
+    let g = Graph::from_edges(11, &edges).with_vsize(vsize);
+    let mut part = vec![0, 0, 0, 1, 1, 1, 1, 1, 1, 2, 2];
+    assert_eq!(g.partition_stats(&part, 3).comm_volume, 11);
+
+    fm_refine_objective(&g, &mut part, 3, 10, Objective::CommVolume);
+    assert_eq!(part[3], 0);
+    assert_eq!(part[9], 2);
+    assert_eq!(g.partition_stats(&part, 3).comm_volume, 6);
+}
+
+#[test]
+fn volume_passes_strictly_reduce_volume() {
+    let g = grid(12, 12);
+    let mut part: Vec<usize> = (0..g.n).map(|u| ((u * 7919) % 13) % 3).collect();
+    let mut volume = g.partition_stats(&part, 3).comm_volume;
+    for _ in 0..20 {
+        let before = part.clone();
+        fm_refine_objective(&g, &mut part, 3, 1, Objective::CommVolume);
+        if part == before {
+            break;
+        }
+        let next = g.partition_stats(&part, 3).comm_volume;
+        assert!(next < volume, "volume went from {} to {}", volume, next);
+        volume = next;
+    }
+}
+
+#[test]
+fn enforce_contiguity_reattaches_stray_fragments() {
+    // Path 0-1-2-3-4-5-6 where part 0 is split into {0, 1} and {5}, and
+    // part 1 into {2, 3} and {6}.
+    let g = common::path(7);
+    let mut part = vec![0, 0, 1, 1, 2, 0, 1];
+    assert!(!g.part_is_contiguous(&part, 3));
+    enforce_contiguity(&g, &mut part, 3);
+    assert!(g.part_is_contiguous(&part, 3));
+    // 5 ties between parts 1 and 2 and joins 6 in part 1; the pair {5, 6}
+    // is then a stray of part 1 and joins part 2.
+    assert_eq!(part, vec![0, 0, 1, 1, 2, 2, 2]);
+}
+
+#[test]
+fn enforce_contiguity_picks_the_most_connected_part() {
+    // Vertex 4 is a stray of part 0 touching part 1 once and part 2 twice.
+    let g = Graph::from_edges(
+        6,
+        &[
+            (0, 1, 1),
+            (1, 2, 1),
+            (2, 3, 1),
+            (3, 4, 1),
+            (4, 5, 2),
+            (5, 0, 1),
+        ],
+    );
+    let mut part = vec![0, 0, 1, 1, 0, 2];
+    enforce_contiguity(&g, &mut part, 3);
+    assert_eq!(part, vec![0, 0, 1, 1, 2, 2]);
+    assert!(g.part_is_contiguous(&part, 3));
+}
+
+#[test]
+fn refined_grid_partition_can_be_made_contiguous() {
+    let g = grid(12, 12);
+    let (_, mut part) = metis_rs::partition(&g, 7);
+    enforce_contiguity(&g, &mut part, 7);
+    assert!(g.part_is_contiguous(&part, 7));
+    assert_eq!(
+        part.iter().collect::<std::collections::HashSet<_>>().len(),
+        7
+    );
+}
+
+#[test]
+fn observer_sees_every_pass() {
+    let g = grid(8, 8);
+    let mut part: Vec<usize> = (0..g.n).map(|u| u % 2).collect();
+    let mut expected = part.clone();
+    fm_refine(&g, &mut expected, 2, 10);
+
+    let mut reports = Vec::new();
+    fm_refine_with_observer(&g, &mut part, 2, 10, &mut |pass, cut, moves| {
+        reports.push((pass, cut, moves))
+    });
+    assert_eq!(part, expected);
+    assert!(!reports.is_empty());
+    for (i, &(pass, _, _)) in reports.iter().enumerate() {
+        assert_eq!(pass, i);
+    }
+    // Cuts never increase and the last pass either ran out or found nothing.
+    assert!(reports.windows(2).all(|w| w[1].1 <= w[0].1));
+    let &(last, cut, moves) = reports.last().unwrap();
+    assert!(moves == 0 || last == 9);
+    assert_eq!(cut, g.edge_cut(&part));
+    assert!(reports[0].2 > 0);
+}
+
+/// Number of pairs of distinct parts joined by at least one edge.
+fn adjacent_part_pairs(g: &Graph, part: &[usize]) -> usize {
+    let mut pairs = std::collections::HashSet::new();
+    for u in 0..g.n {
+        for &v in g.neighbors(u) {
+            if part[u] < part[v] {
+                pairs.insert((part[u], part[v]));
+            }
+        }
+    }
+    pairs.len()
+}
+
+#[test]
+fn minconn_refuses_to_join_separate_parts() {
+    // Triangles {0, 1, 2} and {7, 8, 9} in parts 0 and 2 hang off the path
+    // 3-4-5-6 in part 1. Vertex 3 gains 1 in cut by joining part 0, but
+    // its edge to 7 would then make parts 0 and 2 adjacent.
+    let g = Graph::from_edges(
+        10,
+        &[
+            (0, 1, 1),
+            (1, 2, 1),
+            (0, 2, 1),
+            (7, 8, 1),
+            (8, 9, 1),
+            (7, 9, 1),
+            (3, 0, 1),
+            (3, 1, 1),
+            (3, 7, 1),
+            (3, 4, 1),
+            (4, 5, 1),
+            (5, 6, 1),
+            (6, 9, 1),
+        ],
+    );
+    let start = vec![0, 0, 0, 1, 1, 1, 1, 2, 2, 2];
+    assert_eq!(adjacent_part_pairs(&g, &start), 2);
+
+    let mut plain = start.clone();
+    fm_refine(&g, &mut plain, 3, 10);
+    assert_eq!(plain[3], 0);
+    assert_eq!(adjacent_part_pairs(&g, &plain), 3);
+
+    let mut part = start.clone();
+    fm_refine_minconn(&g, &mut part, 3, 10);
+    assert_eq!(part, start);
+}
+
+#[test]
+fn enforce_balance_repairs_overweight_parts() {
+    let g = grid(10, 10);
+    // Part 0 holds the top seven rows.
+    let mut part: Vec<usize> = (0..g.n).map(|u| usize::from(u >= 70)).collect();
+    let mut stuck = part.clone();
+    fm_refine(&g, &mut stuck, 2, 10);
+    assert_eq!(part_counts(&stuck, 2), vec![70, 30]);
+
+    enforce_balance(&g, &mut part, 2, 1.05);
+    let counts = part_counts(&part, 2);
+    assert!(counts.iter().all(|&c| c <= 53), "unbalanced: {:?}", counts);
+    // Boundary vertices move first, so the cut stays close to a straight
+    // line across the grid.
+    assert!(g.edge_cut(&part) <= 12, "cut {}", g.edge_cut(&part));
+}
+
+#[test]
+fn refine_kway_improves_an_imbalanced_external_partition() {
+    let g = grid(12, 12);
+    // Stripes of uneven width: 3, 2, 1 and 6 rows.
+    let mut part: Vec<usize> = (0..g.n)
+        .map(|u| match u / 12 {
+            0..=2 => 0,
+            3..=4 => 1,
+            5 => 2,
+            _ => 3,
+        })
+        .collect();
+    let mut balanced = part.clone();
+    enforce_balance(&g, &mut balanced, 4, 1.05);
+    let opts = PartitionConfig::default().imbalance(1.05);
+
+    let cut = refine_kway(&g, &mut part, 4, &opts);
+    assert_eq!(cut, g.edge_cut(&part));
+    let counts = part_counts(&part, 4);
+    assert!(counts.iter().all(|&c| c <= 38), "unbalanced: {:?}", counts);
+    // Refinement after the repair wins back some of the cut it cost.
+    assert!(cut < g.edge_cut(&balanced), "cut {}", cut);
+}
+
+#[test]
+fn min_improvement_stops_refinement_early() {
+    let g = grid(30, 30);
+    let start: Vec<usize> = (0..g.n).map(|u| ((u * 7919) % 13) % 4).collect();
+    let refine = |fraction: f64| {
+        let mut part = start.clone();
+        let opts = PartitionConfig::default().min_improvement(fraction);
+        let cut = refine_kway(&g, &mut part, 4, &opts);
+        assert_eq!(cut, g.edge_cut(&part));
+        cut
+    };
+    // Every pass gains less than the whole cut, so only one pass runs.
+    let (full, one_pass) = (refine(0.0), refine(1.0));
+    assert!(full < one_pass, "{} vs {}", full, one_pass);
+    assert!(one_pass < g.edge_cut(&start));
+}
+
+#[test]
+fn refine_kway_keeps_pinned_vertices() {
+    let g = grid(8, 8);
+    let mut part = vec![0usize; g.n];
+    let mut fixed = vec![None; g.n];
+    fixed[0] = Some(1);
+    fixed[63] = Some(0);
+    let opts = PartitionConfig::default().fixed(&fixed);
+
+    refine_kway(&g, &mut part, 2, &opts);
+    assert_eq!((part[0], part[63]), (1, 0));
+    let counts = part_counts(&part, 2);
+    assert!(counts.iter().all(|&c| c <= 34), "unbalanced: {:?}", counts);
+}
+
+/// Bisection of a 10x10 grid along column 5, with the boundary shifted one
+/// column left on rows 0-1, 4-5 and 8-9 and one column right on the rest.
+fn toothed_bisection() -> Vec<usize> {
+    (0..100)
+        .map(|u| {
+            let (r, c) = (u / 10, u % 10);
+            let edge = if (r / 2) % 2 == 0 { 4 } else { 6 };
+            usize::from(c >= edge)
+        })
+        .collect()
+}
+
+#[test]
+fn flow_refinement_straightens_a_toothed_boundary() {
+    let g = grid(10, 10);
+    let start = toothed_bisection();
+    assert_eq!(g.edge_cut(&start), 18);
+
+    // Every corner of a tooth has gain 0, so FM is stuck.
+    let mut fm = start.clone();
+    fm_refine(&g, &mut fm, 2, 10);
+    assert_eq!(g.edge_cut(&fm), 18);
+
+    let mut part = start.clone();
+    flow_refine(&g, &mut part, 2, 1.2);
+    assert_eq!(g.edge_cut(&part), 10);
+    assert!(part_counts(&part, 2).iter().all(|&c| c <= 60));
+}